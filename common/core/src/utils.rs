@@ -25,6 +25,11 @@ pub fn base64_encode(bytes: Vec<u8>) -> String {
     general_purpose::STANDARD.encode(bytes)
 }
 
+/// 将Base64字符串解码为字节向量
+pub fn base64_decode(text: &str) -> Result<Vec<u8>, String> {
+    general_purpose::STANDARD.decode(text).map_err(|err| err.to_string())
+}
+
 
 /// 将serde_json::Map转换为std::collections::HashMap
 ///
@@ -37,6 +37,12 @@ pub struct GlobalEnv {
     pub cache: Cache,
     // jwt配置
     pub jwt: Jwt,
+    // restful接口配置
+    #[serde(default)]
+    pub restful: Restful,
+    // 数据库配置
+    #[serde(default)]
+    pub database: Database,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -51,3 +57,295 @@ pub struct Jwt {
     // jwt expire hour
     pub expire_hour: u32,
 }
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Restful {
+    // @explain 调试配置
+    #[serde(default)]
+    pub explain: Explain,
+    // 行级安全策略配置
+    #[serde(default)]
+    pub rls: Rls,
+    // 库、表、字段的对外暴露配置
+    #[serde(default)]
+    pub exposure: Exposure,
+    // 按表、方法的角色权限
+    #[serde(default)]
+    pub access: AccessControl,
+    // GraphQL 接口配置
+    #[serde(default)]
+    pub graphql: Graphql,
+    // 查询开销限制
+    #[serde(default)]
+    pub limits: Limits,
+    // 按标签调用的查询模板
+    #[serde(default)]
+    pub templates: Templates,
+}
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Explain {
+    // 是否允许 @explain，生产环境应关闭
+    #[serde(default)]
+    pub enabled: bool,
+    // 允许使用 @explain 的角色，为空时不限制角色
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Rls {
+    // 不受策略限制的角色
+    #[serde(default)]
+    pub bypass_roles: Vec<String>,
+    // 策略列表，同一张表的多条策略以 AND 连接
+    #[serde(default)]
+    pub policies: Vec<Policy>,
+}
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Policy {
+    // 表名，schema.table
+    pub table: String,
+    // 行谓词，如 `user_id = $auth.sub`，多个条件以 and 连接
+    pub check: String,
+    // 生效的操作: select、insert、update、delete，为空时对所有操作生效
+    #[serde(default)]
+    pub actions: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Exposure {
+    // 未声明的库和表的访问级别
+    #[serde(default)]
+    pub default: Access,
+    // 库的访问级别
+    #[serde(default)]
+    pub schemas: Vec<SchemaExposure>,
+    // 表的访问级别及字段控制，优先于库的配置
+    #[serde(default)]
+    pub tables: Vec<TableExposure>,
+}
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SchemaExposure {
+    // 库名
+    pub name: String,
+    pub access: Access,
+}
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TableExposure {
+    // 表名，schema.table
+    pub name: String,
+    // 未配置时继承库的访问级别
+    #[serde(default)]
+    pub access: Option<Access>,
+    // 不返回、不可查询和写入的字段
+    #[serde(default)]
+    pub hidden_columns: Vec<String>,
+    // 可以查询但不可写入的字段
+    #[serde(default)]
+    pub readonly_columns: Vec<String>,
+}
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Access {
+    // 可读写
+    #[default]
+    Public,
+    // 只读
+    ReadOnly,
+    // 不可见，等同于不存在
+    Hidden,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AccessControl {
+    // 未声明的表及表中未声明的方法使用的权限
+    #[serde(default)]
+    pub default: AccessRule,
+    // 表的权限
+    #[serde(default)]
+    pub tables: Vec<TableAccessRule>,
+}
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TableAccessRule {
+    // 表名，schema.table
+    pub name: String,
+    #[serde(flatten)]
+    pub rule: AccessRule,
+}
+/// 各方法允许的角色，未登录的角色为 anonymous，None 表示继承上一级配置
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AccessRule {
+    #[serde(default)]
+    pub get: Option<Vec<String>>,
+    #[serde(default)]
+    pub head: Option<Vec<String>>,
+    #[serde(default)]
+    pub post: Option<Vec<String>>,
+    #[serde(default)]
+    pub put: Option<Vec<String>>,
+    #[serde(default)]
+    pub delete: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Graphql {
+    // 是否开启 /api/v1/graphql
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    // 是否允许内省查询
+    #[serde(default = "default_true")]
+    pub introspection: bool,
+    // 外键之外的关联关系
+    #[serde(default)]
+    pub relations: Vec<GraphqlRelation>,
+}
+impl Default for Graphql {
+    fn default() -> Self {
+        Self { enabled: true, introspection: true, relations: vec![] }
+    }
+}
+/// 关联关系，相当于在 `target` 节点上声明 `"target_column@": "table/column"`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GraphqlRelation {
+    // 表名，schema.table
+    pub table: String,
+    // 关联字段名
+    pub name: String,
+    // 本表字段
+    pub column: String,
+    // 关联的表，schema.table
+    pub target: String,
+    // 关联表的字段，是关联表的主键时返回单条数据，否则返回列表
+    pub target_column: String,
+}
+
+/// 单个查询请求的开销限制，0 表示不限制
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Limits {
+    // 节点最大嵌套深度，如 `[]/Comment[]/timeline.User` 为 3
+    #[serde(default = "default_max_depth")]
+    pub max_depth: usize,
+    // 最多的查询节点数
+    #[serde(default = "default_max_nodes")]
+    pub max_nodes: usize,
+    // 列表的 count 上限
+    #[serde(default = "default_max_count")]
+    pub max_count: usize,
+    // 一个请求所有节点合计查询的最大行数
+    #[serde(default = "default_max_rows")]
+    pub max_rows: usize,
+    // 每条查询语句的 MySQL MAX_EXECUTION_TIME，单位毫秒
+    #[serde(default = "default_max_execution_time")]
+    pub max_execution_time: u64,
+    // 批量新增 `schema.table[]` 一次最多的行数
+    #[serde(default = "default_max_insert_rows")]
+    pub max_insert_rows: usize,
+    // 批量新增时每条 INSERT 语句的行数
+    #[serde(default = "default_insert_batch_size")]
+    pub insert_batch_size: usize,
+    // 导出一次最多的行数，导出不受 `max_count` 限制
+    #[serde(default = "default_max_export_rows")]
+    pub max_export_rows: usize,
+}
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_depth: default_max_depth(),
+            max_nodes: default_max_nodes(),
+            max_count: default_max_count(),
+            max_rows: default_max_rows(),
+            max_execution_time: default_max_execution_time(),
+            max_insert_rows: default_max_insert_rows(),
+            insert_batch_size: default_insert_batch_size(),
+            max_export_rows: default_max_export_rows(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Templates {
+    // 开启后只能通过模板查询，不接受任意结构的 get、head、导出请求及 GraphQL 查询
+    #[serde(default)]
+    pub strict: bool,
+    // 不受 strict 限制的角色
+    #[serde(default)]
+    pub bypass_roles: Vec<String>,
+    // 模板列表
+    #[serde(default)]
+    pub requests: Vec<RequestTemplate>,
+}
+/// 查询模板，相当于 APIJSON 的 Request 表
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RequestTemplate {
+    // 标签，调用路径为 /api/v1/rest/get/{tag}.json
+    pub tag: String,
+    // 版本，请求中未指定 version 时使用最大的版本
+    #[serde(default = "default_version")]
+    pub version: u32,
+    // 允许的请求结构，节点中的 MUST、REFUSE 为必须、禁止的字段，其余的值由服务端固定
+    pub structure: serde_json::Value,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Database {
+    // 查询结果字段值的序列化配置
+    #[serde(default)]
+    pub decode: Decode,
+}
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Decode {
+    // DECIMAL 输出格式，string 保留精度，number 便于前端计算
+    #[serde(default)]
+    pub decimal: DecimalFormat,
+    // 时间字段输出的时区，如 UTC、+08:00，DATETIME 按该时区解释
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    // tinyint(1) 是否输出为布尔值
+    #[serde(default = "default_true")]
+    pub tinyint1_as_bool: bool,
+}
+impl Default for Decode {
+    fn default() -> Self {
+        Self { decimal: DecimalFormat::default(), timezone: default_timezone(), tinyint1_as_bool: true }
+    }
+}
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DecimalFormat {
+    #[default]
+    String,
+    Number,
+}
+
+fn default_timezone() -> String {
+    String::from("UTC")
+}
+fn default_true() -> bool {
+    true
+}
+fn default_version() -> u32 {
+    1
+}
+fn default_max_depth() -> usize {
+    5
+}
+fn default_max_nodes() -> usize {
+    20
+}
+fn default_max_count() -> usize {
+    100
+}
+fn default_max_rows() -> usize {
+    10000
+}
+fn default_max_execution_time() -> u64 {
+    5000
+}
+fn default_max_insert_rows() -> usize {
+    5000
+}
+fn default_insert_batch_size() -> usize {
+    500
+}
+fn default_max_export_rows() -> usize {
+    100000
+}
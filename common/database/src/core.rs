@@ -1,10 +1,15 @@
 use lazy_static::lazy_static;
 use std::sync::RwLock;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::collections::HashMap;
 use fnv::FnvHashMap;
-use sqlx::{mysql::{MySqlColumn, MySqlRow, MySqlPool}, Column, Row, TypeInfo, types::Decimal};
+use futures_util::{Stream, StreamExt};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, SecondsFormat, TimeZone};
+use sqlx::{mysql::{types::MySqlTime, MySqlColumn, MySqlRow, MySqlPool}, Column, Row, TypeInfo, ValueRef, types::Decimal};
 use common::utils::base64_encode;
-use crate::{ColumnMeta, DbMeta, TableMeta};
+use common::yaml::{DecimalFormat, Decode};
+use crate::{ColumnMeta, DbMeta, ForeignKeyMeta, TableMeta};
+use crate::param::SqlParam;
 
 lazy_static! {
     static ref DB_MAP: RwLock<FnvHashMap<String, DbMeta>> = RwLock::new(FnvHashMap::default());
@@ -12,38 +17,149 @@ lazy_static! {
     static ref TABLE_MAP: RwLock<FnvHashMap<String, TableMeta>> = RwLock::new(FnvHashMap::default());
 }
 
+// 元数据版本，每次加载完成后加一
+static META_VERSION: AtomicU64 = AtomicU64::new(0);
+
 // MySQL系统数据库列表`
 const MYSQL_SYS_DB: &[&str] = &["information_schema", "mysql", "performance_schema", "sys"];
 
 #[derive(Debug, Clone)]
 pub struct DBConn {
     pool: MySqlPool,
+    // 字段值序列化配置
+    decode: DecodeOptions,
+}
+
+/// 字段值序列化配置，由 `database.decode` 解析而来
+#[derive(Debug, Clone)]
+struct DecodeOptions {
+    decimal: DecimalFormat,
+    timezone: FixedOffset,
+    tinyint1_as_bool: bool,
+}
+
+impl DecodeOptions {
+    fn new(decode: &Decode) -> Result<Self, String> {
+        Ok(Self { decimal: decode.decimal, timezone: parse_timezone(&decode.timezone)?, tinyint1_as_bool: decode.tinyint1_as_bool })
+    }
+
+    fn decimal_value(&self, decimal: Decimal) -> serde_json::Value {
+        match self.decimal {
+            DecimalFormat::String => serde_json::Value::String(decimal.to_string()),
+            DecimalFormat::Number if decimal.scale() == 0 => decimal.to_string().parse::<i64>()
+                .map_or_else(|_| json_f64(decimal.to_string().parse().unwrap_or_default()), serde_json::Value::from),
+            DecimalFormat::Number => json_f64(decimal.to_string().parse().unwrap_or_default()),
+        }
+    }
+
+    /// 输出 ISO-8601，`utc` 表示值为UTC时间，否则视为配置时区的本地时间
+    fn datetime_value(&self, val: NaiveDateTime, utc: bool) -> serde_json::Value {
+        let datetime = if utc {
+            self.timezone.from_utc_datetime(&val)
+        } else {
+            self.timezone.from_local_datetime(&val).single().unwrap_or_else(|| self.timezone.from_utc_datetime(&val))
+        };
+        serde_json::Value::String(datetime.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+    }
+
+    /// 按字段类型转换写入的值: JSON 字段序列化，时间字段解析，布尔值在数值字段中为 1/0
+    fn column_param(&self, column: &ColumnMeta, value: &serde_json::Value) -> Result<SqlParam, String> {
+        let type_name = column.type_name.to_lowercase();
+        let numeric = ["tinyint", "smallint", "mediumint", "int", "bigint", "bit", "year", "decimal", "numeric", "float", "double", "real"]
+            .iter().any(|prefix| type_name.starts_with(prefix));
+        match value {
+            serde_json::Value::Null => Ok(SqlParam::Null),
+            _ if type_name.starts_with("json") => Ok(SqlParam::Json(value.clone())),
+            serde_json::Value::Array(_) | serde_json::Value::Object(_) => Err(format!("column '{}' expects a scalar value", column.field)),
+            serde_json::Value::Bool(val) if numeric => Ok(SqlParam::Int(*val as i64)),
+            serde_json::Value::String(val) if type_name.starts_with("datetime") || type_name.starts_with("timestamp") => {
+                self.parse_datetime(val, type_name.starts_with("timestamp"))
+                    .map(SqlParam::DateTime)
+                    .ok_or_else(|| format!("column '{}' expects a datetime, got '{}'", column.field, val))
+            }
+            serde_json::Value::Bool(_) | serde_json::Value::Number(_) if !numeric => Ok(SqlParam::String(value.to_string())),
+            _ => Ok(SqlParam::from(value)),
+        }
+    }
+
+    /// 解析 ISO-8601 或 `2024-05-01 10:30:00`，与 `datetime_value` 相反: `utc` 时转换为UTC时间，否则为配置时区的本地时间
+    fn parse_datetime(&self, val: &str, utc: bool) -> Option<NaiveDateTime> {
+        if let Ok(datetime) = DateTime::parse_from_rfc3339(val) {
+            return Some(if utc { datetime.naive_utc() } else { datetime.with_timezone(&self.timezone).naive_local() });
+        }
+        let local = ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"].iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(val, format).ok())
+            .or_else(|| NaiveDate::parse_from_str(val, "%Y-%m-%d").ok().and_then(|date| date.and_hms_opt(0, 0, 0)))?;
+        if utc {
+            self.timezone.from_local_datetime(&local).single().map(|datetime| datetime.naive_utc())
+        } else {
+            Some(local)
+        }
+    }
+}
+
+/// 解析时区配置: `UTC`、`Z`、`+08:00`、`-0530`
+pub fn parse_timezone(timezone: &str) -> Result<FixedOffset, String> {
+    let timezone = timezone.trim();
+    if timezone.is_empty() || timezone.eq_ignore_ascii_case("utc") || timezone.eq_ignore_ascii_case("z") {
+        return Ok(FixedOffset::east_opt(0).unwrap());
+    }
+    let invalid = || format!("invalid timezone '{}'", timezone);
+    let (sign, offset) = match timezone.split_at_checked(1) {
+        Some(("+", offset)) => (1, offset),
+        Some(("-", offset)) => (-1, offset),
+        _ => return Err(invalid()),
+    };
+    let (hour, minute) = offset.split_once(':').unwrap_or_else(|| offset.split_at(offset.len().min(2)));
+    let hour: i32 = hour.parse().map_err(|_| invalid())?;
+    let minute: i32 = if minute.is_empty() { 0 } else { minute.parse().map_err(|_| invalid())? };
+    if hour > 14 || minute > 59 {
+        return Err(invalid());
+    }
+    FixedOffset::east_opt(sign * (hour * 3600 + minute * 60)).ok_or_else(invalid)
+}
+
+// NaN/Infinity 无法表示为JSON数字
+fn json_f64(val: f64) -> serde_json::Value {
+    serde_json::Number::from_f64(val).map_or(serde_json::Value::Null, serde_json::Value::Number)
 }
 
 impl DBConn {
     pub async fn new(url: &str) -> Result<Self, sqlx::Error> {
+        Self::connect(url, &Decode::default()).await
+    }
+
+    /// 连接数据库并加载元数据，`decode` 为字段值序列化配置
+    pub async fn connect(url: &str, decode: &Decode) -> Result<Self, sqlx::Error> {
+        let decode = DecodeOptions::new(decode).map_err(|err| sqlx::Error::Configuration(err.into()))?;
         let pool = MySqlPool::connect(url).await?;
-        let mut ds = Self { pool };
+        let ds = Self { pool, decode };
         ds.init().await?;
         Ok(ds)
     }
 
-    async fn init(&mut self) -> Result<(), sqlx::Error> {
-        self.load_db().await?;
-
-        let db_names = {
-            let db_map = DB_MAP.read().unwrap();
-            db_map.keys().cloned().collect::<Vec<_>>()
-        };
+    async fn init(&self) -> Result<(), sqlx::Error> {
+        let db_names = self.load_db().await?;
 
-        for db_name in db_names {
-            self.load_db_table(&db_name).await?;
+        for db_name in &db_names {
+            self.load_db_table(db_name).await?;
         }
 
+        // 移除已删除的库、表
+        let mut db_tables = DB_TABLES_MAP.write().unwrap();
+        db_tables.retain(|schema, _| db_names.contains(schema));
+        TABLE_MAP.write().unwrap().retain(|_, table| db_tables.get(&table.schema).is_some_and(|names| names.contains(&table.name)));
+        META_VERSION.fetch_add(1, Ordering::SeqCst);
+
         Ok(())
     }
 
-    async fn load_db(&mut self) -> Result<(), sqlx::Error> {
+    /// 重新加载库、表元数据，建表或表结构变更后调用
+    pub async fn reload_meta(&self) -> Result<(), sqlx::Error> {
+        self.init().await
+    }
+
+    async fn load_db(&self) -> Result<Vec<String>, sqlx::Error> {
         let list_db_sql = "SELECT table_schema AS name,
                           ROUND(SUM(data_length + index_length) / 1024 / 1024, 2) AS size
                           FROM information_schema.tables
@@ -51,6 +167,7 @@ impl DBConn {
 
         let db_list = sqlx::query(list_db_sql).fetch_all(&self.pool).await?;
         let mut all_dbs = DB_MAP.write().unwrap();
+        let mut db_names = Vec::with_capacity(db_list.len());
 
         for db_row in db_list.iter() {
             let db_name: String = match db_row.try_get("name") {
@@ -68,16 +185,18 @@ impl DBConn {
             let db_size: Decimal = db_row.get("size");
             let size = db_size.to_string().parse::<f64>().unwrap_or(0.0);
 
+            db_names.push(db_name.clone());
             all_dbs.insert(
                 db_name.clone(),
                 DbMeta { name: db_name, size }
             );
         }
+        all_dbs.retain(|name, _| db_names.contains(name));
 
-        Ok(())
+        Ok(db_names)
     }
 
-    async fn load_db_table(&mut self, schema: &str) -> Result<(), sqlx::Error> {
+    async fn load_db_table(&self, schema: &str) -> Result<(), sqlx::Error> {
         let list_db_table_sql = format!(
             "SELECT TABLE_NAME, TABLE_COMMENT
              FROM information_schema.tables
@@ -87,10 +206,9 @@ impl DBConn {
 
         let tables = sqlx::query(&list_db_table_sql).fetch_all(&self.pool).await?;
         let mut table_name_list = Vec::with_capacity(tables.len());
+        let mut table_meta_list = Vec::with_capacity(tables.len());
 
         {
-            let mut all_tables = TABLE_MAP.write().unwrap();
-
             for table_row in tables {
                 let table_name: String = match table_row.try_get("TABLE_NAME") {
                     Ok(name) => name,
@@ -108,21 +226,26 @@ impl DBConn {
                 };
 
                 let columns = self.load_table_meta(schema, &table_name).await?;
+                let foreign_keys = self.load_foreign_keys(schema, &table_name).await?;
                 let table_meta = TableMeta {
                     schema: schema.to_string(),
                     name: table_name.clone(),
                     columns,
-                    comment: Some(table_comment)
+                    comment: Some(table_comment),
+                    foreign_keys,
                 };
 
                 let table_key = format!("{}.{}", schema, &table_name);
                 log::info!("mysql.table: {} loaded", &table_key);
 
-                all_tables.insert(table_key, table_meta);
+                table_meta_list.push((table_key, table_meta));
                 table_name_list.push(table_name);
             }
         }
 
+        // 加载完成后再写入，避免加载期间阻塞读取
+        TABLE_MAP.write().unwrap().extend(table_meta_list);
+
         let mut db_tables = DB_TABLES_MAP.write().unwrap();
         db_tables.insert(schema.to_string(), table_name_list);
 
@@ -139,7 +262,25 @@ impl DBConn {
         Ok(column_map)
     }
 
-    pub async fn query_one(&self, sql: &str, params: Vec<String>) -> Result<Option<HashMap<String, serde_json::Value>>, sqlx::Error> {
+    async fn load_foreign_keys(&self, schema: &str, table_name: &str) -> Result<Vec<ForeignKeyMeta>, sqlx::Error> {
+        let list_foreign_key_sql = "SELECT COLUMN_NAME, REFERENCED_TABLE_SCHEMA, REFERENCED_TABLE_NAME, REFERENCED_COLUMN_NAME
+             FROM information_schema.KEY_COLUMN_USAGE
+             WHERE TABLE_SCHEMA=? AND TABLE_NAME=? AND REFERENCED_TABLE_NAME IS NOT NULL";
+        let rows = sqlx::query(list_foreign_key_sql).bind(schema).bind(table_name).fetch_all(&self.pool).await?;
+        // information_schema 的字段可能以二进制返回
+        let get_string = |row: &MySqlRow, name: &str| -> String {
+            row.try_get::<String, _>(name)
+                .unwrap_or_else(|_| row.try_get::<Vec<u8>, _>(name).map(|bytes| String::from_utf8(bytes).unwrap_or_default()).unwrap_or_default())
+        };
+        Ok(rows.iter().map(|row| ForeignKeyMeta {
+            column: get_string(row, "COLUMN_NAME"),
+            referenced_schema: get_string(row, "REFERENCED_TABLE_SCHEMA"),
+            referenced_table: get_string(row, "REFERENCED_TABLE_NAME"),
+            referenced_column: get_string(row, "REFERENCED_COLUMN_NAME"),
+        }).collect())
+    }
+
+    pub async fn query_one(&self, sql: &str, params: Vec<SqlParam>) -> Result<Option<HashMap<String, serde_json::Value>>, sqlx::Error> {
         let sql = if !sql.to_lowercase().contains("limit") {
             format!("{} limit 1", sql)
         } else {
@@ -156,7 +297,7 @@ impl DBConn {
             let columns = row.columns();
             let mut record = HashMap::with_capacity(columns.len());
             for column in columns {
-                let value = Self::get_column_val(&row, column);
+                let value = self.get_column_val(&row, column);
                 record.insert(column.name().to_string(), value);
             }
             Ok(Some(record))
@@ -165,7 +306,7 @@ impl DBConn {
         }
     }
 
-    pub async fn query_list(&self, sql: &str, params: Vec<String>) -> Result<Vec<HashMap<String, serde_json::Value>>, sqlx::Error> {
+    pub async fn query_list(&self, sql: &str, params: Vec<SqlParam>) -> Result<Vec<HashMap<String, serde_json::Value>>, sqlx::Error> {
         let mut query = sqlx::query(sql);
         for param in params {
             query = query.bind(param);
@@ -176,7 +317,7 @@ impl DBConn {
         for row in rows.into_iter() {
             let mut record = HashMap::with_capacity(row.columns().len());
             for column in row.columns() {
-                let value = Self::get_column_val(&row, column);
+                let value = self.get_column_val(&row, column);
                 record.insert(column.name().to_string(), value);
             }
             results.push(record);
@@ -185,69 +326,96 @@ impl DBConn {
         Ok(results)
     }
 
-    fn get_column_val(row: &MySqlRow, column: &MySqlColumn) -> serde_json::Value {
-        match column.type_info().name() {
-            "BIGINT" | "INT" => row.try_get::<i64, _>(column.name()).map_or(serde_json::Value::Null, serde_json::Value::from),
-            "DATETIME" | "TIMESTAMP" | "DATE" | "TIME" => row.try_get::<chrono::NaiveDateTime, _>(column.name())
-                .map_or(serde_json::Value::Null, |val| serde_json::Value::String(val.to_string())),
-            "TINYTEXT" | "TEXT" | "MEDIUMTEXT" | "LONGTEXT" => row.try_get::<String, _>(column.name())
-                .map_or(serde_json::Value::Null, serde_json::Value::String),
-            "JSON" => row.try_get::<serde_json::Value, _>(column.name())
-                .unwrap_or(serde_json::Value::Null),
-            "BLOB" | "TINYBLOB" | "MEDIUMBLOB" | "LONGBLOB" | "VARBINARY" | "BINARY" => {
-                row.try_get::<Vec<u8>, _>(column.name()).map_or_else(
-                    |_| serde_json::Value::Null,
-                    |bytes| match String::from_utf8(bytes.clone()) {
-                        Ok(s) => serde_json::Value::String(s),
-                        Err(_) => serde_json::Value::String(base64_encode(bytes)),
-                    }
-                )
+    /// 流式查询: 逐行读取结果，不把全部结果载入内存，用于导出等大结果集
+    pub fn query_stream<'a>(&'a self, sql: &'a str, params: Vec<SqlParam>) -> impl Stream<Item = Result<HashMap<String, serde_json::Value>, sqlx::Error>> + 'a {
+        let mut query = sqlx::query(sql);
+        for param in params {
+            query = query.bind(param);
+        }
+        query.fetch(&self.pool).map(move |row| row.map(|row| {
+            let mut record = HashMap::with_capacity(row.columns().len());
+            for column in row.columns() {
+                record.insert(column.name().to_string(), self.get_column_val(&row, column));
             }
-            "DECIMAL" => {
-                row.try_get::<Decimal, _>(column.name())
-                    .map(|decimal| serde_json::Value::String(decimal.to_string()))
-                    .map_err(|err| {
-                        log::error!("DECIMAL.getError: failed to decode column \"{}\": {}", column.name(), err);
-                    })
-                    .unwrap_or(serde_json::Value::Null)
+            record
+        }))
+    }
+
+    fn get_column_val(&self, row: &MySqlRow, column: &MySqlColumn) -> serde_json::Value {
+        let index = column.ordinal();
+        if row.try_get_raw(index).is_ok_and(|value| value.is_null()) {
+            return serde_json::Value::Null;
+        }
+
+        let type_name = column.type_info().name();
+        let decoded: Result<serde_json::Value, sqlx::Error> = match type_name {
+            "NULL" => Ok(serde_json::Value::Null),
+            // tinyint(1)
+            "BOOLEAN" if self.decode.tinyint1_as_bool => row.try_get::<bool, _>(index).map(serde_json::Value::Bool),
+            "BOOLEAN" | "TINYINT" | "SMALLINT" | "MEDIUMINT" | "INT" | "BIGINT" => row.try_get::<i64, _>(index).map(serde_json::Value::from),
+            "TINYINT UNSIGNED" | "SMALLINT UNSIGNED" | "MEDIUMINT UNSIGNED" | "INT UNSIGNED" | "BIGINT UNSIGNED" | "YEAR" | "BIT" => {
+                row.try_get_unchecked::<u64, _>(index).map(serde_json::Value::from)
             }
-            "FLOAT" | "DOUBLE" => {
-                row.try_get::<f64, _>(column.name())
-                    .map_or_else(
-                        |err| {
-                            log::error!("FLOAT/DOUBLE.getError: failed to decode column \"{}\": {}", column.name(), err);
-                            serde_json::Value::Null
-                        },
-                        serde_json::Value::from
-                    )
+            "DECIMAL" => row.try_get::<Decimal, _>(index).map(|decimal| self.decode.decimal_value(decimal)),
+            // f32直接转f64会带出多余的尾数，按字面值转换
+            "FLOAT" => row.try_get::<f32, _>(index).map(|val| json_f64(val.to_string().parse().unwrap_or(val as f64))),
+            "DOUBLE" => row.try_get::<f64, _>(index).map(json_f64),
+            "DATETIME" => row.try_get::<NaiveDateTime, _>(index).map(|val| self.decode.datetime_value(val, false)),
+            // TIMESTAMP按连接时区(UTC)返回
+            "TIMESTAMP" => row.try_get::<NaiveDateTime, _>(index).map(|val| self.decode.datetime_value(val, true)),
+            "DATE" => row.try_get::<NaiveDate, _>(index).map(|val| serde_json::Value::String(val.to_string())),
+            // TIME的范围是 -838:59:59 ~ 838:59:59，不能用NaiveTime
+            "TIME" => row.try_get::<MySqlTime, _>(index).map(|val| serde_json::Value::String(val.to_string())),
+            "CHAR" | "VARCHAR" | "TINYTEXT" | "TEXT" | "MEDIUMTEXT" | "LONGTEXT" | "ENUM" | "SET" => {
+                row.try_get::<String, _>(index).map(serde_json::Value::String)
             }
-            _ => row.try_get::<String, _>(column.name())
-                .map_or(serde_json::Value::Null, serde_json::Value::from),
-        }
+            "JSON" => row.try_get::<serde_json::Value, _>(index),
+            // 二进制及GEOMETRY等其他类型: 优先按utf8输出，否则base64
+            _ => row.try_get_unchecked::<Vec<u8>, _>(index).map(|bytes| match String::from_utf8(bytes) {
+                Ok(s) => serde_json::Value::String(s),
+                Err(err) => serde_json::Value::String(base64_encode(err.into_bytes())),
+            }),
+        };
+
+        decoded.unwrap_or_else(|err| {
+            log::error!("{}.getError: failed to decode column \"{}\": {}", type_name, column.name(), err);
+            serde_json::Value::Null
+        })
     }
 
-    pub async fn insert(&self, sql: &str) -> Result<i64, sqlx::Error> {
-        let result = sqlx::query(sql)
-            .execute(&self.pool)
-            .await?;
+    /// 写入的值按字段类型转换为语句参数，时间按 `database.decode.timezone` 解释
+    pub fn column_param(&self, column: &ColumnMeta, value: &serde_json::Value) -> Result<SqlParam, String> {
+        self.decode.column_param(column, value)
+    }
+
+    pub async fn insert(&self, sql: &str, params: Vec<SqlParam>) -> Result<i64, sqlx::Error> {
+        let mut query = sqlx::query(sql);
+        for param in params {
+            query = query.bind(param);
+        }
+        let result = query.execute(&self.pool).await?;
         Ok(result.rows_affected() as i64)
     }
 
-    pub async fn update(&self, sql: &str) -> Result<u64, sqlx::Error> {
-        let result = sqlx::query(sql)
-            .execute(&self.pool)
-            .await?;
+    pub async fn update(&self, sql: &str, params: Vec<SqlParam>) -> Result<u64, sqlx::Error> {
+        let mut query = sqlx::query(sql);
+        for param in params {
+            query = query.bind(param);
+        }
+        let result = query.execute(&self.pool).await?;
         Ok(result.rows_affected())
     }
 
-    pub async fn delete(&self, sql: &str) -> Result<u64, sqlx::Error> {
-        let result = sqlx::query(sql)
-            .execute(&self.pool)
-            .await?;
+    pub async fn delete(&self, sql: &str, params: Vec<SqlParam>) -> Result<u64, sqlx::Error> {
+        let mut query = sqlx::query(sql);
+        for param in params {
+            query = query.bind(param);
+        }
+        let result = query.execute(&self.pool).await?;
         Ok(result.rows_affected())
     }
 
-    pub async fn count(&self, sql: &str, params: Vec<String>) -> Result<i64, sqlx::Error> {
+    pub async fn count(&self, sql: &str, params: Vec<SqlParam>) -> Result<i64, sqlx::Error> {
         let mut query_scalar = sqlx::query_scalar::<_, i64>(sql);
         for param in params {
             query_scalar = query_scalar.bind(param);
@@ -264,6 +432,11 @@ impl DBConn {
     }
 }
 
+/// 当前元数据版本，用于判断依赖元数据生成的内容是否需要重新生成
+pub fn meta_version() -> u64 {
+    META_VERSION.load(Ordering::SeqCst)
+}
+
 pub fn is_table_exists(schema: &str, table: &str) -> bool {
     let table_key = format!("{schema}.{table}");
     TABLE_MAP.read().unwrap().contains_key(&table_key)
@@ -274,6 +447,13 @@ pub fn get_table(schema: &str, table: &str) -> Option<TableMeta> {
     TABLE_MAP.read().unwrap().get(&table_key).cloned()
 }
 
+/// 所有表的元数据，按 schema.table 排序
+pub fn get_table_list() -> Vec<TableMeta> {
+    let mut tables: Vec<TableMeta> = TABLE_MAP.read().unwrap().values().cloned().collect();
+    tables.sort_by(|a, b| (&a.schema, &a.name).cmp(&(&b.schema, &b.name)));
+    tables
+}
+
 pub fn get_table_name_list(schema: &str) -> HashMap<String, String> {
     let db_tables = DB_TABLES_MAP.read().unwrap();
     let tables = match db_tables.get(schema) {
@@ -293,3 +473,57 @@ pub fn get_table_name_list(schema: &str) -> HashMap<String, String> {
         }
     ).collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveDateTime};
+    use sqlx::types::Decimal;
+    use common::yaml::{DecimalFormat, Decode};
+    use crate::core::{parse_timezone, DecodeOptions};
+    use crate::param::SqlParam;
+    use crate::ColumnMeta;
+
+    #[test]
+    fn test_decode_options() {
+        assert_eq!(parse_timezone("UTC").unwrap().local_minus_utc(), 0);
+        assert_eq!(parse_timezone("+08:00").unwrap().local_minus_utc(), 8 * 3600);
+        assert_eq!(parse_timezone("-0530").unwrap().local_minus_utc(), -(5 * 3600 + 30 * 60));
+        assert!(parse_timezone("Asia/Shanghai").is_err());
+        assert!(parse_timezone("+25:00").is_err());
+
+        let decode = Decode { decimal: DecimalFormat::Number, timezone: "+08:00".to_string(), tinyint1_as_bool: true };
+        let options = DecodeOptions::new(&decode).unwrap();
+        let val: NaiveDateTime = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap().and_hms_opt(10, 30, 0).unwrap();
+        assert_eq!(options.datetime_value(val, false), "2024-05-01T10:30:00+08:00");
+        assert_eq!(options.datetime_value(val, true), "2024-05-01T18:30:00+08:00");
+        assert_eq!(options.decimal_value(Decimal::new(1999, 2)), serde_json::json!(19.99));
+        assert_eq!(options.decimal_value(Decimal::new(42, 0)), serde_json::json!(42));
+
+        let options = DecodeOptions::new(&Decode::default()).unwrap();
+        assert_eq!(options.datetime_value(val, true), "2024-05-01T10:30:00Z");
+        assert_eq!(options.decimal_value(Decimal::new(1999, 2)), "19.99");
+    }
+
+    #[test]
+    fn test_column_param() {
+        let column = |type_name: &str| ColumnMeta { field: "f".to_string(), type_name: type_name.to_string(), null: None, default: None, comment: None, key: None, extra: None };
+        let decode = Decode { decimal: DecimalFormat::String, timezone: "+08:00".to_string(), tinyint1_as_bool: true };
+        let options = DecodeOptions::new(&decode).unwrap();
+        let param = |type_name: &str, value: serde_json::Value| options.column_param(&column(type_name), &value);
+        let val: NaiveDateTime = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap().and_hms_opt(10, 30, 0).unwrap();
+
+        assert_eq!(param("json", serde_json::json!({"a": [1]})), Ok(SqlParam::Json(serde_json::json!({"a": [1]}))));
+        assert_eq!(param("json", serde_json::json!("a")), Ok(SqlParam::Json(serde_json::json!("a"))));
+        assert_eq!(param("tinyint(1)", serde_json::json!(true)), Ok(SqlParam::Int(1)));
+        assert_eq!(param("varchar(10)", serde_json::json!(false)), Ok(SqlParam::String("false".to_string())));
+        assert_eq!(param("varchar(10)", serde_json::json!(1.5)), Ok(SqlParam::String("1.5".to_string())));
+        assert_eq!(param("bigint unsigned", serde_json::json!(1)), Ok(SqlParam::Int(1)));
+        assert_eq!(param("varchar(10)", serde_json::Value::Null), Ok(SqlParam::Null));
+        assert_eq!(param("datetime", serde_json::json!("2024-05-01 10:30:00")), Ok(SqlParam::DateTime(val)));
+        assert_eq!(param("datetime(3)", serde_json::json!("2024-05-01T02:30:00Z")), Ok(SqlParam::DateTime(val)));
+        assert_eq!(param("timestamp", serde_json::json!("2024-05-01T18:30:00+08:00")), Ok(SqlParam::DateTime(val)));
+        assert_eq!(param("timestamp", serde_json::json!("2024-05-01 18:30:00")), Ok(SqlParam::DateTime(val)));
+        assert!(param("datetime", serde_json::json!("yesterday")).is_err());
+        assert!(param("varchar(10)", serde_json::json!([1])).is_err());
+    }
+}
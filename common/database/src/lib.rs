@@ -1,14 +1,16 @@
 pub mod core;
+pub mod param;
 
 
 
 
 use sqlx::Row;
+use common::yaml::Decode;
 use crate::core::DBConn;
 
 // 初始化数据库连接池
-pub async fn init_datasource_conn(url: &str) -> Result<DBConn, sqlx::Error> {
-    DBConn::new(url).await
+pub async fn init_datasource_conn(url: &str, decode: &Decode) -> Result<DBConn, sqlx::Error> {
+    DBConn::connect(url, decode).await
 }
 
 // 数据库元数据
@@ -30,6 +32,22 @@ pub struct TableMeta {
     pub columns: fnv::FnvHashMap<String, ColumnMeta>,
     // 表注释
     pub comment: Option<String>,
+    // 外键
+    #[serde(default)]
+    pub foreign_keys: Vec<ForeignKeyMeta>,
+}
+
+// 外键元数据
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq)]
+pub struct ForeignKeyMeta {
+    // 字段名
+    pub column: String,
+    // 引用的库名
+    pub referenced_schema: String,
+    // 引用的表名
+    pub referenced_table: String,
+    // 引用的字段名
+    pub referenced_column: String,
 }
 
 // 字段元数据
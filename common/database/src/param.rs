@@ -0,0 +1,150 @@
+use chrono::NaiveDateTime;
+use sqlx::{encode::IsNull, error::BoxDynError, mysql::MySqlTypeInfo, types::Json, Encode, MySql, Type};
+
+/// 带类型的语句参数，按原生类型绑定，`Null` 绑定为 SQL NULL 而不是字符串 "NULL"
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlParam {
+    Null,
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+    Bytes(Vec<u8>),
+    DateTime(NaiveDateTime),
+    Json(serde_json::Value),
+}
+
+impl From<&serde_json::Value> for SqlParam {
+    /// JSON 数字优先按整数绑定，数组、对象按 JSON 文本绑定
+    fn from(value: &serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Null => SqlParam::Null,
+            serde_json::Value::Bool(val) => SqlParam::Bool(*val),
+            serde_json::Value::Number(number) => match (number.as_i64(), number.as_u64()) {
+                (Some(val), _) => SqlParam::Int(val),
+                (None, Some(val)) => SqlParam::UInt(val),
+                _ => SqlParam::Float(number.as_f64().unwrap_or_default()),
+            },
+            serde_json::Value::String(val) => SqlParam::String(val.clone()),
+            serde_json::Value::Array(_) | serde_json::Value::Object(_) => SqlParam::Json(value.clone()),
+        }
+    }
+}
+
+impl From<serde_json::Value> for SqlParam {
+    fn from(value: serde_json::Value) -> Self {
+        SqlParam::from(&value)
+    }
+}
+
+impl From<i64> for SqlParam {
+    fn from(val: i64) -> Self {
+        SqlParam::Int(val)
+    }
+}
+
+impl From<u64> for SqlParam {
+    fn from(val: u64) -> Self {
+        SqlParam::UInt(val)
+    }
+}
+
+impl From<f64> for SqlParam {
+    fn from(val: f64) -> Self {
+        SqlParam::Float(val)
+    }
+}
+
+impl From<bool> for SqlParam {
+    fn from(val: bool) -> Self {
+        SqlParam::Bool(val)
+    }
+}
+
+impl From<String> for SqlParam {
+    fn from(val: String) -> Self {
+        SqlParam::String(val)
+    }
+}
+
+impl From<&str> for SqlParam {
+    fn from(val: &str) -> Self {
+        SqlParam::String(val.to_string())
+    }
+}
+
+impl From<Vec<u8>> for SqlParam {
+    fn from(val: Vec<u8>) -> Self {
+        SqlParam::Bytes(val)
+    }
+}
+
+impl From<NaiveDateTime> for SqlParam {
+    fn from(val: NaiveDateTime) -> Self {
+        SqlParam::DateTime(val)
+    }
+}
+
+impl<T: Into<SqlParam>> From<Option<T>> for SqlParam {
+    fn from(val: Option<T>) -> Self {
+        val.map_or(SqlParam::Null, Into::into)
+    }
+}
+
+impl Type<MySql> for SqlParam {
+    // 实际类型由 `produces` 按值给出
+    fn type_info() -> MySqlTypeInfo {
+        <&str as Type<MySql>>::type_info()
+    }
+}
+
+impl Encode<'_, MySql> for SqlParam {
+    fn encode_by_ref(&self, buf: &mut Vec<u8>) -> Result<IsNull, BoxDynError> {
+        match self {
+            SqlParam::Null => Ok(IsNull::Yes),
+            SqlParam::Int(val) => <i64 as Encode<MySql>>::encode_by_ref(val, buf),
+            SqlParam::UInt(val) => <u64 as Encode<MySql>>::encode_by_ref(val, buf),
+            SqlParam::Float(val) => <f64 as Encode<MySql>>::encode_by_ref(val, buf),
+            SqlParam::Bool(val) => <bool as Encode<MySql>>::encode_by_ref(val, buf),
+            SqlParam::String(val) => <String as Encode<MySql>>::encode_by_ref(val, buf),
+            SqlParam::Bytes(val) => <Vec<u8> as Encode<MySql>>::encode_by_ref(val, buf),
+            SqlParam::DateTime(val) => <NaiveDateTime as Encode<MySql>>::encode_by_ref(val, buf),
+            SqlParam::Json(val) => <Json<&serde_json::Value> as Encode<MySql>>::encode_by_ref(&Json(val), buf),
+        }
+    }
+
+    fn produces(&self) -> Option<MySqlTypeInfo> {
+        Some(match self {
+            SqlParam::Null | SqlParam::String(_) => <String as Type<MySql>>::type_info(),
+            SqlParam::Int(_) => <i64 as Type<MySql>>::type_info(),
+            SqlParam::UInt(_) => <u64 as Type<MySql>>::type_info(),
+            SqlParam::Float(_) => <f64 as Type<MySql>>::type_info(),
+            SqlParam::Bool(_) => <bool as Type<MySql>>::type_info(),
+            SqlParam::Bytes(_) => <Vec<u8> as Type<MySql>>::type_info(),
+            SqlParam::DateTime(_) => <NaiveDateTime as Type<MySql>>::type_info(),
+            SqlParam::Json(_) => <Json<serde_json::Value> as Type<MySql>>::type_info(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::param::SqlParam;
+
+    #[test]
+    fn test_from_json() {
+        let params: Vec<SqlParam> = serde_json::json!([null, true, -1, u64::MAX, 1.5, "NULL", [1, 2]])
+            .as_array().unwrap().iter().map(SqlParam::from).collect();
+        assert_eq!(params, vec![
+            SqlParam::Null,
+            SqlParam::Bool(true),
+            SqlParam::Int(-1),
+            SqlParam::UInt(u64::MAX),
+            SqlParam::Float(1.5),
+            SqlParam::String("NULL".to_string()),
+            SqlParam::Json(serde_json::json!([1, 2])),
+        ]);
+        assert_eq!(SqlParam::from(None::<i64>), SqlParam::Null);
+    }
+}
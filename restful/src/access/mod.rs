@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, RwLock};
+use common::yaml::{AccessControl, AccessRule};
+use crate::policy::Auth;
+
+/// 未登录请求的角色
+pub const ANONYMOUS_ROLE: &str = "anonymous";
+
+/// 按表、方法的角色权限
+static ACCESS_REGISTRY: LazyLock<RwLock<AccessControl>> = LazyLock::new(|| RwLock::new(AccessControl::default()));
+
+/// 加载 `restful.access` 配置
+pub fn init_access(access: &AccessControl) {
+    *ACCESS_REGISTRY.write().unwrap() = access.clone();
+}
+
+/// 方法对应的角色列表
+fn method_roles<'a>(rule: &'a AccessRule, method: &str) -> Option<&'a Vec<String>> {
+    match method {
+        "get" => rule.get.as_ref(),
+        "head" => rule.head.as_ref(),
+        "post" => rule.post.as_ref(),
+        "put" => rule.put.as_ref(),
+        "delete" => rule.delete.as_ref(),
+        _ => None,
+    }
+}
+
+/// 角色是否可以对表执行该方法: 表的配置优先，其次是默认配置，均未配置时不限制
+pub fn is_allowed(table_key: &str, method: &str, role: &str) -> bool {
+    let registry = ACCESS_REGISTRY.read().unwrap();
+    registry.tables.iter()
+        .find(|table| table.name.eq_ignore_ascii_case(table_key))
+        .and_then(|table| method_roles(&table.rule, method))
+        .or_else(|| method_roles(&registry.default, method))
+        .is_none_or(|roles| roles.iter().any(|allowed| allowed == role))
+}
+
+/// 检查请求中涉及的所有表，返回无权限的表及错误信息
+pub fn check_access(method: &str, auth: &Auth, body_map: &HashMap<String, serde_json::Value>) -> Result<(), HashMap<String, serde_json::Value>> {
+    let role = auth.role.as_deref().unwrap_or(ANONYMOUS_ROLE);
+    let mut table_keys = Vec::new();
+    for (key, value) in body_map {
+        collect_table_keys(method, key, value, &mut table_keys);
+    }
+
+    let denied: HashMap<String, serde_json::Value> = table_keys.into_iter()
+        .filter(|table_key| !is_allowed(table_key, method, role))
+        .map(|table_key| {
+            let err = format!("role '{}' is not allowed to {} {}", role, method, table_key);
+            (table_key, serde_json::Value::String(err))
+        })
+        .collect();
+    if denied.is_empty() { Ok(()) } else { Err(denied) }
+}
+
+/// 收集请求中的表节点，GET 请求中以 `[]` 结尾的键为命名空间，其下的对象也是表节点，子查询读取的表同样需要权限；
+/// POST 请求中的 `schema.table[]` 为批量新增的表
+fn collect_table_keys(method: &str, key: &str, value: &serde_json::Value, table_keys: &mut Vec<String>) {
+    if let ("post", Some(table_key), true) = (method, key.strip_suffix("[]"), value.is_array()) {
+        table_keys.push(table_key.to_string());
+        return;
+    }
+    if key.starts_with('@') || !value.is_object() {
+        return;
+    }
+    match (method, key.ends_with("[]"), value.as_object()) {
+        ("get", true, Some(map)) => map.iter().for_each(|(k, v)| collect_table_keys(method, k, v, table_keys)),
+        ("get", false, Some(map)) => {
+            table_keys.push(key.to_string());
+            collect_subquery_tables(map, table_keys);
+        }
+        _ => table_keys.push(key.to_string()),
+    }
+}
+
+/// 收集子查询 `"字段@": {"from": "schema.Table", ...}` 中的表，包括嵌套的子查询
+fn collect_subquery_tables(node: &serde_json::Map<String, serde_json::Value>, table_keys: &mut Vec<String>) {
+    for (key, value) in node {
+        if let (true, Some(serde_json::Value::String(table_key))) = (key.ends_with('@'), value.get("from")) {
+            table_keys.push(table_key.clone());
+            if let Some(serde_json::Value::Object(attrs)) = value.get(table_key) {
+                collect_subquery_tables(attrs, table_keys);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use common::yaml::{AccessControl, AccessRule, TableAccessRule};
+    use crate::access::{check_access, ACCESS_REGISTRY};
+    use crate::policy::Auth;
+    use crate::verify::tests::lock_registries;
+
+    #[test]
+    fn test_check_access() {
+        let _lock = lock_registries();
+        let roles = |roles: &[&str]| Some(roles.iter().map(|role| role.to_string()).collect::<Vec<_>>());
+        *ACCESS_REGISTRY.write().unwrap() = AccessControl {
+            default: AccessRule { get: roles(&["anonymous", "user", "admin"]), delete: roles(&["admin"]), ..Default::default() },
+            tables: vec![TableAccessRule { name: "ideabase.account".to_string(), rule: AccessRule { get: roles(&["admin"]), post: roles(&["admin"]), ..Default::default() } }],
+        };
+        let body: HashMap<String, serde_json::Value> = serde_json::from_value(serde_json::json!({
+            "[]": { "count": 10, "timeline.Moment": {}, "Account[]": { "ideabase.account": {} } },
+            "@explain": true
+        })).unwrap();
+        let user = Auth { sub: Some(1), role: Some("user".to_string()) };
+        let admin = Auth { sub: Some(2), role: Some("admin".to_string()) };
+
+        let denied = check_access("get", &user, &body).unwrap_err();
+        assert_eq!(denied.len(), 1);
+        assert_eq!(denied["ideabase.account"], "role 'user' is not allowed to get ideabase.account");
+        assert!(check_access("get", &admin, &body).is_ok());
+
+        // 子查询中的表
+        let body: HashMap<String, serde_json::Value> = serde_json::from_value(serde_json::json!({
+            "timeline.Moment": { "user_id{}@": { "from": "ideabase.account", "ideabase.account": { "@column": "id" } } }
+        })).unwrap();
+        assert!(check_access("get", &user, &body).is_err());
+        assert!(check_access("get", &admin, &body).is_ok());
+
+        let body: HashMap<String, serde_json::Value> = serde_json::from_value(serde_json::json!({ "timeline.Moment": { "id": 1 } })).unwrap();
+        assert!(check_access("delete", &Auth::default(), &body).is_err());
+        assert!(check_access("delete", &admin, &body).is_ok());
+        // 未配置的方法不限制
+        assert!(check_access("put", &Auth::default(), &body).is_ok());
+
+        // 批量新增的表
+        let body: HashMap<String, serde_json::Value> = serde_json::from_value(serde_json::json!({ "ideabase.account[]": [{ "email": "a@b.c" }] })).unwrap();
+        assert_eq!(check_access("post", &user, &body).unwrap_err()["ideabase.account"], "role 'user' is not allowed to post ideabase.account");
+        assert!(check_access("post", &admin, &body).is_ok());
+        *ACCESS_REGISTRY.write().unwrap() = AccessControl::default();
+    }
+}
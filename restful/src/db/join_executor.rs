@@ -0,0 +1,337 @@
+use std::collections::{HashMap, HashSet};
+use database::core::DBConn;
+use crate::db::query_executor::QueryExecutor;
+use crate::verify::quote_ident;
+
+/// 排名列，同一条主节点数据连接出的行排名相同，按排名分页
+const RANK_COLUMN: &str = "__rk";
+
+type Row = HashMap<String, serde_json::Value>;
+
+/// 连接类型，对应 APIJSON `join` 中每一项的前缀
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JoinKind {
+    // &
+    Inner,
+    // <
+    Left,
+    // >
+    Right,
+}
+
+impl JoinKind {
+    pub fn from_symbol(symbol: char) -> Result<Self, String> {
+        match symbol {
+            '&' => Ok(JoinKind::Inner),
+            '<' => Ok(JoinKind::Left),
+            '>' => Ok(JoinKind::Right),
+            _ => Err(format!("join type '{}' is not supported", symbol)),
+        }
+    }
+
+    fn keyword(&self) -> &'static str {
+        match self {
+            JoinKind::Inner => "INNER JOIN",
+            JoinKind::Left => "LEFT JOIN",
+            JoinKind::Right => "RIGHT JOIN",
+        }
+    }
+}
+
+/// `join` 中的一项，如 `&/User/id@`: 从节点以 `id@` 引用主节点的字段
+#[derive(Debug, Clone, PartialEq)]
+pub struct JoinRef {
+    pub kind: JoinKind,
+    // 被连接的从节点路径
+    pub node_path: String,
+    // 从节点的关联字段
+    pub field: String,
+    // 主节点路径
+    pub primary_path: String,
+    // 主节点被引用的字段
+    pub primary_field: String,
+}
+
+/// 连接查询中的一张表，每个节点的查询作为派生表，节点自身的条件和行级安全策略留在派生表内
+#[derive(Debug)]
+struct JoinTable {
+    executor: QueryExecutor,
+    // 派生表输出的字段
+    columns: Vec<String>,
+    // 判断该表在结果行中是否有数据的字段: 主节点为主键，从节点为关联字段
+    presence: String,
+    // 主键，用于 RIGHT JOIN 时区分没有主节点数据的行
+    primary_key: Option<String>,
+    join: Option<JoinRef>,
+}
+
+impl JoinTable {
+    fn alias(index: usize) -> String {
+        quote_ident(&format!("t{}", index))
+    }
+}
+
+/// 连接查询的结果，按节点拆分后交给原有的嵌套逻辑
+#[derive(Debug, Default)]
+pub struct JoinOutput {
+    // 主节点数据，按排名顺序
+    pub primary: Vec<Row>,
+    // 各从节点的数据(与 `join` 中的顺序一致)，已去重
+    pub joined: Vec<Vec<Row>>,
+    // RIGHT JOIN 中没有主节点数据的结果，每项为各从节点的数据
+    pub orphans: Vec<Vec<Vec<Row>>>,
+}
+
+/// JOIN 模式: 主节点与 `join` 中的从节点合并为一条 SQL，分页作用于主节点数据
+#[derive(Debug)]
+pub struct JoinExecutor {
+    // 第一张为主节点，其余按 `join` 中的顺序
+    tables: Vec<JoinTable>,
+}
+
+impl JoinExecutor {
+    /// # 参数
+    /// * `primary` - 已解析条件和分页的主节点查询
+    /// * `joins` - 从节点及其已解析条件的查询
+    pub fn new(mut primary: QueryExecutor, joins: Vec<(JoinRef, QueryExecutor)>) -> Result<Self, String> {
+        if primary.is_grouped() || joins.iter().any(|(_, executor)| executor.is_grouped()) {
+            return Err("join does not support DISTINCT, @group or @having".to_string());
+        }
+        let primary_key = primary.primary_key().map_err(|err| format!("join requires {}", err))?;
+        primary.add_column(&primary_key)?;
+        add_order_columns(&mut primary)?;
+        for (join, _) in &joins {
+            primary.add_column(&join.primary_field)?;
+        }
+
+        let mut tables = vec![JoinTable {
+            columns: primary.output_columns()?,
+            executor: primary,
+            presence: primary_key.clone(),
+            primary_key: Some(primary_key),
+            join: None,
+        }];
+        for (join, mut executor) in joins {
+            executor.add_column(&join.field)?;
+            add_order_columns(&mut executor)?;
+            let primary_key = match join.kind {
+                JoinKind::Right => {
+                    let primary_key = executor.primary_key().map_err(|err| format!("{}: right join requires {}", join.node_path, err))?;
+                    executor.add_column(&primary_key)?;
+                    Some(primary_key)
+                }
+                _ => None,
+            };
+            tables.push(JoinTable { columns: executor.output_columns()?, executor, presence: join.field.clone(), primary_key, join: Some(join) });
+        }
+        Ok(JoinExecutor { tables })
+    }
+
+    pub async fn exec(&self, db: &DBConn) -> Result<JoinOutput, sqlx::Error> {
+        let sql = self.to_sql();
+        let bind_params = self.bind_params();
+        log::info!("sql.join: {}, params: {}", sql, serde_json::to_string(&bind_params).unwrap());
+        let rows = db.query_list(&sql, QueryExecutor::to_sql_params(&bind_params)).await?;
+        Ok(self.split_rows(rows))
+    }
+
+    /// 统计主节点数据的条数
+    pub async fn exec_count(&self, db: &DBConn) -> Result<i64, sqlx::Error> {
+        let sql = self.to_count_sql();
+        let bind_params = self.bind_params();
+        log::info!("sql.count: {}, params: {}", sql, serde_json::to_string(&bind_params).unwrap());
+        db.count(&sql, QueryExecutor::to_sql_params(&bind_params)).await
+    }
+
+    /// 执行 MySQL `EXPLAIN`，返回查询计划
+    pub async fn explain(&self, db: &DBConn) -> Result<Vec<HashMap<String, serde_json::Value>>, sqlx::Error> {
+        let sql = format!("EXPLAIN {}", self.to_sql());
+        db.query_list(&sql, QueryExecutor::to_sql_params(&self.bind_params())).await
+    }
+
+    pub fn to_sql(&self) -> String {
+        let columns: Vec<String> = self.tables.iter().enumerate()
+            .flat_map(|(index, table)| table.columns.iter().map(move |column| {
+                format!("{}.{} AS {}", JoinTable::alias(index), quote_ident(column), quote_ident(&format!("{}.{}", index, column)))
+            }))
+            .collect();
+        let rank = quote_ident(RANK_COLUMN);
+        let inner_sql = format!("SELECT {},DENSE_RANK() OVER (ORDER BY {}) AS {}{}", columns.join(","), self.rank_keys().join(","), rank, self.tables_clause());
+
+        let mut sql = format!("SELECT * FROM ({}) AS t", inner_sql);
+        let primary = &self.tables[0].executor;
+        if primary.limit() > 0 {
+            let offset = primary.limit() * primary.page();
+            sql.push_str(&format!(" WHERE {}>{} AND {}<={}", rank, offset, rank, offset + primary.limit()));
+        }
+        // 同一条主节点数据内，从节点数据按各自的 @order 排序
+        let mut order = vec![rank];
+        for (index, table) in self.tables.iter().enumerate().skip(1) {
+            for item in table.executor.order_items() {
+                order.push(format!("{} {}", quote_ident(&format!("{}.{}", index, item.column)), if item.desc { "DESC" } else { "ASC" }));
+            }
+        }
+        sql.push_str(&format!(" ORDER BY {}", order.join(",")));
+        primary.with_hint(sql)
+    }
+
+    pub fn to_count_sql(&self) -> String {
+        let keys: Vec<String> = self.row_keys().iter().enumerate().map(|(index, key)| format!("{} AS {}", key, quote_ident(&format!("k{}", index)))).collect();
+        self.tables[0].executor.with_hint(format!("SELECT count(*) FROM (SELECT DISTINCT {}{}) AS t", keys.join(","), self.tables_clause()))
+    }
+
+    /// 按派生表的顺序返回绑定参数
+    pub fn bind_params(&self) -> Vec<serde_json::Value> {
+        self.tables.iter().flat_map(|table| table.executor.count_params()).collect()
+    }
+
+    fn tables_clause(&self) -> String {
+        let mut sql = format!(" FROM ({}) AS {}", self.tables[0].executor.to_select_sql(false), JoinTable::alias(0));
+        for (index, table) in self.tables.iter().enumerate().skip(1) {
+            let Some(join) = &table.join else { continue };
+            sql.push_str(&format!(" {} ({}) AS {} ON {}.{}={}.{}",
+                join.kind.keyword(), table.executor.to_select_sql(false), JoinTable::alias(index),
+                JoinTable::alias(index), quote_ident(&join.field), JoinTable::alias(0), quote_ident(&join.primary_field)));
+        }
+        sql
+    }
+
+    /// 唯一确定一条结果的键: 主节点主键，RIGHT JOIN 没有主节点数据时加上从节点主键
+    fn row_keys(&self) -> Vec<String> {
+        let primary_key = format!("{}.{}", JoinTable::alias(0), quote_ident(&self.tables[0].presence));
+        let mut keys = vec![primary_key.clone()];
+        for (index, table) in self.tables.iter().enumerate().skip(1) {
+            if let Some(column) = &table.primary_key {
+                keys.push(format!("CASE WHEN {} IS NULL THEN {}.{} END", primary_key, JoinTable::alias(index), quote_ident(column)));
+            }
+        }
+        keys
+    }
+
+    /// 排名的排序键: 主节点的 @order 加唯一键，没有主节点数据的结果排在最后
+    fn rank_keys(&self) -> Vec<String> {
+        let mut row_keys = self.row_keys();
+        let mut keys = Vec::new();
+        if row_keys.len() > 1 {
+            keys.push(format!("{} IS NULL", row_keys[0]));
+        }
+        for item in self.tables[0].executor.order_items() {
+            keys.push(format!("{}.{} {}", JoinTable::alias(0), quote_ident(&item.column), if item.desc { "DESC" } else { "ASC" }));
+        }
+        keys.append(&mut row_keys);
+        keys
+    }
+
+    /// 将结果行按节点拆分: 同一排名的行属于同一条主节点数据，从节点数据去重
+    fn split_rows(&self, rows: Vec<Row>) -> JoinOutput {
+        let mut output = JoinOutput { joined: vec![Vec::new(); self.tables.len() - 1], ..Default::default() };
+        let mut seen: Vec<HashSet<String>> = vec![HashSet::new(); self.tables.len() - 1];
+        let mut current_rank = None;
+        for mut row in rows {
+            let rank = row.remove(RANK_COLUMN);
+            let is_new_rank = current_rank != rank;
+            current_rank = rank;
+            let mut tables = self.tables.iter().enumerate().map(|(index, table)| {
+                let prefix = format!("{}.", index);
+                let node_row: Row = table.columns.iter()
+                    .map(|column| (column.clone(), row.remove(&format!("{}{}", prefix, column)).unwrap_or(serde_json::Value::Null)))
+                    .collect();
+                Some(node_row).filter(|node_row| node_row.get(&table.presence).is_some_and(|value| !value.is_null()))
+            });
+
+            match tables.next().flatten() {
+                Some(primary_row) => {
+                    if is_new_rank {
+                        output.primary.push(primary_row);
+                    }
+                    for ((joined, seen), node_row) in output.joined.iter_mut().zip(seen.iter_mut()).zip(tables) {
+                        if let Some(node_row) = node_row.filter(|node_row| seen.insert(serde_json::to_value(node_row).unwrap().to_string())) {
+                            joined.push(node_row);
+                        }
+                    }
+                }
+                None => {
+                    if is_new_rank {
+                        output.orphans.push(vec![Vec::new(); self.tables.len() - 1]);
+                    }
+                    let Some(orphan) = output.orphans.last_mut() else { continue };
+                    for (joined, node_row) in orphan.iter_mut().zip(tables) {
+                        if let Some(node_row) = node_row.filter(|node_row| !joined.contains(node_row)) {
+                            joined.push(node_row);
+                        }
+                    }
+                }
+            }
+        }
+        output
+    }
+}
+
+/// 排序字段需要出现在派生表的结果中
+fn add_order_columns(executor: &mut QueryExecutor) -> Result<(), String> {
+    let columns: Vec<String> = executor.order_items().iter().filter(|item| !item.alias).map(|item| item.column.clone()).collect();
+    for column in columns {
+        executor.add_column(&column)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use database::TableMeta;
+    use crate::db::join_executor::{JoinExecutor, JoinKind, JoinRef};
+    use crate::db::query_executor::QueryExecutor;
+    use crate::verify::tests::test_table_with;
+
+    fn test_table(name: &str, columns: &[&str]) -> TableMeta {
+        let columns: Vec<(&str, &str)> = columns.iter().map(|column| (*column, "bigint")).collect();
+        test_table_with("timeline", name, &columns)
+    }
+
+    fn join_ref(kind: JoinKind, name: &str, field: &str, primary_field: &str) -> JoinRef {
+        JoinRef { kind, node_path: format!("[]/{}", name), field: field.to_string(), primary_path: "[]/Moment".to_string(), primary_field: primary_field.to_string() }
+    }
+
+    #[test]
+    fn test_join_sql() {
+        // 作者在某个城市的动态，以及每条动态的评论
+        let mut moment = QueryExecutor::with_table(test_table("moment", &["id", "user_id", "content"]));
+        moment.parse_condition("@column", &serde_json::json!("id,content")).unwrap();
+        moment.parse_condition("@order", &serde_json::json!("id-")).unwrap();
+        moment.page_size(serde_json::json!(1), serde_json::json!(10));
+        let mut user = QueryExecutor::with_table(test_table("user", &["id", "city"]));
+        user.parse_condition("city", &serde_json::json!("Hangzhou")).unwrap();
+        let comment = QueryExecutor::with_table(test_table("comment", &["id", "moment_id"]));
+        let join = JoinExecutor::new(moment, vec![
+            (join_ref(JoinKind::Inner, "User", "id", "user_id"), user),
+            (join_ref(JoinKind::Left, "Comment", "moment_id", "id"), comment),
+        ]).unwrap();
+        assert_eq!(join.to_sql(), "SELECT * FROM (SELECT `t0`.`id` AS `0.id`,`t0`.`content` AS `0.content`,`t0`.`user_id` AS `0.user_id`,\
+            `t1`.`city` AS `1.city`,`t1`.`id` AS `1.id`,`t2`.`id` AS `2.id`,`t2`.`moment_id` AS `2.moment_id`,\
+            DENSE_RANK() OVER (ORDER BY `t0`.`id` DESC,`t0`.`id`) AS `__rk` \
+            FROM (SELECT `id`,`content`,`user_id` FROM `timeline`.`moment`) AS `t0` \
+            INNER JOIN (SELECT * FROM `timeline`.`user` WHERE `city`=?) AS `t1` ON `t1`.`id`=`t0`.`user_id` \
+            LEFT JOIN (SELECT * FROM `timeline`.`comment`) AS `t2` ON `t2`.`moment_id`=`t0`.`id`) AS t \
+            WHERE `__rk`>10 AND `__rk`<=20 ORDER BY `__rk`");
+        assert_eq!(join.bind_params(), vec![serde_json::json!("Hangzhou")]);
+        assert!(join.to_count_sql().starts_with("SELECT count(*) FROM (SELECT DISTINCT `t0`.`id` AS `k0` FROM (SELECT"));
+
+        // 按排名拆分: 主节点数据去重，LEFT JOIN 没有匹配时从节点无数据
+        let row = |rank: i64, moment_id: i64, comment_id: Option<i64>| HashMap::from([
+            ("__rk".to_string(), serde_json::json!(rank)),
+            ("0.id".to_string(), serde_json::json!(moment_id)), ("0.content".to_string(), serde_json::json!("a")), ("0.user_id".to_string(), serde_json::json!(7)),
+            ("1.id".to_string(), serde_json::json!(7)), ("1.city".to_string(), serde_json::json!("Hangzhou")),
+            ("2.id".to_string(), serde_json::json!(comment_id)), ("2.moment_id".to_string(), serde_json::json!(comment_id.map(|_| moment_id))),
+        ]);
+        let output = join.split_rows(vec![row(1, 3, Some(30)), row(1, 3, Some(31)), row(2, 2, None)]);
+        assert_eq!(output.primary.iter().map(|row| row["id"].clone()).collect::<Vec<_>>(), vec![serde_json::json!(3), serde_json::json!(2)]);
+        assert_eq!(output.joined[0].len(), 1);
+        assert_eq!(output.joined[1].iter().map(|row| row["id"].clone()).collect::<Vec<_>>(), vec![serde_json::json!(30), serde_json::json!(31)]);
+        assert!(output.orphans.is_empty());
+
+        let mut grouped = QueryExecutor::with_table(test_table("moment", &["id", "user_id"]));
+        grouped.parse_condition("@group", &serde_json::json!("user_id")).unwrap();
+        assert!(JoinExecutor::new(grouped, vec![]).is_err());
+    }
+}
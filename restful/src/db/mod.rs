@@ -1,3 +1,5 @@
 pub mod create_executor;
 pub mod query_executor;
-pub mod query_context;
\ No newline at end of file
+pub mod join_executor;
+pub mod query_context;
+pub mod query_plan;
\ No newline at end of file
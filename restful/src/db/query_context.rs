@@ -1,15 +1,11 @@
 use fnv::FnvHashMap;
-use std::rc::Rc;
-use std::cell::RefCell;
 use std::collections::{BTreeMap, HashMap, VecDeque};
 use http::StatusCode;
+use crate::db::join_executor::{JoinKind, JoinRef};
 use crate::db::query_executor::QueryExecutor;
-
-/// 主节点权重常量
-pub const RATIO_PRIMARY: i32 = 10000;
-/// 被依赖一次的权重系数
-const RATIO_RELATED: i32 = 10;
-
+use crate::db::query_plan::QueryPlan;
+use crate::policy::Auth;
+use common::yaml::Limits;
 
 #[derive(Debug)]
 pub struct QueryContext {
@@ -18,27 +14,45 @@ pub struct QueryContext {
     // 错误信息
     pub err_msg: Option<String>,
 
-    // 主节点字段映射表(主节点路径 -> 主节点字段 -> 指向从节点关联字段路径)
-    pub primary_relate_kv: FnvHashMap<String, HashMap<String, String>>,
+    // 主节点字段映射表(主节点路径 -> 主节点字段 -> 指向从节点关联字段路径列表)
+    pub primary_relate_kv: FnvHashMap<String, HashMap<String, Vec<String>>>,
     // 从节点字段映射表(从节点路径 -> 从节点字段 -> 指向主节点关联字段路径)
     pub slave_relate_kv: FnvHashMap<String, HashMap<String, String>>,
 
-    // 分层节点，层级: 节点列表
-    pub layer_query_node: BTreeMap<i32, Vec<Rc<RefCell<QueryNode>>>>,
+    // 执行计划，按节点间的依赖关系分阶段
+    pub plan: QueryPlan,
+    // @explain 调试模式
+    pub explain: ExplainMode,
+    // 调试信息(节点路径 -> SQL、参数、行数、耗时、依赖)
+    pub explain_nodes: BTreeMap<String, serde_json::Value>,
+    // 请求者身份，用于行级安全策略
+    pub auth: Auth,
+    // 查询开销限制
+    pub limits: Limits,
+    // 已返回的数据行数，用于行数预算
+    pub rows: usize,
     // 命名空间节点
     pub namespace_node: FnvHashMap<String, FnvHashMap<String, serde_json::Value>>,
+    // 命名空间分页信息引用(响应字段 -> 引用路径)，如 "total" -> "[]/total"
+    pub namespace_ref_kv: FnvHashMap<String, String>,
+    // 命名空间分页信息(命名空间路径 -> {total, count, page, max, more})
+    pub namespace_info: FnvHashMap<String, serde_json::Value>,
     // 数据查询节点，节点路径: 节点
-    pub query_node: FnvHashMap<String, Rc<RefCell<QueryNode>>>,
+    pub query_node: FnvHashMap<String, QueryNode>,
+    // JOIN 模式(主节点路径 -> 并入主节点查询的从节点)
+    pub joins: FnvHashMap<String, Vec<JoinRef>>,
+    // RIGHT JOIN 中没有主节点数据的结果(主节点路径 -> 结果列表)
+    pub join_orphans: FnvHashMap<String, Vec<HashMap<String, serde_json::Value>>>,
 
     // 主节点数据列表(节点路径 -> 结果数据)，主节点就是每一个命名空间的主查询节点
     pub primary_node_data: FnvHashMap<String, Vec<HashMap<String, serde_json::Value>>>,
     // 被关联字段的值(主节点字段路径 -> 主节点字段值(默认Value::Null, 结果是array or object))
     pub primary_node_related_field_values: FnvHashMap<String, serde_json::Value>,
-    // 从节点关联字段映射表(从节点父路径 -> 字段对应的值), 用于主节点获取从节点数据
+    // 从节点关联数据(从节点路径 -> "字段/字段值" -> 数据), 用于按上游节点的字段值获取从节点数据
     pub slave_node_relate_data: FnvHashMap<String, FnvHashMap<String, Vec<HashMap<String, serde_json::Value>>>>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct QueryNode {
     // 节点名称
     pub name: String,
@@ -48,8 +62,6 @@ pub struct QueryNode {
     pub is_list: bool,
     /// 属性映射
     pub attributes: HashMap<String, serde_json::Value>,
-    // 权重
-    pub weight: i32,
     // SQL执行器，负责生成和执行SQL
     pub sql_executor: QueryExecutor,
 }
@@ -58,23 +70,25 @@ pub struct QueryNode {
 impl QueryContext {
     /// 从 JSON 值构建 QueryContext
     pub fn from_json(root: HashMap<String, serde_json::Value>) -> Self {
-        // 创建处理队列，每项包含：(父路径, 节点名称, 节点值, 深度)
-        let mut json_vec_deque: VecDeque<(String, String, serde_json::Value, i32)> = VecDeque::new();
+        // 创建处理队列，每项包含：(父路径, 节点名称, 节点值)
+        let mut json_vec_deque: VecDeque<(String, String, serde_json::Value)> = VecDeque::new();
 
-        // 分层节点，层级: 节点列表
-        let mut layer_query_node: BTreeMap<i32, Vec<Rc<RefCell<QueryNode>>>> = BTreeMap::default();
         // 初始化数据结构，用于构建查询上下文
         let mut namespace_node = FnvHashMap::default();
         // 数据查询节点，节点路径: 节点
-        let mut query_node: FnvHashMap<String, Rc<RefCell<QueryNode>>> = FnvHashMap::default();
+        let mut query_node: FnvHashMap<String, QueryNode> = FnvHashMap::default();
 
-        // 主节点字段映射表(主节点路径 -> 主节点字段 -> 指向从节点关联字段路径)
-        let mut primary_relate_kv: FnvHashMap<String, HashMap<String, String>> = FnvHashMap::default();
+        // 主节点字段映射表(主节点路径 -> 主节点字段 -> 指向从节点关联字段路径列表)
+        let mut primary_relate_kv: FnvHashMap<String, HashMap<String, Vec<String>>> = FnvHashMap::default();
         // 从节点字段映射表(从节点路径 -> 从节点字段 -> 指向主节点关联字段路径)
         let mut slave_relate_kv: FnvHashMap<String, HashMap<String, String>> = FnvHashMap::default();
 
         // 被关联字段的值(主节点字段路径 -> 主节点字段值(默认Value::Null, 结果是array or object))
         let mut primary_node_related_field_values: FnvHashMap<String, serde_json::Value> = FnvHashMap::default();
+        // 命名空间分页信息引用(响应字段 -> 引用路径)
+        let mut namespace_ref_kv: FnvHashMap<String, String> = FnvHashMap::default();
+        // @explain 调试模式
+        let mut explain = Ok(ExplainMode::Off);
 
         // 处理根节点，区分数组节点和普通节点
         for (key, val) in root {
@@ -92,21 +106,25 @@ impl QueryContext {
                             // 将子对象加入处理队列：
                             // - 父路径: 当前数组节点的key
                             // - 子节点名称: k
-                            // - 子节点值: v 
-                            // - 深度设为2(相对于根节点的深度)
-                            json_vec_deque.push_back((key.clone(), k.clone(), v.clone(), 2));
+                            // - 子节点值: v
+                            json_vec_deque.push_back((key.clone(), k.clone(), v.clone()));
                         }
                     );
                 }
-            } else { // 处理普通节点：直接加入队列，深度为1
+            } else if key == "@explain" {
+                explain = ExplainMode::from_value(&val);
+            } else if let (Some(ref_key), serde_json::Value::String(ref_path)) = (key.strip_suffix('@'), &val) {
+                // 分页信息引用: "total@": "/[]/total"、"info@": "/[]/info"
+                namespace_ref_kv.insert(ref_key.to_string(), ref_path.trim_start_matches('/').to_string());
+            } else { // 处理普通节点：直接加入队列
                 if let Some(_) = val.as_object() {
-                    json_vec_deque.push_back((String::new(), key.clone(), val.clone(), 1));
+                    json_vec_deque.push_back((String::new(), key.clone(), val.clone()));
                 }
             }
         }
 
         // 从队列中依次处理节点，构建查询上下文
-        while let Some((parent_path, name, node_val, depth)) = json_vec_deque.pop_front() {
+        while let Some((parent_path, name, node_val)) = json_vec_deque.pop_front() {
             // 构建节点的完整路径
             let node_path = if parent_path.is_empty() { name.clone() } else { format!("{}/{}", parent_path, name) };
 
@@ -118,7 +136,7 @@ impl QueryContext {
                     map.iter()
                         .filter(|(_, v)| v.is_object())
                         .for_each(|(k, v)| {
-                            json_vec_deque.push_back((node_path.clone(), k.clone(), v.clone(), depth + 1));
+                            json_vec_deque.push_back((node_path.clone(), k.clone(), v.clone()));
                         }
                     );
                 }
@@ -128,7 +146,10 @@ impl QueryContext {
                 let mut is_list = parent_path.ends_with("[]");
                 if let Some(map) = node_val.as_object() {
                     for (field_key, field_value) in map {
-                        if is_scalar_field(field_value) {
+                        // 对象为子节点，其余(标量、数组、null)及子查询 `"字段@": {...}` 均为查询条件
+                        if field_key.ends_with('@') && field_value.is_object() {
+                            attributes.insert(field_key.clone(), field_value.clone());
+                        } else if !field_value.is_object() {
                             // 解析字符串值中的路径引用，建立节点间的关联关系
                             if field_key.ends_with('@') {
                                 let field_name = field_key[..(field_key.len()-1)].to_string();
@@ -144,7 +165,7 @@ impl QueryContext {
                                     let index = primary_field_path.rfind('/').unwrap_or(0);
                                     let primary_node_path = &primary_field_path[..index];
                                     let primary_related_field = &primary_field_path[(index+1)..];
-                                    primary_relate_kv.entry(primary_node_path.to_string()).or_default().insert(primary_related_field.to_string(), field_path.to_string());
+                                    primary_relate_kv.entry(primary_node_path.to_string()).or_default().entry(primary_related_field.to_string()).or_default().push(field_path);
                                 }
                             } else { // 普通查询属性
                                 attributes.insert(field_key.clone(), field_value.clone());
@@ -153,24 +174,43 @@ impl QueryContext {
                     }
                 }
                 
-                // 创建查询节点并添加到对应深度的节点列表中
-                let shared_node = Rc::new(RefCell::new(QueryNode {
+                // 创建查询节点
+                let node = QueryNode {
                     name: (&name).to_string(),
                     path: node_path.clone(),
-                    weight: 0,
                     is_list,
                     attributes,
                     sql_executor: QueryExecutor::new(),
-                }));
-                layer_query_node.entry(depth).or_default().push(shared_node.clone());
-                query_node.insert(node_path, shared_node);
+                };
+                query_node.insert(node_path, node);
             }
         }
 
-        let mut ctx = QueryContext { code: StatusCode::OK, err_msg: None,
-            layer_query_node,
+        // 根据节点间的引用关系构建执行计划，循环引用或引用不存在的节点视为请求错误
+        let (code, err_msg, plan, explain) = match (QueryPlan::build(query_node.keys(), &slave_relate_kv), explain) {
+            (Ok(plan), Ok(explain)) => (StatusCode::OK, None, plan, explain),
+            (Err(err), _) | (_, Err(err)) => (StatusCode::BAD_REQUEST, Some(err), QueryPlan::default(), ExplainMode::Off),
+        };
+        // 命名空间的 JOIN 模式
+        let (code, err_msg, joins) = match (code, parse_namespace_joins(&namespace_node, &slave_relate_kv)) {
+            (StatusCode::OK, Ok(joins)) => (code, err_msg, joins),
+            (StatusCode::OK, Err(err)) => (StatusCode::BAD_REQUEST, Some(err), FnvHashMap::default()),
+            _ => (code, err_msg, FnvHashMap::default()),
+        };
+
+        QueryContext { code, err_msg,
+            plan,
+            explain,
+            explain_nodes: BTreeMap::new(),
+            auth: Auth::default(),
+            limits: Limits::default(),
+            rows: 0,
             namespace_node,
+            namespace_ref_kv,
+            namespace_info: FnvHashMap::default(),
             query_node,
+            joins,
+            join_orphans: FnvHashMap::default(),
 
             primary_relate_kv,
             slave_relate_kv,
@@ -178,46 +218,6 @@ impl QueryContext {
             primary_node_related_field_values,
             slave_node_relate_data: FnvHashMap::default(),
             primary_node_data: FnvHashMap::default(),
-        };
-        ctx.compute_node_weight();
-        ctx
-    }
-
-    /// 计算每个节点的权重
-    fn compute_node_weight(&mut self) {
-        // 收集所有节点引用，将多层嵌套的节点扁平化为一个列表
-        let all_nodes: Vec<Rc<RefCell<QueryNode>>> = self.layer_query_node.values().flatten().cloned().collect();
-
-        // 统计每个节点被依赖的次数，用于后续权重计算
-        let mut counts: HashMap<String, u32> = HashMap::new();
-        for node_rc in &all_nodes {
-            let node_path = node_rc.borrow().path.clone();
-            // 检查当前节点是否有从属关系映射
-            if let Some(relate) = self.slave_relate_kv.get(&node_path) {
-                // 遍历所有从属关系映射值（主节点路径）
-                relate.values().for_each(|parent_path| {
-                    // 更新主节点被依赖计数：
-                    // 1. 如果主节点路径不存在于counts中，则插入并初始化为0
-                    // 2. 对主节点路径的计数加1
-                    *counts.entry(parent_path.clone()).or_insert(0) += 1;
-                });
-            }
-        }
-
-        // 根据节点的依赖关系计算权重
-        // 1. 无依赖的节点获得基础权重 RATIO_PRIMARY
-        // 2. 被依赖的节点获得额外权重 RATIO_RELATED^count
-        for node_rc in &all_nodes {
-            let (path, has_dep) = {
-                let b = node_rc.borrow();
-                let b_path = b.path.clone();
-                let b_relate_kv = self.slave_relate_kv.get(&b_path);
-                (b_path, b_relate_kv.is_some())
-            };
-            let count = counts.get(&path).copied().unwrap_or(0);
-            let addition = if count > 0 { RATIO_RELATED.pow(count) } else { 0 };
-            let weight = if !has_dep { RATIO_PRIMARY + addition } else { addition };
-            node_rc.borrow_mut().weight = weight;
         }
     }
 }
@@ -240,6 +240,160 @@ fn collect_scalar_attrs(v: &serde_json::Value) -> FnvHashMap<String, serde_json:
     }
 }
 
+impl QueryContext {
+    /// 节点是否已并入其他节点的 JOIN 查询
+    pub fn is_joined(&self, node_path: &str) -> bool {
+        self.joins.values().flatten().any(|join| join.node_path == node_path)
+    }
+
+    /// 检查请求结构是否超出开销限制: 嵌套深度、查询节点数、列表 `count`，0 表示不限制
+    pub fn check_limits(&self, limits: &Limits) -> Result<(), String> {
+        // 子查询同样计入节点数和深度
+        let mut node_paths: Vec<&String> = self.query_node.keys().collect();
+        let mut subquery_paths = Vec::new();
+        for (path, node) in &self.query_node {
+            collect_subquery_paths(path, node.attributes.iter(), &mut subquery_paths);
+        }
+        node_paths.extend(subquery_paths.iter());
+        node_paths.sort();
+        if limits.max_depth > 0 {
+            let mut paths: Vec<&String> = node_paths.iter().copied().chain(self.namespace_node.keys()).collect();
+            paths.sort();
+            if let Some(path) = paths.iter().find(|path| path.split('/').count() > limits.max_depth) {
+                return Err(format!("{}: depth {} exceeds the limit {}", path, path.split('/').count(), limits.max_depth));
+            }
+        }
+        if limits.max_nodes > 0 && node_paths.len() > limits.max_nodes {
+            return Err(format!("{}: {} query nodes exceed the limit {}", node_paths[limits.max_nodes], node_paths.len(), limits.max_nodes));
+        }
+        if limits.max_count > 0 {
+            let mut namespaces: Vec<(&String, &FnvHashMap<String, serde_json::Value>)> = self.namespace_node.iter().collect();
+            namespaces.sort_by_key(|(namespace, _)| *namespace);
+            for (namespace, attrs) in namespaces {
+                // 非数字的 count 按默认值处理，不足 1 表示不分页
+                let Some(count) = attrs.get("count").and_then(|count| count.as_f64()) else { continue };
+                if count < 1.0 || count > limits.max_count as f64 {
+                    return Err(format!("{}: count {} is out of range 1..={}", namespace, attrs["count"], limits.max_count));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 节点条件中的子查询路径，如 `[]/timeline.Moment/user_id{}@`，包括嵌套的子查询
+fn collect_subquery_paths<'a>(path: &str, attrs: impl Iterator<Item = (&'a String, &'a serde_json::Value)>, paths: &mut Vec<String>) {
+    for (key, value) in attrs {
+        if let (true, Some(serde_json::Value::String(table_key))) = (key.ends_with('@'), value.get("from")) {
+            let subquery_path = format!("{}/{}", path, key);
+            if let Some(serde_json::Value::Object(sub_attrs)) = value.get(table_key) {
+                collect_subquery_paths(&subquery_path, sub_attrs.iter(), paths);
+            }
+            paths.push(subquery_path);
+        }
+    }
+}
+
+/// 解析所有命名空间的 `join`，返回 主节点路径 -> 连接的从节点
+fn parse_namespace_joins(namespace_node: &FnvHashMap<String, FnvHashMap<String, serde_json::Value>>, slave_relate_kv: &FnvHashMap<String, HashMap<String, String>>) -> Result<FnvHashMap<String, Vec<JoinRef>>, String> {
+    let mut joins = FnvHashMap::default();
+    for (namespace, attrs) in namespace_node {
+        match attrs.get("join") {
+            Some(serde_json::Value::String(join)) => {
+                let refs = parse_join(namespace, join, slave_relate_kv)?;
+                joins.insert(refs[0].primary_path.clone(), refs);
+            }
+            Some(_) => return Err(format!("{}: 'join' expects a string", namespace)),
+            None => {}
+        }
+    }
+    Ok(joins)
+}
+
+/// 解析命名空间的 `join`: `"&/User/id@,</Comment/moment_id@"`
+///
+/// 前缀 `&`、`<`、`>` 分别为 INNER、LEFT、RIGHT JOIN，路径相对于命名空间，指向从节点中的 `字段@` 引用。
+/// 从节点须是命名空间的直接子节点，且都引用命名空间的主节点
+pub fn parse_join(namespace: &str, join: &str, slave_relate_kv: &FnvHashMap<String, HashMap<String, String>>) -> Result<Vec<JoinRef>, String> {
+    let mut refs: Vec<JoinRef> = Vec::new();
+    for item in join.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+        let malformed = || format!("{}: malformed join item '{}'", namespace, item);
+        let mut chars = item.chars();
+        let kind = JoinKind::from_symbol(chars.next().ok_or_else(malformed)?).map_err(|err| format!("{}: {}", namespace, err))?;
+        let path = chars.as_str().strip_prefix('/').and_then(|path| path.strip_suffix('@')).ok_or_else(malformed)?;
+        let (name, field) = path.split_once('/').ok_or_else(malformed)?;
+        if name.is_empty() || field.is_empty() || field.contains('/') {
+            return Err(malformed());
+        }
+
+        let node_path = format!("{}/{}", namespace, name);
+        let relate_kv = slave_relate_kv.get(&node_path).filter(|relate_kv| relate_kv.contains_key(field))
+            .ok_or_else(|| format!("{}: join node '{}' has no '{}@' reference", namespace, node_path, field))?;
+        if relate_kv.len() > 1 {
+            return Err(format!("{}: join node '{}' must reference only one field", namespace, node_path));
+        }
+        if refs.iter().any(|join| join.node_path == node_path) {
+            return Err(format!("{}: join node '{}' is duplicated", namespace, node_path));
+        }
+        let (primary_path, primary_field) = relate_kv[field].rsplit_once('/').ok_or_else(malformed)?;
+        refs.push(JoinRef { kind, node_path, field: field.to_string(), primary_path: primary_path.to_string(), primary_field: primary_field.to_string() });
+    }
+
+    let Some(first) = refs.first() else {
+        return Err(format!("{}: 'join' is empty", namespace));
+    };
+    // 主节点属于当前命名空间，且自身不引用其他节点
+    let is_main_node = get_parent_node_path(&first.primary_path) == namespace && !slave_relate_kv.contains_key(&first.primary_path);
+    if !is_main_node || refs.iter().any(|join| join.primary_path != first.primary_path) {
+        return Err(format!("{}: join nodes must reference the main node of the namespace", namespace));
+    }
+    Ok(refs)
+}
+
+/// 命名空间查询类型，对应 APIJSON 的 `"query": 0|1|2`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NamespaceQuery {
+    // 0: 只查数据
+    Data,
+    // 1: 只查总数
+    Total,
+    // 2: 数据和总数
+    All,
+}
+
+impl NamespaceQuery {
+    pub fn from_value(value: Option<&serde_json::Value>) -> Result<Self, String> {
+        match value.map(|v| v.as_i64()) {
+            None | Some(Some(0)) => Ok(NamespaceQuery::Data),
+            Some(Some(1)) => Ok(NamespaceQuery::Total),
+            Some(Some(2)) => Ok(NamespaceQuery::All),
+            _ => Err(format!("'query' must be 0, 1 or 2, got {}", value.unwrap())),
+        }
+    }
+}
+
+/// `@explain` 调试模式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExplainMode {
+    // 不输出调试信息
+    Off,
+    // 输出每个节点的SQL、参数、行数、耗时和依赖
+    On,
+    // 额外输出 MySQL EXPLAIN 结果
+    Mysql,
+}
+
+impl ExplainMode {
+    pub fn from_value(value: &serde_json::Value) -> Result<Self, String> {
+        match value {
+            serde_json::Value::Bool(false) => Ok(ExplainMode::Off),
+            serde_json::Value::Bool(true) => Ok(ExplainMode::On),
+            serde_json::Value::String(mode) if mode == "mysql" => Ok(ExplainMode::Mysql),
+            _ => Err(format!("'@explain' must be true, false or \"mysql\", got {}", value)),
+        }
+    }
+}
+
 // 获取父节点路径
 // 参数: node_path - 当前节点的完整路径字符串
 // 返回值: 父节点路径字符串，如果没有父节点则返回空字符串
@@ -251,7 +405,10 @@ pub fn get_parent_node_path(node_path: &str) -> String {
 mod tests {
     use common::json::json_to_json_value;
     use common::utils::serde_json_map_to_hashmap;
-    use crate::db::query_context::QueryContext;
+    use std::collections::HashMap;
+    use crate::db::join_executor::JoinKind;
+    use crate::db::query_context::{parse_join, ExplainMode, QueryContext};
+    use common::yaml::Limits;
 
     #[test]
     fn test_query_ctx() {
@@ -275,8 +432,89 @@ mod tests {
 
         let v = json_to_json_value(json_str);
         let ctx = QueryContext::from_json(serde_json_map_to_hashmap(v.as_object().unwrap()));
-        for (k, v) in &ctx.layer_query_node {
-            println!("{}: {:?}", k, v);
+        assert_eq!(ctx.code, http::StatusCode::OK);
+        assert_eq!(ctx.plan.stages, vec![
+            vec!["[]/timeline.Moment", "timeline.Moment"],
+            vec!["[]/Comment[]/timeline.Comment", "[]/timeline.User", "timeline.User"],
+            vec!["[]/Comment[]/timeline.User"],
+        ]);
+    }
+
+    #[test]
+    fn test_parse_join() {
+        let json_str = r#"
+    {
+      "[]":{
+        "join":"&/timeline.User/id@,</timeline.Comment/moment_id@",
+        "timeline.Moment":{},
+        "timeline.User":{ "id@":"[]/timeline.Moment/user_id", "city":"Hangzhou" },
+        "timeline.Comment":{ "moment_id@":"[]/timeline.Moment/id" }
+      }
+    }
+    "#;
+        let v = json_to_json_value(json_str);
+        let ctx = QueryContext::from_json(serde_json_map_to_hashmap(v.as_object().unwrap()));
+        assert_eq!(ctx.code, http::StatusCode::OK);
+        let joins = &ctx.joins["[]/timeline.Moment"];
+        assert_eq!(joins.iter().map(|join| (join.kind, join.node_path.as_str(), join.primary_field.as_str())).collect::<Vec<_>>(), vec![
+            (JoinKind::Inner, "[]/timeline.User", "user_id"),
+            (JoinKind::Left, "[]/timeline.Comment", "id"),
+        ]);
+        assert!(ctx.is_joined("[]/timeline.User"));
+
+        let relate_kv = ctx.slave_relate_kv;
+        assert!(parse_join("[]", "|/timeline.User/id@", &relate_kv).is_err());
+        assert!(parse_join("[]", "&/timeline.User/user_id@", &relate_kv).is_err());
+        assert!(parse_join("[]", "&timeline.User/id@", &relate_kv).is_err());
+    }
+
+    #[test]
+    fn test_check_limits() {
+        let json_str = r#"
+    {
+      "[]":{
+        "count":20,
+        "timeline.Moment":{},
+        "Comment[]":{
+          "count":0,
+          "timeline.Comment":{"moment_id@":"[]/timeline.Moment/id"}
         }
+      }
+    }
+    "#;
+        let v = json_to_json_value(json_str);
+        let ctx = QueryContext::from_json(serde_json_map_to_hashmap(v.as_object().unwrap()));
+        let limits = Limits { max_depth: 3, max_nodes: 2, max_count: 20, ..Limits::default() };
+        assert_eq!(ctx.check_limits(&limits), Err("[]/Comment[]: count 0 is out of range 1..=20".to_string()));
+        let limits = Limits { max_count: 0, ..limits };
+        assert_eq!(ctx.check_limits(&limits), Ok(()));
+        let limits = Limits { max_depth: 2, ..limits };
+        assert_eq!(ctx.check_limits(&limits), Err("[]/Comment[]/timeline.Comment: depth 3 exceeds the limit 2".to_string()));
+        let limits = Limits { max_depth: 0, max_nodes: 1, ..limits };
+        assert_eq!(ctx.check_limits(&limits), Err("[]/timeline.Moment: 2 query nodes exceed the limit 1".to_string()));
+
+        // 嵌套的子查询
+        let ctx = QueryContext::from_json(serde_json::from_value(serde_json::json!({
+            "timeline.Moment": { "user_id{}@": { "from": "timeline.User", "timeline.User": { "@column": "id", "id{}@": { "from": "timeline.Comment", "timeline.Comment": { "@column": "user_id" } } } } }
+        })).unwrap());
+        let limits = Limits { max_depth: 3, max_nodes: 3, ..Limits::default() };
+        assert_eq!(ctx.check_limits(&limits), Ok(()));
+        assert_eq!(ctx.check_limits(&Limits { max_depth: 2, ..limits.clone() }), Err("timeline.Moment/user_id{}@/id{}@: depth 3 exceeds the limit 2".to_string()));
+        assert_eq!(ctx.check_limits(&Limits { max_nodes: 2, ..limits }), Err("timeline.Moment/user_id{}@/id{}@: 3 query nodes exceed the limit 2".to_string()));
+    }
+
+    #[test]
+    fn test_query_ctx_send() {
+        // 同一阶段的节点并发查询，查询上下文需要能跨线程使用
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<QueryContext>();
+    }
+
+    #[test]
+    fn test_explain_mode() {
+        let ctx = QueryContext::from_json(HashMap::from([("@explain".to_string(), serde_json::json!("mysql"))]));
+        assert_eq!(ctx.explain, ExplainMode::Mysql);
+        let ctx = QueryContext::from_json(HashMap::from([("@explain".to_string(), serde_json::json!(1))]));
+        assert_eq!(ctx.code, http::StatusCode::BAD_REQUEST);
     }
 }
@@ -1,18 +1,51 @@
 use std::collections::HashMap;
-use database::core::{get_table, DBConn};
+use database::TableMeta;
+use database::core::DBConn;
+use database::param::SqlParam;
+use common::utils::{base64_decode, base64_encode};
+use crate::exposure::{has_hidden_columns, visible_table};
+use crate::limits::query_limits;
+use crate::policy::{policy_filter, Auth, PolicyAction};
+use crate::verify::{parse_aggregate, parse_column_expr, parse_order_items, quote_ident, quote_table, resolve_column, resolve_json_column, resolve_json_path, OrderItem};
 
 pub const DEFAULT_MAX_COUNT: usize = 10;
+/// 分组取前N条时的行号列
+const ROW_NUMBER_COLUMN: &str = "__rn";
+/// 子查询中表的别名前缀，后接嵌套层数，相关子查询以此区分外层表
+const SUBQUERY_ALIAS: &str = "__sq";
 
 #[derive(Debug, Clone)]
 pub struct QueryExecutor {
     schema: String,
     table: String,
+    table_meta: Option<TableMeta>,
+    // 表中有隐藏字段，不能使用 `*` 查询
+    hidden_columns: bool,
+    distinct: bool,
     columns: Vec<String>,
+    // 结果中的字段名，与 columns 一一对应
+    labels: Vec<String>,
+    // @column 中定义的别名
+    aliases: Vec<String>,
     where_clauses: Vec<String>,
     params: Vec<serde_json::Value>,
-    order: Option<String>,
+    group: Vec<String>,
+    having_clauses: Vec<String>,
+    having_params: Vec<serde_json::Value>,
+    order: Vec<OrderItem>,
+    // 游标分页的排序键(@order 字段 + 主键)，None 表示 OFFSET 分页
+    keyset: Option<Vec<OrderItem>>,
+    // 游标条件及参数，只作用于数据查询，不影响总数统计
+    keyset_clause: Option<String>,
+    keyset_params: Vec<serde_json::Value>,
+    // 分组取前N条的分区字段，按关联字段分区后每个分区分别分页
+    partition: Vec<String>,
     page: i32,
     limit: i32,
+    // MySQL MAX_EXECUTION_TIME 提示，单位毫秒，0 表示不限制
+    max_execution_time: u64,
+    // 子查询的嵌套层数，0 表示最外层查询
+    subquery_depth: usize,
 }
 
 impl QueryExecutor {
@@ -20,47 +53,90 @@ impl QueryExecutor {
         QueryExecutor {
             schema: String::new(),
             table: String::new(),
+            table_meta: None,
+            hidden_columns: false,
+            distinct: false,
             columns: vec![],
+            labels: vec![],
+            aliases: vec![],
             where_clauses: vec![],
             params: vec![],
-            order: None,
+            group: vec![],
+            having_clauses: vec![],
+            having_params: vec![],
+            order: vec![],
+            keyset: None,
+            keyset_clause: None,
+            keyset_params: vec![],
+            partition: vec![],
             page: 0,
             limit: 1,
+            max_execution_time: 0,
+            subquery_depth: 0,
         }
     }
 
+    #[cfg(test)]
+    pub(crate) fn with_table(table: TableMeta) -> Self {
+        let mut executor = QueryExecutor::new();
+        executor.schema = table.schema.clone();
+        executor.table = table.name.clone();
+        executor.table_meta = Some(table);
+        executor
+    }
+
     pub async fn exec(&self, db: &DBConn) -> Result<Vec<HashMap<String, serde_json::Value>>, sqlx::Error> {
         let sql = self.to_sql();
-        log::info!("sql.exec: {}, params: {}", sql, serde_json::to_string(&self.params).unwrap());
-        let params: Vec<String> = self.params.iter()
-            .map(|v| match v {
-                serde_json::Value::Null => "NULL".to_string(),
-                serde_json::Value::String(s) => s.clone(),
-                serde_json::Value::Array(_) | serde_json::Value::Object(_) =>
-                    serde_json::to_string(v).unwrap_or_else(|_| "NULL".to_string()),
-                _ => v.to_string(),
-            })
-            .collect();
-        db.query_list(&sql, params).await
+        let bind_params = self.bind_params();
+        log::info!("sql.exec: {}, params: {}", sql, serde_json::to_string(&bind_params).unwrap());
+        let mut rows = db.query_list(&sql, Self::to_sql_params(&bind_params)).await?;
+        if !self.partition.is_empty() {
+            rows.iter_mut().for_each(|row| { row.remove(ROW_NUMBER_COLUMN); });
+        }
+        Ok(rows)
     }
 
-    pub fn to_sql(&self) -> String {
-        let mut sql = String::from("SELECT ");
-        if self.columns.is_empty() {
-            sql.push('*');
-        } else {
-            sql.push_str(&self.columns.join(","));
+    /// 统计与当前查询条件匹配的总行数，忽略排序和分页
+    pub async fn exec_count(&self, db: &DBConn) -> Result<i64, sqlx::Error> {
+        let sql = self.to_count_sql();
+        let bind_params = self.count_params();
+        log::info!("sql.count: {}, params: {}", sql, serde_json::to_string(&bind_params).unwrap());
+        db.count(&sql, Self::to_sql_params(&bind_params)).await
+    }
+
+    /// 执行 MySQL `EXPLAIN`，返回查询计划
+    pub async fn explain(&self, db: &DBConn) -> Result<Vec<HashMap<String, serde_json::Value>>, sqlx::Error> {
+        let sql = format!("EXPLAIN {}", self.to_sql());
+        db.query_list(&sql, Self::to_sql_params(&self.bind_params())).await
+    }
+
+    /// 按 JSON 值的类型转换为语句参数，`null` 绑定为 SQL NULL
+    pub(crate) fn to_sql_params(params: &[serde_json::Value]) -> Vec<SqlParam> {
+        params.iter().map(SqlParam::from).collect()
+    }
+
+    /// 设置语句的最长执行时间，单位毫秒，0 表示不限制
+    pub fn max_execution_time(&mut self, millis: u64) {
+        self.max_execution_time = millis;
+    }
+
+    /// 在最外层 SELECT 中加上 `MAX_EXECUTION_TIME` 优化器提示，超时的语句由 MySQL 中断
+    pub(crate) fn with_hint(&self, sql: String) -> String {
+        match (self.max_execution_time, sql.strip_prefix("SELECT ")) {
+            (0, _) | (_, None) => sql,
+            (millis, Some(rest)) => format!("SELECT /*+ MAX_EXECUTION_TIME({}) */ {}", millis, rest),
         }
-        sql.push_str(" FROM ");
-        sql.push_str(format!("{}.{}", self.schema, self.table).as_str());
-        if !self.where_clauses.is_empty() {
-            sql.push_str(" WHERE ");
-            sql.push_str(&self.where_clauses.join(" AND "));
+    }
+
+    pub fn to_sql(&self) -> String {
+        if !self.partition.is_empty() {
+            return self.with_hint(self.to_partition_sql());
         }
+        let mut sql = self.to_select_sql(true);
 
-        if let Some(order) = &self.order {
+        if !self.order.is_empty() {
             sql.push_str(" ORDER BY ");
-            sql.push_str(order);
+            sql.push_str(&self.order_sql());
         }
 
         if self.limit > 0 {
@@ -70,62 +146,432 @@ impl QueryExecutor {
             sql.push_str(" OFFSET ");
             sql.push_str(&(self.limit * self.page).to_string());
         }
+        self.with_hint(sql)
+    }
+
+    /// 分组取前N条: 按分区字段编号，每个分区按 `page`、`limit` 截取，需要 MySQL 8.0+
+    fn to_partition_sql(&self) -> String {
+        let columns = if self.columns.is_empty() { self.all_columns_sql(true) } else { self.columns.join(",") };
+        // 以主键兜底排序，否则每组取到哪些行、各页的分界都不确定
+        let mut order = self.order.clone();
+        if let Ok(primary_key) = self.primary_key() {
+            if !order.iter().any(|item| item.column == primary_key) {
+                order.push(OrderItem { column: primary_key, desc: false, alias: false });
+            }
+        }
+        let order = match order.is_empty() {
+            true => String::new(),
+            false => format!(" ORDER BY {}", order.iter().map(OrderItem::to_sql).collect::<Vec<_>>().join(",")),
+        };
+        let row_number = quote_ident(ROW_NUMBER_COLUMN);
+        let inner_sql = format!("SELECT {},ROW_NUMBER() OVER (PARTITION BY {}{}) AS {}{}",
+            columns, self.partition.join(","), order, row_number, self.table_clause(true));
+
+        let mut sql = format!("SELECT * FROM ({}) AS t", inner_sql);
+        if self.limit > 0 {
+            let offset = self.limit * self.page;
+            sql.push_str(&format!(" WHERE {}>{} AND {}<={}", row_number, offset, row_number, offset + self.limit));
+        }
+        sql.push_str(&format!(" ORDER BY {},{}", self.partition.join(","), row_number));
+        sql
+    }
+
+    /// 未指定 `@column` 时查询所有字段，表中有隐藏字段时逐个列出可见字段
+    fn all_columns_sql(&self, qualified: bool) -> String {
+        match (&self.table_meta, self.hidden_columns) {
+            (Some(table), true) => {
+                let mut columns: Vec<String> = table.columns.keys().map(|column| quote_ident(column)).collect();
+                columns.sort();
+                columns.join(",")
+            }
+            _ if qualified => format!("{}.*", quote_table(&self.schema, &self.table)),
+            _ => "*".to_string(),
+        }
+    }
+
+    fn order_sql(&self) -> String {
+        self.order.iter().map(OrderItem::to_sql).collect::<Vec<_>>().join(",")
+    }
+
+    /// 生成 COUNT 语句，分组或去重时统计子查询的行数
+    pub fn to_count_sql(&self) -> String {
+        let sql = if self.distinct || !self.group.is_empty() || !self.having_clauses.is_empty() {
+            format!("SELECT count(*) FROM ({}) AS t", self.to_select_sql(false))
+        } else {
+            format!("SELECT count(*){}", self.table_clause(false))
+        };
+        self.with_hint(sql)
+    }
+
+    /// 生成不含排序和分页的 SELECT 语句，`with_keyset` 表示是否带上游标条件
+    pub(crate) fn to_select_sql(&self, with_keyset: bool) -> String {
+        let mut sql = String::from("SELECT ");
+        if self.distinct {
+            sql.push_str("DISTINCT ");
+        }
+        if self.columns.is_empty() {
+            sql.push_str(&self.all_columns_sql(false));
+        } else {
+            sql.push_str(&self.columns.join(","));
+        }
+        sql.push_str(&self.table_clause(with_keyset));
         sql
     }
+
+    /// FROM ... WHERE ... GROUP BY ... HAVING ...
+    fn table_clause(&self, with_keyset: bool) -> String {
+        let mut sql = String::from(" FROM ");
+        sql.push_str(&quote_table(&self.schema, &self.table));
+        if self.subquery_depth > 0 {
+            sql.push_str(" AS ");
+            sql.push_str(&self.table_ref());
+        }
+        let mut where_clauses: Vec<&str> = self.where_clauses.iter().map(String::as_str).collect();
+        if let (true, Some(keyset_clause)) = (with_keyset, &self.keyset_clause) {
+            where_clauses.push(keyset_clause);
+        }
+        if !where_clauses.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&where_clauses.join(" AND "));
+        }
+
+        if !self.group.is_empty() {
+            sql.push_str(" GROUP BY ");
+            sql.push_str(&self.group.join(","));
+        }
+        if !self.having_clauses.is_empty() {
+            sql.push_str(" HAVING ");
+            sql.push_str(&self.having_clauses.join(" AND "));
+        }
+        sql
+    }
+
+    /// 按SQL中占位符的顺序返回绑定参数: WHERE、游标条件在前，HAVING 在后
+    pub fn bind_params(&self) -> Vec<serde_json::Value> {
+        self.params.iter().chain(self.keyset_params.iter()).chain(self.having_params.iter()).cloned().collect()
+    }
+
+    /// 总数统计的绑定参数，不含游标条件
+    pub fn count_params(&self) -> Vec<serde_json::Value> {
+        self.params.iter().chain(self.having_params.iter()).cloned().collect()
+    }
     
     pub fn parse_table(&mut self, table_key: &str) -> Result<(), String> {
-        let table_key = if table_key.ends_with("[]") { &table_key[..table_key.len()-2] } else { table_key };
-        let schema_table_vec = table_key.split(".").collect::<Vec<&str>>();
-        let schema = schema_table_vec[0];
-        let table = schema_table_vec[1];
-        match get_table(&schema, table) {
-            Some(table) => {
-                self.table = table.name.clone();
-                self.schema = table.schema.clone();
-                Ok(())
-            },
-            None => Err(format!("table: {} not exists", table_key))
-        }
-    }
-
-    pub fn parse_condition(&mut self, field: &str, value: &serde_json::Value) {
-        if field.starts_with('@') {
-            match &field[1..] {
-                "order" => {
-                    if let serde_json::Value::String(order) = value {
-                        self.order = Some(order.to_string());
-                    }
-                }
-                "column" => {
-                    if let serde_json::Value::String(cols) = value {
-                        self.columns.extend(cols.split(',').map(|s| s.trim().to_string().to_lowercase()));
-                    }
+        let table_key = table_key.strip_suffix("[]").unwrap_or(table_key);
+        // 隐藏的表视为不存在，隐藏字段不可查询
+        let table = visible_table(table_key)?;
+        self.hidden_columns = has_hidden_columns(&table);
+        self.table = table.name.clone();
+        self.schema = table.schema.clone();
+        self.table_meta = Some(table);
+        Ok(())
+    }
+
+    /// 追加请求者在当前表上生效的行级安全策略条件
+    pub fn apply_policy(&mut self, action: PolicyAction, auth: &Auth) -> Result<(), String> {
+        let filter = policy_filter(self.table_meta()?, action, auth);
+        self.where_clauses.extend(filter.clauses);
+        self.params.extend(filter.params);
+        Ok(())
+    }
+
+    /// 解析单个查询条件，兼容 APIJSON 条件语法
+    ///
+    /// * `"field": value` - 等值，数组为 `IN (...)`，`null` 为 `IS NULL`
+    /// * `"field!": value` - 取反，`!=` / `NOT IN` / `IS NOT NULL`
+    /// * `"field{}": [..]` - `IN (...)`；`"field{}": ">=10,<20"` 范围表达式，逗号分隔的条件以 `OR` 连接
+    /// * `"field&{}": ">=10,<20"` / `"field|{}": "<10,>20"` - 范围表达式以 `AND` / `OR` 连接
+    /// * `"field$": "%a%"` - `LIKE`，`"field~": "^a"` - `REGEXP`，数组值以 `OR` 连接
+    /// * `"field>": 1` / `"field>="` / `"field<"` / `"field<="` - 比较
+    /// * `"json_field.key": value` - JSON 字段中的值，条件与普通字段相同，值为数字时按数值比较
+    /// * `"json_field<>": value` / `"json_field.key<>": value` - `JSON_CONTAINS`，包含给定的 JSON 值
+    ///
+    /// 以及功能键 `@column`、`@group`、`@having`、`@order`，调用方需保证 `@column` 先于 `@having` 解析
+    pub fn parse_condition(&mut self, field: &str, value: &serde_json::Value) -> Result<(), String> {
+        if let Some(keyword) = field.strip_prefix('@') {
+            match (keyword, value) {
+                ("order", serde_json::Value::String(order)) => {
+                    self.order = parse_order_items(self.table_meta()?, &self.aliases, order)?;
                 }
+                ("column", serde_json::Value::String(cols)) => self.parse_column(cols)?,
+                ("group", serde_json::Value::String(group)) => self.parse_group(group)?,
+                ("having", serde_json::Value::String(having)) => self.parse_having(having)?,
+                ("order" | "column" | "group" | "having", _) => return Err(format!("'{}' expects a string", field)),
                 _ => {}
             }
-            return;
+            return Ok(());
         }
-        if field.ends_with('$') {
-            let actual_field = &field[..field.len() - 1];
-            self.where_clauses.push(format!("{} LIKE ?", actual_field));
-            self.params.push(value.to_owned());
-            return;
+
+        let (name, op, negate) = split_condition_key(field);
+        if name.is_empty() {
+            return Err(format!("condition key '{}' has no field", field));
         }
-        match value {
-            serde_json::Value::Array(values) => {
-                let mut condition = format!("{} in (", field);
-                let placeholders: Vec<_> = (0..values.len())
-                    .map(|i| if i == 0 { "?" } else { ",?" })
-                    .collect();
-                condition.push_str(&placeholders.join(""));
-                condition.push(')');
+        let column = &match name.contains('.') {
+            // JSON 路径取出的值为文本，数值条件转换为数值比较
+            true => {
+                let path = resolve_json_path(self.table_meta()?, name)?.text_sql();
+                if is_numeric_condition(op, value) { format!("CAST({} AS DOUBLE)", path) } else { path }
+            }
+            false => quote_ident(&resolve_column(self.table_meta()?, name)?),
+        };
+
+        let condition = match (op, value) {
+            // JSON 包含
+            (ConditionOp::Contains, _) => return self.parse_json_contains(field, name, negate, value),
+            // null -> IS [NOT] NULL
+            (ConditionOp::Eq, serde_json::Value::Null) => {
+                let condition = if negate { format!("{} IS NOT NULL", column) } else { format!("{} IS NULL", column) };
                 self.where_clauses.push(condition);
+                return Ok(());
+            }
+            (_, serde_json::Value::Null) => return Err(format!("condition '{}' does not accept null", field)),
+            // 数组 -> IN (...)
+            (ConditionOp::Eq | ConditionOp::In | ConditionOp::AndIn | ConditionOp::OrIn, serde_json::Value::Array(values)) => {
+                if values.is_empty() {
+                    return Err(format!("condition '{}' has an empty array", field));
+                }
+                if values.iter().any(|v| v.is_array() || v.is_object()) {
+                    return Err(format!("condition '{}' only accepts scalar array items", field));
+                }
+                let placeholders = vec!["?"; values.len()].join(",");
                 self.params.extend(values.to_owned());
+                format!("{} in ({})", column, placeholders)
             }
-            _ => {
-                self.where_clauses.push(format!("{}=?", field));
+            // 范围表达式 -> (cond OR cond) / (cond AND cond)
+            (ConditionOp::In | ConditionOp::OrIn, serde_json::Value::String(expr)) => self.parse_range_expr(field, column, expr, " OR ")?,
+            (ConditionOp::AndIn, serde_json::Value::String(expr)) => self.parse_range_expr(field, column, expr, " AND ")?,
+            (ConditionOp::In | ConditionOp::AndIn | ConditionOp::OrIn, _) => return Err(format!("condition '{}' expects an array or a range expression", field)),
+            // LIKE / REGEXP，数组值以 OR 连接
+            (ConditionOp::Like | ConditionOp::Regexp, _) => {
+                let keyword = if op == ConditionOp::Like { "LIKE" } else { "REGEXP" };
+                let patterns = match value {
+                    serde_json::Value::Array(values) => values.to_owned(),
+                    _ => vec![value.to_owned()],
+                };
+                if patterns.is_empty() || patterns.iter().any(|v| !v.is_string()) {
+                    return Err(format!("condition '{}' expects string patterns", field));
+                }
+                let clauses: Vec<_> = (0..patterns.len()).map(|_| format!("{} {} ?", column, keyword)).collect();
+                self.params.extend(patterns);
+                if clauses.len() == 1 { clauses[0].clone() } else { format!("({})", clauses.join(" OR ")) }
+            }
+            (_, serde_json::Value::Array(_) | serde_json::Value::Object(_)) => return Err(format!("condition '{}' expects a scalar value", field)),
+            // 比较
+            (ConditionOp::Eq, _) => {
+                self.params.push(value.to_owned());
+                let compare = if negate { "!=" } else { "=" };
+                self.where_clauses.push(format!("{}{}?", column, compare));
+                return Ok(());
+            }
+            (ConditionOp::Gt | ConditionOp::Ge | ConditionOp::Lt | ConditionOp::Le, _) => {
                 self.params.push(value.to_owned());
+                format!("{}{}?", column, op.compare_symbol())
+            }
+        };
+
+        if negate {
+            self.where_clauses.push(format!("NOT ({})", condition));
+        } else {
+            self.where_clauses.push(condition);
+        }
+        Ok(())
+    }
+
+    /// `JSON_CONTAINS`: JSON 字段或其中的路径包含给定的 JSON 值，值以 JSON 文本绑定
+    fn parse_json_contains(&mut self, field: &str, column: &str, negate: bool, value: &serde_json::Value) -> Result<(), String> {
+        if value.is_null() {
+            return Err(format!("condition '{}' does not accept null", field));
+        }
+        let condition = if column.contains('.') {
+            let path = resolve_json_path(self.table_meta()?, column)?;
+            format!("JSON_CONTAINS({},?,'{}')", quote_ident(&path.column), path.path)
+        } else {
+            format!("JSON_CONTAINS({},?)", quote_ident(&resolve_json_column(self.table_meta()?, column)?))
+        };
+        self.params.push(serde_json::Value::String(value.to_string()));
+        self.where_clauses.push(if negate { format!("NOT {}", condition) } else { condition });
+        Ok(())
+    }
+
+    /// 解析子查询条件，兼容 APIJSON 子查询语法，子查询由数据库执行，参数随外层语句一起绑定
+    ///
+    /// * `"field{}@": {"from": "schema.Table", "schema.Table": {"@column": "col", ...}}` - `IN (SELECT ...)`，`"field!{}@"` 为 `NOT IN`
+    /// * `"field@"` / `"field!@"` / `"field>@"` / `"field>=@"` / `"field<@"` / `"field<=@"` - 与单值子查询比较
+    /// * `"field}{@"` / `"field!}{@"` - `EXISTS` / `NOT EXISTS`，字段名只用于区分多个条件
+    ///
+    /// 子查询节点中的条件与普通节点相同，`"col@": "/outer_col"` 引用外层表的字段构成相关子查询，
+    /// 值为对象的 `"col{}@"` 等为嵌套的子查询；子查询的表同样受暴露控制和行级安全策略限制
+    pub fn parse_subquery(&mut self, field: &str, value: &serde_json::Value, auth: &Auth) -> Result<(), String> {
+        let subquery_node = value.as_object().ok_or_else(|| format!("subquery '{}' expects an object", field))?;
+        let table_key = match subquery_node.get("from") {
+            Some(serde_json::Value::String(table_key)) => table_key,
+            _ => return Err(format!("subquery '{}' expects 'from' naming a table", field)),
+        };
+        if let Some(key) = subquery_node.keys().find(|key| *key != "from" && *key != table_key) {
+            return Err(format!("subquery '{}' has unknown key '{}'", field, key));
+        }
+        let attrs = match subquery_node.get(table_key) {
+            Some(serde_json::Value::Object(attrs)) => attrs.clone(),
+            Some(_) => return Err(format!("subquery '{}' expects '{}' to be an object", field, table_key)),
+            None => serde_json::Map::new(),
+        };
+
+        // 子查询的嵌套层数不超过 `restful.limits.max_depth`
+        let max_depth = query_limits().max_depth;
+        if max_depth > 0 && self.subquery_depth >= max_depth {
+            return Err(format!("subquery '{}' exceeds the depth limit {}", field, max_depth));
+        }
+        let mut subquery = QueryExecutor::new();
+        subquery.subquery_depth = self.subquery_depth + 1;
+        subquery.parse_table(&table_key.to_lowercase())?;
+        subquery.apply_policy(PolicyAction::Select, auth)?;
+        // 按键排序保证生成的SQL及参数顺序稳定，`@column` 先于 `@having` 解析
+        let mut attr_keys: Vec<&String> = attrs.keys().collect();
+        attr_keys.sort();
+        for attr_key in attr_keys {
+            let attr_value = &attrs[attr_key];
+            let parsed = match (attr_key.strip_suffix('@'), attr_value) {
+                (Some(_), serde_json::Value::Object(_)) => subquery.parse_subquery(attr_key, attr_value, auth),
+                (Some(column), serde_json::Value::String(outer_field)) => subquery.correlate(column, outer_field, self),
+                (Some(_), _) => Err(format!("'{}' expects a subquery or an outer field like '/id'", attr_key)),
+                (None, _) => subquery.parse_condition(attr_key, attr_value),
+            };
+            parsed.map_err(|err| format!("{}: {}", field, err))?;
+        }
+        self.add_subquery(field, &subquery)
+    }
+
+    /// 相关子查询: 子查询的字段等于外层表的字段，`outer_field` 形如 `/id`
+    fn correlate(&mut self, column: &str, outer_field: &str, outer: &QueryExecutor) -> Result<(), String> {
+        let outer_column = outer_field.strip_prefix('/')
+            .ok_or_else(|| format!("'{}@' expects an outer field like '/id'", column))?;
+        let column = quote_ident(&resolve_column(self.table_meta()?, column)?);
+        let outer_column = quote_ident(&resolve_column(outer.table_meta()?, outer_column)?);
+        self.where_clauses.push(format!("{}={}.{}", column, outer.table_ref(), outer_column));
+        Ok(())
+    }
+
+    /// 按条件键将子查询加入 WHERE 条件，子查询的参数按出现的位置绑定
+    fn add_subquery(&mut self, field: &str, subquery: &QueryExecutor) -> Result<(), String> {
+        let key = field.strip_suffix('@').ok_or_else(|| format!("subquery key '{}' must end with '@'", field))?;
+        let condition = if let Some(rest) = key.strip_suffix("}{") {
+            let exists = if rest.ends_with('!') { "NOT EXISTS" } else { "EXISTS" };
+            format!("{} (SELECT 1{})", exists, subquery.table_clause(false))
+        } else {
+            let (column, op, negate) = split_condition_key(key);
+            if column.is_empty() {
+                return Err(format!("condition key '{}' has no field", field));
             }
+            let column = quote_ident(&resolve_column(self.table_meta()?, column)?);
+            let [select_column] = subquery.columns.as_slice() else {
+                return Err(format!("subquery '{}' expects exactly one column in @column", field));
+            };
+            let distinct = if subquery.distinct { "DISTINCT " } else { "" };
+            let sql = format!("SELECT {}{}{}", distinct, select_column, subquery.table_clause(false));
+            match (op, negate) {
+                (ConditionOp::In, false) => format!("{} IN ({})", column, sql),
+                (ConditionOp::In, true) => format!("{} NOT IN ({})", column, sql),
+                (ConditionOp::Eq, false) => format!("{}=({})", column, sql),
+                (ConditionOp::Eq, true) => format!("{}!=({})", column, sql),
+                (ConditionOp::Gt | ConditionOp::Ge | ConditionOp::Lt | ConditionOp::Le, false) => format!("{}{}({})", column, op.compare_symbol(), sql),
+                (ConditionOp::Gt | ConditionOp::Ge | ConditionOp::Lt | ConditionOp::Le, true) => format!("NOT ({}{}({}))", column, op.compare_symbol(), sql),
+                _ => return Err(format!("condition '{}' does not support subqueries", field)),
+            }
+        };
+        self.where_clauses.push(condition);
+        self.params.extend(subquery.count_params());
+        Ok(())
+    }
+
+    /// 在SQL中引用当前表的名称，子查询中为别名
+    fn table_ref(&self) -> String {
+        if self.subquery_depth > 0 {
+            quote_ident(&format!("{}{}", SUBQUERY_ALIAS, self.subquery_depth))
+        } else {
+            quote_table(&self.schema, &self.table)
+        }
+    }
+
+    fn table_meta(&self) -> Result<&TableMeta, String> {
+        self.table_meta.as_ref().ok_or_else(|| format!("table {}.{} is not resolved", self.schema, self.table))
+    }
+
+    /// 解析 `@column`: `"DISTINCT category"`、`"category,count(id):cnt,sum(price):total"`
+    fn parse_column(&mut self, cols: &str) -> Result<(), String> {
+        let cols = cols.trim();
+        let cols = match cols.split_once(char::is_whitespace) {
+            Some((keyword, rest)) if keyword.eq_ignore_ascii_case("distinct") => {
+                self.distinct = true;
+                rest
+            }
+            _ => cols,
+        };
+        let table = self.table_meta()?.clone();
+        for item in cols.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            let column = parse_column_expr(&table, item)?;
+            if let Some(alias) = column.alias {
+                self.aliases.push(alias);
+            }
+            self.columns.push(column.sql);
+            self.labels.push(column.name);
+        }
+        Ok(())
+    }
+
+    /// 解析 `@group`: `"category,brand"`
+    fn parse_group(&mut self, group: &str) -> Result<(), String> {
+        let table = self.table_meta()?.clone();
+        for column in group.split(',').map(str::trim).filter(|column| !column.is_empty()) {
+            self.group.push(quote_ident(&resolve_column(&table, column)?));
+        }
+        Ok(())
+    }
+
+    /// 解析 `@having`: `"count(id)>1;sum(price)<=100"`，分号分隔的条件以 `AND` 连接，
+    /// 左侧可以是聚合表达式、`@column` 中的别名或字段
+    fn parse_having(&mut self, having: &str) -> Result<(), String> {
+        let table = self.table_meta()?.clone();
+        for item in having.split(';').map(str::trim).filter(|item| !item.is_empty()) {
+            let malformed = || format!("malformed @having item '{}'", item);
+            let index = item.find(['>', '<', '=', '!']).ok_or_else(malformed)?;
+            let (lhs, rest) = (item[..index].trim(), &item[index..]);
+            let (compare, raw_value) = RANGE_COMPARES.iter()
+                .find_map(|compare| rest.strip_prefix(compare).map(|value| (*compare, value.trim())))
+                .ok_or_else(malformed)?;
+            if lhs.is_empty() || raw_value.is_empty() {
+                return Err(malformed());
+            }
+
+            let lhs = if lhs.contains('(') {
+                parse_aggregate(&table, lhs)?.0
+            } else if self.aliases.iter().any(|alias| alias == lhs) {
+                quote_ident(lhs)
+            } else {
+                quote_ident(&resolve_column(&table, lhs)?)
+            };
+            self.having_clauses.push(format!("{}{}?", lhs, compare));
+            self.having_params.push(parse_range_value(raw_value));
+        }
+        Ok(())
+    }
+
+    /// 解析范围表达式，如 `">=10,<20"`，每一项需以比较符开头
+    fn parse_range_expr(&mut self, field: &str, column: &str, expr: &str, joiner: &str) -> Result<String, String> {
+        let mut clauses = Vec::new();
+        for item in expr.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            let (compare, raw_value) = RANGE_COMPARES.iter()
+                .find_map(|compare| item.strip_prefix(compare).map(|rest| (*compare, rest.trim())))
+                .ok_or_else(|| format!("condition '{}' has malformed range item '{}'", field, item))?;
+            if raw_value.is_empty() {
+                return Err(format!("condition '{}' has malformed range item '{}'", field, item));
+            }
+            clauses.push(format!("{}{}?", column, compare));
+            self.params.push(parse_range_value(raw_value));
+        }
+        match clauses.len() {
+            0 => Err(format!("condition '{}' has an empty range expression", field)),
+            1 => Ok(clauses.remove(0)),
+            _ => Ok(format!("({})", clauses.join(joiner))),
         }
     }
 
@@ -134,6 +580,113 @@ impl QueryExecutor {
         self.limit = Self::parse_num(&count, 10);
     }
 
+    /// 启用游标分页: 以 `@order` 字段加主键作为排序键，`cursor` 为上一页返回的 `next_cursor`，空字符串表示第一页
+    pub fn keyset_page(&mut self, cursor: &str) -> Result<(), String> {
+        if self.is_grouped() {
+            return Err("cursor pagination does not support DISTINCT, @group or @having".to_string());
+        }
+        if let Some(item) = self.order.iter().find(|item| item.alias) {
+            return Err(format!("cursor pagination can not order by alias '{}'", item.column));
+        }
+        let primary_key = self.primary_key().map_err(|err| format!("cursor pagination requires {}", err))?;
+        // NULL 不能用 > < 比较，也不能写入游标，排序字段须为 NOT NULL
+        let table = self.table_meta()?;
+        let nullable = |item: &&OrderItem| item.column != primary_key && table.columns.get(&item.column).is_some_and(|column| column.null.as_deref() == Some("YES"));
+        if let Some(item) = self.order.iter().find(nullable) {
+            return Err(format!("cursor pagination can not order by nullable column '{}'", item.column));
+        }
+
+        // 排序键必须唯一，未按主键排序时以主键兜底，方向与最后一个排序字段一致
+        let mut keys = self.order.clone();
+        if !keys.iter().any(|item| item.column == primary_key) {
+            let desc = keys.last().is_some_and(|item| item.desc);
+            keys.push(OrderItem { column: primary_key, desc, alias: false });
+        }
+
+        // 游标条件: (a>?) OR (a=? AND b>?) ...，降序字段使用 <
+        if !cursor.is_empty() {
+            let values = decode_cursor(cursor, keys.len())?;
+            let mut branches = Vec::with_capacity(keys.len());
+            for (index, key) in keys.iter().enumerate() {
+                let mut conditions: Vec<String> = keys[..index].iter().map(|prev| format!("{}=?", quote_ident(&prev.column))).collect();
+                conditions.push(format!("{}{}?", quote_ident(&key.column), if key.desc { "<" } else { ">" }));
+                self.keyset_params.extend(values[..=index].iter().cloned());
+                branches.push(if conditions.len() == 1 { conditions.remove(0) } else { format!("({})", conditions.join(" AND ")) });
+            }
+            self.keyset_clause = Some(format!("({})", branches.join(" OR ")));
+        }
+
+        // 排序键需要出现在结果中，用于生成下一页游标
+        for key in &keys {
+            self.add_column(&key.column)?;
+        }
+        self.order = keys.clone();
+        self.keyset = Some(keys);
+        self.page = 0;
+        Ok(())
+    }
+
+    /// 根据本页最后一行生成下一页游标，不足一页时没有下一页
+    pub fn next_cursor(&self, rows: &[HashMap<String, serde_json::Value>]) -> Option<String> {
+        let keys = self.keyset.as_ref()?;
+        if self.limit <= 0 || rows.len() < self.limit as usize {
+            return None;
+        }
+        let last_row = rows.last()?;
+        let values: Vec<_> = keys.iter().map(|key| last_row.get(&key.column).cloned().unwrap_or(serde_json::Value::Null)).collect();
+        Some(base64_encode(serde_json::to_vec(&values).unwrap()))
+    }
+
+    pub fn is_keyset(&self) -> bool { self.keyset.is_some() }
+
+    /// 按关联字段分区分页，用于嵌套列表中每个上级数据各取 `count` 条
+    pub fn partition_by(&mut self, columns: &[&String]) -> Result<(), String> {
+        if self.is_grouped() {
+            return Err("nested list does not support DISTINCT, @group or @having".to_string());
+        }
+        for column in columns {
+            let column = quote_ident(&resolve_column(self.table_meta()?, column)?);
+            if !self.partition.contains(&column) {
+                self.partition.push(column);
+            }
+        }
+        Ok(())
+    }
+
+    /// 是否去重、分组或带 HAVING 条件，此时结果行与表中的行不再一一对应
+    pub fn is_grouped(&self) -> bool {
+        self.distinct || !self.group.is_empty() || !self.having_clauses.is_empty()
+    }
+
+    /// 单字段主键
+    pub fn primary_key(&self) -> Result<String, String> {
+        let table = self.table_meta()?;
+        let primary_keys: Vec<&String> = table.columns.values()
+            .filter(|column| column.key.as_deref() == Some("PRI"))
+            .map(|column| &column.field)
+            .collect();
+        match primary_keys[..] {
+            [primary_key] => Ok(primary_key.clone()),
+            _ => Err(format!("a single-column primary key on {}.{}", table.schema, table.name)),
+        }
+    }
+
+    pub fn order_items(&self) -> &[OrderItem] { &self.order }
+
+    /// 结果中的字段名，未指定 `@column` 时为表中所有可见字段
+    pub fn output_columns(&self) -> Result<Vec<String>, String> {
+        if !self.columns.is_empty() {
+            return Ok(self.labels.clone());
+        }
+        let mut columns: Vec<String> = self.table_meta()?.columns.keys().cloned().collect();
+        columns.sort();
+        Ok(columns)
+    }
+
+    pub fn page(&self) -> i32 { self.page }
+
+    pub fn limit(&self) -> i32 { self.limit }
+
     fn parse_num(value: &serde_json::Value, default_val: i32) -> i32 {
         match value {
             serde_json::Value::Number(n) => n.as_f64()
@@ -143,11 +696,314 @@ impl QueryExecutor {
         }
     }
 
-    pub fn add_column(&mut self, column: &str) {
+    pub fn add_column(&mut self, column: &str) -> Result<(), String> {
+        let name = resolve_column(self.table_meta()?, column)?;
+        let column = quote_ident(&name);
         // *代替，必然包含所有字段
-        if self.columns.is_empty() { return; }
+        if self.columns.is_empty() { return Ok(()); }
         // 包含当前字段，跳过
-        if self.columns.iter().any(|c| c.eq(column)) { return; }
-        self.columns.push(column.to_string());
+        if self.columns.iter().any(|c| c.eq(&column)) { return Ok(()); }
+        self.columns.push(column);
+        self.labels.push(name);
+        Ok(())
+    }
+}
+/// 条件操作符，对应 APIJSON 条件键的后缀
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ConditionOp {
+    Eq,
+    // {}
+    In,
+    // &{}
+    AndIn,
+    // |{}
+    OrIn,
+    // $
+    Like,
+    // ~
+    Regexp,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    // <>
+    Contains,
+}
+
+impl ConditionOp {
+    fn compare_symbol(&self) -> &'static str {
+        match self {
+            ConditionOp::Gt => ">",
+            ConditionOp::Ge => ">=",
+            ConditionOp::Lt => "<",
+            ConditionOp::Le => "<=",
+            _ => "=",
+        }
+    }
+}
+
+// 后缀按长度优先匹配，"&{}"/"|{}" 必须先于 "{}"，"<>"/">="/"<=" 必须先于 ">"/"<"
+const CONDITION_SUFFIXES: &[(&str, ConditionOp)] = &[
+    ("&{}", ConditionOp::AndIn),
+    ("|{}", ConditionOp::OrIn),
+    ("{}", ConditionOp::In),
+    ("$", ConditionOp::Like),
+    ("~", ConditionOp::Regexp),
+    ("<>", ConditionOp::Contains),
+    (">=", ConditionOp::Ge),
+    ("<=", ConditionOp::Le),
+    (">", ConditionOp::Gt),
+    ("<", ConditionOp::Lt),
+];
+
+// 范围表达式中支持的比较符
+pub(crate) const RANGE_COMPARES: &[&str] = &[">=", "<=", "!=", ">", "<", "="];
+
+/// 拆分条件键: "id!{}" -> ("id", In, true)
+fn split_condition_key(key: &str) -> (&str, ConditionOp, bool) {
+    let (rest, op) = CONDITION_SUFFIXES.iter()
+        .find_map(|(suffix, op)| key.strip_suffix(suffix).map(|rest| (rest, *op)))
+        .unwrap_or((key, ConditionOp::Eq));
+    match rest.strip_suffix('!') {
+        Some(column) => (column, op, true),
+        None => (rest, op, false),
+    }
+}
+
+/// 条件值是否都是数字: 数字、数字数组或各项都是数字的范围表达式
+fn is_numeric_condition(op: ConditionOp, value: &serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::Number(_) => true,
+        serde_json::Value::Array(values) => !values.is_empty() && values.iter().all(serde_json::Value::is_number),
+        serde_json::Value::String(expr) if matches!(op, ConditionOp::In | ConditionOp::AndIn | ConditionOp::OrIn) => {
+            expr.split(',').map(str::trim).filter(|item| !item.is_empty()).all(|item| {
+                let raw_value = RANGE_COMPARES.iter().find_map(|compare| item.strip_prefix(compare)).unwrap_or(item);
+                parse_range_value(raw_value.trim()).is_number()
+            })
+        }
+        _ => false,
+    }
+}
+
+/// 解码游标: Base64 编码的排序键值数组，值不能为 null
+fn decode_cursor(cursor: &str, key_count: usize) -> Result<Vec<serde_json::Value>, String> {
+    let invalid = || format!("invalid cursor '{}'", cursor);
+    let bytes = base64_decode(cursor).map_err(|_| invalid())?;
+    let values: Vec<serde_json::Value> = serde_json::from_slice(&bytes).map_err(|_| invalid())?;
+    if values.len() != key_count || values.iter().any(|v| v.is_null() || v.is_array() || v.is_object()) {
+        return Err(invalid());
+    }
+    Ok(values)
+}
+
+/// 范围表达式中的值: 数字按数字绑定，'abc' 去掉引号
+pub(crate) fn parse_range_value(raw: &str) -> serde_json::Value {
+    if let Ok(n) = raw.parse::<i64>() {
+        return serde_json::json!(n);
+    }
+    if let Ok(f) = raw.parse::<f64>() {
+        return serde_json::json!(f);
+    }
+    let unquoted = raw.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')).unwrap_or(raw);
+    serde_json::Value::String(unquoted.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use crate::db::query_executor::QueryExecutor;
+    use crate::verify::tests::test_table;
+
+    fn test_executor() -> QueryExecutor {
+        let mut executor = QueryExecutor::new();
+        executor.schema = "ecommerce".to_string();
+        executor.table = "product".to_string();
+        executor.table_meta = Some(test_table());
+        executor
+    }
+
+    fn where_sql(conditions: serde_json::Value) -> Result<(String, Vec<serde_json::Value>), String> {
+        let mut executor = test_executor();
+        for (k, v) in conditions.as_object().unwrap() {
+            executor.parse_condition(k, v)?;
+        }
+        Ok((executor.where_clauses.join(" AND "), executor.params))
+    }
+
+    #[test]
+    fn test_parse_condition() {
+        let cases = vec![
+            (serde_json::json!({"id": 1}), "`id`=?"),
+            (serde_json::json!({"id": [1, 2]}), "`id` in (?,?)"),
+            (serde_json::json!({"status!": "closed"}), "`status`!=?"),
+            (serde_json::json!({"id!{}": [1, 2]}), "NOT (`id` in (?,?))"),
+            (serde_json::json!({"id{}": ">=10,<20"}), "(`id`>=? OR `id`<?)"),
+            (serde_json::json!({"id&{}": ">=10,<20"}), "(`id`>=? AND `id`<?)"),
+            (serde_json::json!({"id|{}": "<10"}), "`id`<?"),
+            (serde_json::json!({"deleted_at": null}), "`deleted_at` IS NULL"),
+            (serde_json::json!({"deleted_at!": null}), "`deleted_at` IS NOT NULL"),
+            (serde_json::json!({"name~": "^a"}), "`name` REGEXP ?"),
+            (serde_json::json!({"name!$": ["%a%", "%b%"]}), "NOT ((`name` LIKE ? OR `name` LIKE ?))"),
+            (serde_json::json!({"like_cnt>=": 100}), "`like_cnt`>=?"),
+            // JSON 字段
+            (serde_json::json!({"attrs.color": "red"}), "`attrs`->>'$.color'=?"),
+            (serde_json::json!({"attrs.size.width>=": 10}), "CAST(`attrs`->>'$.size.width' AS DOUBLE)>=?"),
+            (serde_json::json!({"attrs.size{}": ">=10,<20"}), "(CAST(`attrs`->>'$.size' AS DOUBLE)>=? OR CAST(`attrs`->>'$.size' AS DOUBLE)<?)"),
+            (serde_json::json!({"attrs.tags<>": "sale"}), "JSON_CONTAINS(`attrs`,?,'$.tags')"),
+            (serde_json::json!({"attrs!<>": {"color": "red"}}), "NOT JSON_CONTAINS(`attrs`,?)"),
+        ];
+        for (conditions, expected) in cases {
+            let (sql, _) = where_sql(conditions).unwrap();
+            assert_eq!(sql, expected);
+        }
+
+        let mut executor = test_executor();
+        executor.parse_condition("name$", &serde_json::json!("%a%")).unwrap();
+        executor.parse_condition("@order", &serde_json::json!("id desc")).unwrap();
+        assert_eq!(executor.to_count_sql(), "SELECT count(*) FROM `ecommerce`.`product` WHERE `name` LIKE ?");
+
+        let (_, params) = where_sql(serde_json::json!({"price&{}": ">1.5,<='x'"})).unwrap();
+        assert_eq!(params, vec![serde_json::json!(1.5), serde_json::json!("x")]);
+        let (_, params) = where_sql(serde_json::json!({"attrs.tags<>": "sale"})).unwrap();
+        assert_eq!(params, vec![serde_json::json!("\"sale\"")]);
+        assert!(where_sql(serde_json::json!({"name<>": "a"})).is_err());
+        assert!(where_sql(serde_json::json!({"name.first": "a"})).is_err());
+
+        assert!(where_sql(serde_json::json!({"id{}": "10"})).is_err());
+        assert!(where_sql(serde_json::json!({"id{}": []})).is_err());
+        assert!(where_sql(serde_json::json!({"id>": null})).is_err());
+        // 非法标识符
+        assert!(where_sql(serde_json::json!({"password": "x"})).is_err());
+        assert!(where_sql(serde_json::json!({"id=1 OR 1": 1})).is_err());
+        assert!(where_sql(serde_json::json!({"@order": "id desc, (select 1)"})).is_err());
+        assert!(test_executor().add_column("id) FROM mysql.user -- ").is_err());
+    }
+
+    #[test]
+    fn test_aggregate_sql() {
+        let mut executor = test_executor();
+        executor.limit = 0;
+        let attrs = serde_json::json!({
+            "@column": "category,count(id):cnt,sum(price):total",
+            "@group": "category",
+            "@having": "cnt>1;sum(price)<=100",
+            "@order": "total-",
+            "price>": 0
+        });
+        let mut keys: Vec<_> = attrs.as_object().unwrap().keys().cloned().collect();
+        keys.sort();
+        for key in keys {
+            executor.parse_condition(&key, &attrs[&key]).unwrap();
+        }
+        assert_eq!(executor.to_sql(), "SELECT `category`,count(`id`) AS `cnt`,sum(`price`) AS `total` FROM `ecommerce`.`product` WHERE `price`>? GROUP BY `category` HAVING `cnt`>? AND sum(`price`)<=? ORDER BY `total` DESC");
+        assert_eq!(executor.bind_params(), vec![serde_json::json!(0), serde_json::json!(1), serde_json::json!(100)]);
+        assert_eq!(executor.to_count_sql(), "SELECT count(*) FROM (SELECT `category`,count(`id`) AS `cnt`,sum(`price`) AS `total` FROM `ecommerce`.`product` WHERE `price`>? GROUP BY `category` HAVING `cnt`>? AND sum(`price`)<=?) AS t");
+
+        let mut executor = test_executor();
+        executor.parse_condition("@column", &serde_json::json!("DISTINCT category")).unwrap();
+        assert!(executor.to_sql().starts_with("SELECT DISTINCT `category` FROM"));
+        assert!(executor.to_count_sql().starts_with("SELECT count(*) FROM (SELECT DISTINCT `category` FROM"));
+        assert!(executor.parse_condition("@group", &serde_json::json!("password")).is_err());
+        assert!(executor.parse_condition("@having", &serde_json::json!("sleep(1)>0")).is_err());
+    }
+
+    #[test]
+    fn test_keyset_page() {
+        // 排序字段须为 NOT NULL
+        let mut executor = test_executor();
+        executor.parse_condition("@order", &serde_json::json!("price-")).unwrap();
+        assert_eq!(executor.keyset_page("").unwrap_err(), "cursor pagination can not order by nullable column 'price'");
+        let test_executor = || {
+            let mut executor = test_executor();
+            executor.table_meta.as_mut().unwrap().columns.get_mut("price").unwrap().null = Some("NO".to_string());
+            executor
+        };
+
+        let mut executor = test_executor();
+        executor.parse_condition("@column", &serde_json::json!("name,price")).unwrap();
+        executor.parse_condition("@order", &serde_json::json!("price-")).unwrap();
+        executor.parse_condition("status", &serde_json::json!("on")).unwrap();
+        executor.page_size(serde_json::json!(3), serde_json::json!(2));
+        executor.keyset_page("").unwrap();
+        assert_eq!(executor.to_sql(), "SELECT `name`,`price`,`id` FROM `ecommerce`.`product` WHERE `status`=? ORDER BY `price` DESC,`id` DESC LIMIT 2 OFFSET 0");
+
+        // 满一页才有下一页游标
+        let row = |id: i64, price: &str| HashMap::from([("id".to_string(), serde_json::json!(id)), ("price".to_string(), serde_json::json!(price))]);
+        assert_eq!(executor.next_cursor(&[row(9, "10.00")]), None);
+        let cursor = executor.next_cursor(&[row(9, "10.00"), row(7, "8.50")]).unwrap();
+
+        let mut executor = test_executor();
+        executor.parse_condition("@order", &serde_json::json!("price-")).unwrap();
+        executor.parse_condition("status", &serde_json::json!("on")).unwrap();
+        executor.page_size(serde_json::json!(0), serde_json::json!(2));
+        executor.keyset_page(&cursor).unwrap();
+        assert_eq!(executor.to_sql(), "SELECT * FROM `ecommerce`.`product` WHERE `status`=? AND (`price`<? OR (`price`=? AND `id`<?)) ORDER BY `price` DESC,`id` DESC LIMIT 2 OFFSET 0");
+        assert_eq!(executor.bind_params(), vec![serde_json::json!("on"), serde_json::json!("8.50"), serde_json::json!("8.50"), serde_json::json!(7)]);
+        // 总数统计不受游标影响
+        assert_eq!(executor.to_count_sql(), "SELECT count(*) FROM `ecommerce`.`product` WHERE `status`=?");
+        assert_eq!(executor.count_params(), vec![serde_json::json!("on")]);
+
+        assert!(test_executor().keyset_page("not a cursor").is_err());
+        let mut executor = test_executor();
+        executor.parse_condition("@group", &serde_json::json!("category")).unwrap();
+        assert!(executor.keyset_page("").is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_partition_sql() {
+        // 每个分类取价格最高的2条
+        let mut executor = test_executor();
+        executor.parse_condition("@order", &serde_json::json!("price-")).unwrap();
+        executor.parse_condition("category", &serde_json::json!(["a", "b"])).unwrap();
+        executor.page_size(serde_json::json!(1), serde_json::json!(2));
+        executor.partition_by(&[&"category".to_string()]).unwrap();
+        assert_eq!(executor.to_sql(), "SELECT * FROM (SELECT `ecommerce`.`product`.*,ROW_NUMBER() OVER (PARTITION BY `category` ORDER BY `price` DESC,`id` ASC) AS `__rn` FROM `ecommerce`.`product` WHERE `category` in (?,?)) AS t WHERE `__rn`>2 AND `__rn`<=4 ORDER BY `category`,`__rn`");
+        assert!(executor.partition_by(&[&"password".to_string()]).is_err());
+
+        // 未指定 @order 时按主键排序
+        let mut executor = test_executor();
+        executor.partition_by(&[&"category".to_string()]).unwrap();
+        assert!(executor.to_sql().contains("ROW_NUMBER() OVER (PARTITION BY `category` ORDER BY `id` ASC) AS `__rn`"));
+
+        // 优化器提示只加在最外层的 SELECT
+        executor.max_execution_time(500);
+        assert!(executor.to_sql().starts_with("SELECT /*+ MAX_EXECUTION_TIME(500) */ * FROM (SELECT `ecommerce`.`product`.*,"));
+        assert!(executor.to_count_sql().starts_with("SELECT /*+ MAX_EXECUTION_TIME(500) */ count(*) FROM"));
+    }
+
+    #[test]
+    fn test_subquery_sql() {
+        let subquery = |conditions: serde_json::Value| {
+            let mut subquery = test_executor();
+            subquery.subquery_depth = 1;
+            for (key, value) in conditions.as_object().unwrap() {
+                subquery.parse_condition(key, value).unwrap();
+            }
+            subquery
+        };
+
+        // 分类中有高价商品的商品，参数按占位符的顺序绑定
+        let mut executor = test_executor();
+        executor.parse_condition("status", &serde_json::json!("on")).unwrap();
+        executor.add_subquery("category{}@", &subquery(serde_json::json!({"@column": "category", "price>": 100}))).unwrap();
+        executor.parse_condition("name$", &serde_json::json!("%a%")).unwrap();
+        assert_eq!(executor.to_count_sql(), "SELECT count(*) FROM `ecommerce`.`product` WHERE `status`=? \
+            AND `category` IN (SELECT `category` FROM `ecommerce`.`product` AS `__sq1` WHERE `price`>?) AND `name` LIKE ?");
+        assert_eq!(executor.bind_params(), vec![serde_json::json!("on"), serde_json::json!(100), serde_json::json!("%a%")]);
+
+        // 相关子查询: 子查询的 category 等于外层的 category，生成 NOT EXISTS；不相关的单值子查询与平均价比较
+        let mut executor = test_executor();
+        let mut same_category = subquery(serde_json::json!({}));
+        same_category.correlate("category", "/category", &executor).unwrap();
+        assert!(same_category.correlate("price", "price", &executor).is_err());
+        executor.add_subquery("id!}{@", &same_category).unwrap();
+        executor.add_subquery("price>=@", &subquery(serde_json::json!({"@column": "avg(price)"}))).unwrap();
+        assert_eq!(executor.to_count_sql(), "SELECT count(*) FROM `ecommerce`.`product` \
+            WHERE NOT EXISTS (SELECT 1 FROM `ecommerce`.`product` AS `__sq1` WHERE `category`=`ecommerce`.`product`.`category`) \
+            AND `price`>=(SELECT avg(`price`) AS `avg(price)` FROM `ecommerce`.`product` AS `__sq1`)");
+
+        assert!(executor.add_subquery("id{}@", &subquery(serde_json::json!({}))).is_err());
+        assert!(executor.add_subquery("name$@", &subquery(serde_json::json!({"@column": "name"}))).is_err());
+        assert!(executor.parse_subquery("id{}@", &serde_json::json!({"from": 1}), &crate::policy::Auth::default()).is_err());
+    }
+}
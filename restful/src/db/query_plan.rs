@@ -0,0 +1,131 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use fnv::FnvHashMap;
+use crate::db::query_context::get_parent_node_path;
+
+/// 查询执行计划: 由节点间的 `@` 引用构建的有向无环图
+#[derive(Debug, Default)]
+pub struct QueryPlan {
+    // 节点依赖(节点路径 -> 被依赖的节点路径)
+    pub dependencies: BTreeMap<String, BTreeSet<String>>,
+    // 执行阶段，同一阶段内的节点互不依赖，且其依赖均在之前的阶段中
+    pub stages: Vec<Vec<String>>,
+}
+
+impl QueryPlan {
+    /// 根据从节点关联映射构建执行计划
+    ///
+    /// # 参数
+    /// * `node_paths` - 所有查询节点的路径
+    /// * `slave_relate_kv` - 从节点路径 -> 从节点字段 -> 主节点字段路径
+    ///
+    /// # 错误
+    /// * 引用了不存在的节点
+    /// * 节点之间存在循环引用
+    pub fn build<'a>(node_paths: impl Iterator<Item = &'a String>, slave_relate_kv: &FnvHashMap<String, HashMap<String, String>>) -> Result<Self, String> {
+        let mut dependencies: BTreeMap<String, BTreeSet<String>> = node_paths.map(|path| (path.clone(), BTreeSet::new())).collect();
+
+        // 建立依赖边，引用路径的父路径即为被依赖的节点
+        for (slave_path, relate_kv) in slave_relate_kv {
+            let mut primary_paths = BTreeSet::new();
+            for (field, primary_field_path) in relate_kv {
+                let primary_path = get_parent_node_path(primary_field_path);
+                if !dependencies.contains_key(&primary_path) {
+                    return Err(format!("{}: '{}@' references unknown node '{}'", slave_path, field, primary_field_path));
+                }
+                primary_paths.insert(primary_path);
+            }
+            dependencies.entry(slave_path.clone()).or_default().extend(primary_paths);
+        }
+
+        // 拓扑排序，每一轮取出所有依赖已满足的节点作为一个阶段
+        let mut stages = Vec::new();
+        let mut remaining = dependencies.clone();
+        while !remaining.is_empty() {
+            let stage: Vec<String> = remaining.iter()
+                .filter(|(_, deps)| deps.iter().all(|dep| !remaining.contains_key(dep)))
+                .map(|(path, _)| path.clone())
+                .collect();
+            if stage.is_empty() {
+                return Err(format!("cyclic reference: {}", find_cycle(&remaining).join(" -> ")));
+            }
+            for path in &stage {
+                remaining.remove(path);
+            }
+            stages.push(stage);
+        }
+
+        Ok(QueryPlan { dependencies, stages })
+    }
+
+    /// 节点是否不依赖其他节点
+    pub fn is_primary(&self, node_path: &str) -> bool {
+        self.dependencies.get(node_path).is_none_or(BTreeSet::is_empty)
+    }
+}
+
+/// 在剩余节点中找到一条环路，用于错误提示
+fn find_cycle(remaining: &BTreeMap<String, BTreeSet<String>>) -> Vec<String> {
+    // 剩余节点都至少依赖一个剩余节点，沿依赖走下去必然回到走过的节点
+    let mut path: Vec<String> = Vec::new();
+    let mut current = remaining.keys().next().cloned().unwrap_or_default();
+    while !path.contains(&current) {
+        path.push(current.clone());
+        current = match remaining.get(&current).and_then(|deps| deps.iter().find(|dep| remaining.contains_key(*dep))) {
+            Some(next) => next.clone(),
+            None => break,
+        };
+    }
+    let start = path.iter().position(|path| path == &current).unwrap_or(0);
+    let mut cycle = path.split_off(start);
+    cycle.push(current);
+    cycle
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use fnv::FnvHashMap;
+    use crate::db::query_plan::QueryPlan;
+
+    fn build_relate_kv(relations: &[(&str, &str, &str)]) -> FnvHashMap<String, HashMap<String, String>> {
+        let mut relate_kv: FnvHashMap<String, HashMap<String, String>> = FnvHashMap::default();
+        for (slave, field, primary_field_path) in relations {
+            relate_kv.entry(slave.to_string()).or_default().insert(field.to_string(), primary_field_path.to_string());
+        }
+        relate_kv
+    }
+
+    #[test]
+    fn test_query_plan() {
+        // 跨层级的链式依赖: Moment -> Comment -> User，以及依赖更深层兄弟节点的 Avatar
+        let nodes = ["[]/Moment", "[]/Comment[]/Comment", "[]/Comment[]/User", "Avatar"].map(String::from);
+        let relate_kv = build_relate_kv(&[
+            ("[]/Comment[]/Comment", "moment_id", "[]/Moment/id"),
+            ("[]/Comment[]/User", "id", "[]/Comment[]/Comment/user_id"),
+            ("Avatar", "user_id", "[]/Comment[]/User/id"),
+        ]);
+        let plan = QueryPlan::build(nodes.iter(), &relate_kv).unwrap();
+        assert_eq!(plan.stages, vec![vec!["[]/Moment"], vec!["[]/Comment[]/Comment"], vec!["[]/Comment[]/User"], vec!["Avatar"]]);
+        assert!(plan.is_primary("[]/Moment"));
+        assert!(!plan.is_primary("Avatar"));
+
+        // 互不依赖的节点在同一阶段
+        let nodes = ["Moment", "User", "Comment"].map(String::from);
+        let relate_kv = build_relate_kv(&[("User", "id", "Moment/user_id"), ("Comment", "moment_id", "Moment/id")]);
+        let plan = QueryPlan::build(nodes.iter(), &relate_kv).unwrap();
+        assert_eq!(plan.stages, vec![vec!["Moment"], vec!["Comment", "User"]]);
+    }
+
+    #[test]
+    fn test_query_plan_error() {
+        let nodes = ["Moment", "User"].map(String::from);
+        let err = QueryPlan::build(nodes.iter(), &build_relate_kv(&[("User", "id", "Momnet/user_id")])).unwrap_err();
+        assert_eq!(err, "User: 'id@' references unknown node 'Momnet/user_id'");
+
+        let err = QueryPlan::build(nodes.iter(), &build_relate_kv(&[("User", "id", "Moment/user_id"), ("Moment", "user_id", "User/id")])).unwrap_err();
+        assert_eq!(err, "cyclic reference: Moment -> User -> Moment");
+
+        let err = QueryPlan::build(nodes.iter(), &build_relate_kv(&[("User", "id", "User/id")])).unwrap_err();
+        assert_eq!(err, "cyclic reference: User -> User");
+    }
+}
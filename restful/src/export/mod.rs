@@ -0,0 +1,290 @@
+pub mod xlsx;
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use futures_util::{Stream, StreamExt};
+use database::core::DBConn;
+use crate::db::query_executor::QueryExecutor;
+use crate::export::xlsx::{XlsxWriter, XLSX_MAX_ROWS};
+use crate::limits::query_limits;
+use crate::policy::{Auth, PolicyAction};
+
+/// 累积到该大小后输出一个分块
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// 导出格式，对应导出接口的扩展名
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Ndjson,
+    Xlsx,
+}
+
+impl ExportFormat {
+    pub fn from_extension(extension: &str) -> Result<Self, String> {
+        match extension {
+            "csv" => Ok(ExportFormat::Csv),
+            "ndjson" => Ok(ExportFormat::Ndjson),
+            "xlsx" => Ok(ExportFormat::Xlsx),
+            _ => Err(format!("export format '{}' is not supported", extension)),
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Xlsx => "xlsx",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Ndjson => "application/x-ndjson",
+            ExportFormat::Xlsx => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        }
+    }
+}
+
+/// 解析好的导出查询
+#[derive(Debug)]
+pub struct ExportQuery {
+    // 表名，用作文件名和工作表名
+    pub table: String,
+    // 导出的字段，CSV、XLSX 的表头
+    pub columns: Vec<String>,
+    executor: QueryExecutor,
+}
+
+/// 解析导出请求，请求体与 `get.json` 相同，但只能包含一个表节点:
+///
+/// * `{"schema.Table": {...}}` - 导出所有匹配的数据，至多 `max_export_rows` 条
+/// * `{"[]": {"count": 1000, "page": 0, "schema.Table": {...}}}` - 按 `count`、`page` 分页导出，`count` 不受 `max_count` 限制，上限为 `max_export_rows`
+///
+/// 条件、`@column`、`@order` 与 `get.json` 一致，不支持 `@` 引用和子节点；语句同样带 `max_execution_time` 提示
+pub fn prepare_export(body_map: &HashMap<String, serde_json::Value>, format: ExportFormat, auth: &Auth) -> Result<ExportQuery, String> {
+    let nodes: Vec<(&String, &serde_json::Value)> = body_map.iter().filter(|(key, value)| !key.starts_with('@') && value.is_object()).collect();
+    let [(key, value)] = nodes[..] else {
+        return Err("export expects exactly one table node".to_string());
+    };
+    let (table_key, node, namespace) = match (key.ends_with("[]"), value.as_object()) {
+        (true, Some(namespace)) => {
+            let nodes: Vec<(&String, &serde_json::Value)> = namespace.iter().filter(|(_, value)| value.is_object()).collect();
+            match nodes[..] {
+                [(table_key, node)] => (table_key.as_str(), node, Some(namespace)),
+                _ => return Err(format!("{}: export expects exactly one table node", key)),
+            }
+        }
+        _ => (key.as_str(), value, None),
+    };
+
+    let mut executor = QueryExecutor::new();
+    executor.parse_table(&table_key.to_lowercase())?;
+    executor.apply_policy(PolicyAction::Select, auth)?;
+    let attrs = node.as_object().cloned().unwrap_or_default();
+    let mut attr_keys: Vec<&String> = attrs.keys().collect();
+    attr_keys.sort();
+    for attr_key in attr_keys {
+        let value = &attrs[attr_key];
+        if attr_key.ends_with('@') || value.is_object() {
+            return Err(format!("{}: export does not support '{}'", table_key, attr_key));
+        }
+        executor.parse_condition(attr_key, value).map_err(|err| format!("{}: {}", table_key, err))?;
+    }
+
+    // 不指定 count 时导出至多 max_export_rows 条，XLSX 另受工作表行数限制(首行为表头)
+    let limits = query_limits();
+    executor.max_execution_time(limits.max_execution_time);
+    let page = namespace.and_then(|attrs| attrs.get("page")).cloned().unwrap_or_else(|| serde_json::json!(0));
+    let count = namespace.and_then(|attrs| attrs.get("count")).cloned().unwrap_or_else(|| serde_json::json!(0));
+    executor.page_size(page, count.clone());
+    if limits.max_export_rows > 0 {
+        if executor.limit() > 0 && executor.limit() as usize > limits.max_export_rows {
+            return Err(format!("{}: count {} is out of range 1..={}", key, count, limits.max_export_rows));
+        }
+        if executor.limit() <= 0 {
+            executor.page_size(serde_json::json!(executor.page()), serde_json::json!(limits.max_export_rows));
+        }
+    }
+    if format == ExportFormat::Xlsx && (executor.limit() <= 0 || executor.limit() as usize >= XLSX_MAX_ROWS) {
+        executor.page_size(serde_json::json!(executor.page()), serde_json::json!(XLSX_MAX_ROWS - 1));
+    }
+
+    let table = table_key.rsplit('.').next().unwrap_or(table_key).to_lowercase();
+    Ok(ExportQuery { table, columns: executor.output_columns()?, executor })
+}
+
+/// 流式导出: 逐行读取查询结果并编码，每累积 `CHUNK_SIZE` 字节输出一块，不在内存中保留整个结果
+///
+/// 查询出错或 XLSX 超过 zip 的大小限制时以错误结束，已输出的内容不完整
+pub fn export_stream<B: From<Vec<u8>> + 'static>(db: &'static DBConn, query: ExportQuery, format: ExportFormat) -> impl Stream<Item = Result<B, std::io::Error>> + 'static {
+    async_stream::try_stream! {
+        let sql = query.executor.to_sql();
+        let bind_params = query.executor.bind_params();
+        log::info!("sql.export: {}, params: {}", sql, serde_json::to_string(&bind_params).unwrap());
+
+        let (mut encoder, mut buffer) = RowEncoder::new(format, &query.table, query.columns);
+        let mut rows = std::pin::pin!(db.query_stream(&sql, QueryExecutor::to_sql_params(&bind_params)));
+        while let Some(row) = rows.next().await {
+            let row = row.map_err(std::io::Error::other)?;
+            encoder.write_row(&row, &mut buffer).map_err(std::io::Error::other)?;
+            if buffer.len() >= CHUNK_SIZE {
+                yield B::from(std::mem::take(&mut buffer));
+            }
+        }
+        buffer.extend(encoder.finish().map_err(std::io::Error::other)?);
+        yield B::from(buffer);
+    }
+}
+
+/// 按导出格式编码结果行
+struct RowEncoder {
+    columns: Vec<String>,
+    xlsx: Option<XlsxWriter>,
+    format: ExportFormat,
+}
+
+impl RowEncoder {
+    /// 返回编码器及文件开头的字节: CSV 为 BOM 和表头，XLSX 为文件结构和表头行
+    fn new(format: ExportFormat, table: &str, columns: Vec<String>) -> (Self, Vec<u8>) {
+        let header: Vec<serde_json::Value> = columns.iter().map(|column| serde_json::json!(column)).collect();
+        match format {
+            ExportFormat::Csv => {
+                // BOM 让 Excel 以 UTF-8 打开
+                let mut out = "\u{feff}".as_bytes().to_vec();
+                write_csv_record(header.iter(), &mut out);
+                (RowEncoder { columns, xlsx: None, format }, out)
+            }
+            ExportFormat::Ndjson => (RowEncoder { columns, xlsx: None, format }, Vec::new()),
+            ExportFormat::Xlsx => {
+                let (mut writer, mut out) = XlsxWriter::new(table);
+                // 表头不会超过大小限制
+                out.extend(writer.write_row(header.iter()).unwrap());
+                (RowEncoder { columns, xlsx: Some(writer), format }, out)
+            }
+        }
+    }
+
+    fn write_row(&mut self, row: &HashMap<String, serde_json::Value>, out: &mut Vec<u8>) -> Result<(), String> {
+        let cells = self.columns.iter().map(|column| row.get(column).unwrap_or(&serde_json::Value::Null));
+        match (self.format, self.xlsx.as_mut()) {
+            (ExportFormat::Csv, _) => write_csv_record(cells, out),
+            (ExportFormat::Xlsx, Some(writer)) => out.extend(writer.write_row(cells)?),
+            _ => {
+                let object: serde_json::Map<String, serde_json::Value> = self.columns.iter().cloned().zip(cells.cloned()).collect();
+                out.extend(serde_json::Value::Object(object).to_string().as_bytes());
+                out.push(b'\n');
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Vec<u8>, String> {
+        self.xlsx.map(XlsxWriter::finish).unwrap_or(Ok(Vec::new()))
+    }
+}
+
+/// 写入一行 CSV(RFC 4180): 含逗号、引号、换行的字段用双引号包裹，null 为空字段
+fn write_csv_record<'a>(cells: impl Iterator<Item = &'a serde_json::Value>, out: &mut Vec<u8>) {
+    for (index, cell) in cells.enumerate() {
+        if index > 0 {
+            out.push(b',');
+        }
+        let text = match cell {
+            serde_json::Value::Null => continue,
+            serde_json::Value::String(s) => escape_formula(s).into_owned(),
+            _ => cell.to_string(),
+        };
+        if text.contains([',', '"', '\r', '\n']) {
+            out.push(b'"');
+            out.extend(text.replace('"', "\"\"").as_bytes());
+            out.push(b'"');
+        } else {
+            out.extend(text.as_bytes());
+        }
+    }
+    out.extend(b"\r\n");
+}
+
+/// 以 `=`、`+`、`-`、`@`、制表符、回车开头的文本会被表格软件当作公式执行，前面加 `'` 使其按文本显示；
+/// 以字符串输出的负数(如 DECIMAL 的 `-12.50`)不是公式，保持原样
+pub(crate) fn escape_formula(text: &str) -> Cow<'_, str> {
+    match text.starts_with(['=', '+', '-', '@', '\t', '\r']) && !is_decimal_text(text) {
+        true => Cow::Owned(format!("'{}", text)),
+        false => Cow::Borrowed(text),
+    }
+}
+
+/// 是否为以字符串输出的十进制数，如 DECIMAL 的 `-12.50`: 可选负号，整数部分没有多余的前导零，
+/// 有效数字不超过 15 位(Excel 数字的精度)，更长的数字串(如证件号)仍按文本处理
+pub(crate) fn is_decimal_text(text: &str) -> bool {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    let (int, frac) = match digits.split_once('.') {
+        Some((_, "")) => return false,
+        Some((int, frac)) => (int, frac),
+        None => (digits, ""),
+    };
+    let all_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    !int.is_empty() && all_digits(int) && all_digits(frac)
+        && (int == "0" || !int.starts_with('0'))
+        && int.trim_start_matches('0').len() + frac.len() <= 15
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use crate::export::{escape_formula, is_decimal_text, ExportFormat, RowEncoder};
+    use crate::export::xlsx::cell_xml;
+
+    #[test]
+    fn test_row_encoder() {
+        let columns = vec!["id".to_string(), "name".to_string(), "price".to_string()];
+        let row = HashMap::from([
+            ("id".to_string(), serde_json::json!(1)),
+            ("name".to_string(), serde_json::json!("a \"b\", c")),
+            ("price".to_string(), serde_json::Value::Null),
+        ]);
+
+        let (mut encoder, mut out) = RowEncoder::new(ExportFormat::Csv, "product", columns.clone());
+        encoder.write_row(&row, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "\u{feff}id,name,price\r\n1,\"a \"\"b\"\", c\",\r\n");
+
+        // 公式开头的文本加 `'`，数字不变
+        let formula = HashMap::from([
+            ("id".to_string(), serde_json::json!(-1)),
+            ("name".to_string(), serde_json::json!("=HYPERLINK(\"http://x\")")),
+            ("price".to_string(), serde_json::json!("@SUM(1)")),
+        ]);
+        let (mut encoder, mut out) = RowEncoder::new(ExportFormat::Csv, "product", columns.clone());
+        encoder.write_row(&formula, &mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().ends_with("\r\n-1,\"'=HYPERLINK(\"\"http://x\"\")\",'@SUM(1)\r\n"));
+        assert_eq!(escape_formula("\tcmd"), "'\tcmd");
+        assert_eq!(escape_formula("a-b"), "a-b");
+
+        // DECIMAL 默认以字符串输出，负数不加 `'`，XLSX 中为数字单元格
+        let decimal = HashMap::from([
+            ("id".to_string(), serde_json::json!(2)),
+            ("name".to_string(), serde_json::json!("-1+2")),
+            ("price".to_string(), serde_json::json!("-12.50")),
+        ]);
+        let (mut encoder, mut out) = RowEncoder::new(ExportFormat::Csv, "product", columns.clone());
+        encoder.write_row(&decimal, &mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().ends_with("\r\n2,'-1+2,-12.50\r\n"));
+        assert_eq!(cell_xml(&serde_json::json!("-12.50")), "<c><v>-12.50</v></c>");
+        assert_eq!(cell_xml(&serde_json::json!("-007")), r#"<c t="inlineStr"><is><t xml:space="preserve">'-007</t></is></c>"#);
+        assert!(!is_decimal_text("-1.") && !is_decimal_text("1e5") && !is_decimal_text("110101199001011234"));
+
+        let (mut encoder, mut out) = RowEncoder::new(ExportFormat::Ndjson, "product", columns.clone());
+        encoder.write_row(&row, &mut out).unwrap();
+        encoder.write_row(&row, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap().lines().next().unwrap(), r#"{"id":1,"name":"a \"b\", c","price":null}"#);
+
+        // XLSX 为 zip: 以本地文件头开始，以目录结束记录结尾
+        let (mut encoder, mut out) = RowEncoder::new(ExportFormat::Xlsx, "product", columns);
+        encoder.write_row(&row, &mut out).unwrap();
+        out.extend(encoder.finish().unwrap());
+        assert!(out.starts_with(b"PK\x03\x04"));
+        assert_eq!(&out[out.len() - 22..out.len() - 18], b"PK\x05\x06");
+    }
+}
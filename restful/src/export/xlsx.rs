@@ -0,0 +1,240 @@
+use std::io::Write;
+use flate2::Compression;
+use flate2::write::DeflateEncoder;
+use crate::export::{escape_formula, is_decimal_text};
+
+/// Excel 单个工作表的最大行数
+pub const XLSX_MAX_ROWS: usize = 1_048_576;
+
+const CONTENT_TYPES_XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/></Types>"#;
+const RELS_XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#;
+const WORKBOOK_RELS_XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/></Relationships>"#;
+const SHEET_HEAD_XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>"#;
+const SHEET_TAIL_XML: &str = "</sheetData></worksheet>";
+
+/// 流式生成只有一个工作表的 XLSX，行数据写入后即可取出压缩好的字节
+pub struct XlsxWriter {
+    zip: ZipStream,
+}
+
+impl XlsxWriter {
+    /// 返回写入器及文件开头的字节，工作表的数据最后写入
+    pub fn new(sheet_name: &str) -> (Self, Vec<u8>) {
+        let workbook_xml = format!(r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="{}" sheetId="1" r:id="rId1"/></sheets></workbook>"#, escape_xml(&sheet_title(sheet_name)));
+        let mut zip = ZipStream::default();
+        let mut out = Vec::new();
+        for (name, content) in [("[Content_Types].xml", CONTENT_TYPES_XML), ("_rels/.rels", RELS_XML), ("xl/workbook.xml", &workbook_xml), ("xl/_rels/workbook.xml.rels", WORKBOOK_RELS_XML)] {
+            // 文件开头的固定内容不会超过大小限制
+            out.extend(zip.start_file(name).unwrap());
+            out.extend(zip.write(content.as_bytes()).unwrap());
+            out.extend(zip.finish_file().unwrap());
+        }
+        out.extend(zip.start_file("xl/worksheets/sheet1.xml").unwrap());
+        out.extend(zip.write(SHEET_HEAD_XML.as_bytes()).unwrap());
+        (XlsxWriter { zip }, out)
+    }
+
+    /// 写入一行，文件超过 zip 的 4 GiB 限制时返回错误
+    pub fn write_row<'a>(&mut self, cells: impl Iterator<Item = &'a serde_json::Value>) -> Result<Vec<u8>, String> {
+        let mut xml = String::from("<row>");
+        for cell in cells {
+            xml.push_str(&cell_xml(cell));
+        }
+        xml.push_str("</row>");
+        self.zip.write(xml.as_bytes())
+    }
+
+    /// 结束工作表并写入 zip 目录
+    pub fn finish(mut self) -> Result<Vec<u8>, String> {
+        let mut out = self.zip.write(SHEET_TAIL_XML.as_bytes())?;
+        out.extend(self.zip.finish_file()?);
+        out.extend(self.zip.finish()?);
+        Ok(out)
+    }
+}
+
+/// 数字、布尔值为对应类型的单元格，以字符串输出的十进制数(DECIMAL)为数字单元格，null 为空单元格，其余为文本
+pub(crate) fn cell_xml(cell: &serde_json::Value) -> String {
+    match cell {
+        serde_json::Value::Null => "<c/>".to_string(),
+        serde_json::Value::Number(n) => format!("<c><v>{}</v></c>", n),
+        serde_json::Value::Bool(b) => format!(r#"<c t="b"><v>{}</v></c>"#, u8::from(*b)),
+        serde_json::Value::String(s) if is_decimal_text(s) => format!("<c><v>{}</v></c>", s),
+        serde_json::Value::String(s) => inline_string(&escape_formula(s)),
+        _ => inline_string(&cell.to_string()),
+    }
+}
+
+fn inline_string(s: &str) -> String {
+    format!(r#"<c t="inlineStr"><is><t xml:space="preserve">{}</t></is></c>"#, escape_xml(s))
+}
+
+/// 工作表名称最长31个字符，不能包含 `[]:*?/\`
+fn sheet_title(name: &str) -> String {
+    let title: String = name.chars().filter(|c| !"[]:*?/\\".contains(*c)).take(31).collect();
+    if title.is_empty() { "Sheet1".to_string() } else { title }
+}
+
+/// 转义 XML 特殊字符，并去掉 XML 中不允许的控制字符
+fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// zip 中的一个文件
+struct ZipEntry {
+    name: String,
+    crc32: u32,
+    compressed_size: u64,
+    size: u64,
+    offset: u64,
+}
+
+/// 正在写入的文件
+struct ZipCurrent {
+    entry: ZipEntry,
+    hasher: crc32fast::Hasher,
+    encoder: DeflateEncoder<Vec<u8>>,
+}
+
+/// 只追加写入的 zip: 文件头中不写大小和校验值，由文件数据后的数据描述符给出，无需回写；
+/// 不支持 ZIP64，大小或偏移超过 `u32::MAX` 时报错，而不是回绕后生成损坏的文件
+#[derive(Default)]
+struct ZipStream {
+    // 已输出的字节数
+    offset: u64,
+    entries: Vec<ZipEntry>,
+    current: Option<ZipCurrent>,
+}
+
+// 通用标志位: 大小和校验值在数据描述符中
+const FLAG_DATA_DESCRIPTOR: u16 = 0x0008;
+// 压缩方法: deflate
+const METHOD_DEFLATE: u16 = 8;
+// 1980-01-01 00:00
+const DOS_DATE: u16 = 0x0021;
+
+impl ZipStream {
+    fn start_file(&mut self, name: &str) -> Result<Vec<u8>, String> {
+        let mut out = Vec::with_capacity(30 + name.len());
+        out.extend(0x04034b50u32.to_le_bytes());
+        out.extend(20u16.to_le_bytes());
+        out.extend(FLAG_DATA_DESCRIPTOR.to_le_bytes());
+        out.extend(METHOD_DEFLATE.to_le_bytes());
+        out.extend(0u16.to_le_bytes());
+        out.extend(DOS_DATE.to_le_bytes());
+        // crc32、压缩后大小、原始大小
+        out.extend([0u8; 12]);
+        out.extend((name.len() as u16).to_le_bytes());
+        out.extend(0u16.to_le_bytes());
+        out.extend(name.as_bytes());
+
+        let entry = ZipEntry { name: name.to_string(), crc32: 0, compressed_size: 0, size: 0, offset: self.offset };
+        self.current = Some(ZipCurrent { entry, hasher: crc32fast::Hasher::new(), encoder: DeflateEncoder::new(Vec::new(), Compression::default()) });
+        self.emit(out)
+    }
+
+    /// 写入当前文件的数据，返回已压缩好的字节
+    fn write(&mut self, data: &[u8]) -> Result<Vec<u8>, String> {
+        let Some(current) = self.current.as_mut() else { return Ok(Vec::new()) };
+        current.hasher.update(data);
+        current.entry.size += data.len() as u64;
+        zip_u32(current.entry.size)?;
+        // 写入内存不会失败
+        current.encoder.write_all(data).unwrap();
+        let out = std::mem::take(current.encoder.get_mut());
+        current.entry.compressed_size += out.len() as u64;
+        self.emit(out)
+    }
+
+    fn finish_file(&mut self) -> Result<Vec<u8>, String> {
+        let Some(ZipCurrent { mut entry, hasher, encoder }) = self.current.take() else { return Ok(Vec::new()) };
+        let mut out = encoder.finish().unwrap();
+        entry.compressed_size += out.len() as u64;
+        entry.crc32 = hasher.finalize();
+        out.extend(0x08074b50u32.to_le_bytes());
+        out.extend(entry.crc32.to_le_bytes());
+        out.extend(zip_u32(entry.compressed_size)?.to_le_bytes());
+        out.extend(zip_u32(entry.size)?.to_le_bytes());
+        self.entries.push(entry);
+        self.emit(out)
+    }
+
+    /// 中央目录及目录结束记录
+    fn finish(mut self) -> Result<Vec<u8>, String> {
+        let directory_offset = zip_u32(self.offset)?;
+        let mut out = Vec::new();
+        for entry in &self.entries {
+            out.extend(0x02014b50u32.to_le_bytes());
+            out.extend(20u16.to_le_bytes());
+            out.extend(20u16.to_le_bytes());
+            out.extend(FLAG_DATA_DESCRIPTOR.to_le_bytes());
+            out.extend(METHOD_DEFLATE.to_le_bytes());
+            out.extend(0u16.to_le_bytes());
+            out.extend(DOS_DATE.to_le_bytes());
+            out.extend(entry.crc32.to_le_bytes());
+            out.extend(zip_u32(entry.compressed_size)?.to_le_bytes());
+            out.extend(zip_u32(entry.size)?.to_le_bytes());
+            out.extend((entry.name.len() as u16).to_le_bytes());
+            // 扩展字段长度、注释长度、磁盘号、内部属性、外部属性
+            out.extend([0u8; 12]);
+            out.extend(zip_u32(entry.offset)?.to_le_bytes());
+            out.extend(entry.name.as_bytes());
+        }
+        let directory_size = zip_u32(out.len() as u64)?;
+        out.extend(0x06054b50u32.to_le_bytes());
+        out.extend([0u8; 4]);
+        out.extend((self.entries.len() as u16).to_le_bytes());
+        out.extend((self.entries.len() as u16).to_le_bytes());
+        out.extend(directory_size.to_le_bytes());
+        out.extend(directory_offset.to_le_bytes());
+        out.extend(0u16.to_le_bytes());
+        self.emit(out)
+    }
+
+    fn emit(&mut self, out: Vec<u8>) -> Result<Vec<u8>, String> {
+        self.offset += out.len() as u64;
+        zip_u32(self.offset)?;
+        Ok(out)
+    }
+}
+
+/// zip 中的大小、偏移为 32 位
+fn zip_u32(value: u64) -> Result<u32, String> {
+    u32::try_from(value).map_err(|_| "xlsx exceeds the 4 GiB zip limit, export fewer rows or columns".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::export::xlsx::ZipStream;
+
+    #[test]
+    fn test_zip_limit() {
+        // 偏移超过 4 GiB 时报错
+        let mut zip = ZipStream { offset: u64::from(u32::MAX) - 31, ..Default::default() };
+        assert!(zip.start_file("a").is_ok());
+        assert!(zip.start_file("a").is_err());
+
+        // 原始大小超过 4 GiB 时报错
+        let mut zip = ZipStream::default();
+        zip.start_file("a").unwrap();
+        zip.current.as_mut().unwrap().entry.size = u64::from(u32::MAX);
+        assert_eq!(zip.write(b"a").unwrap_err(), "xlsx exceeds the 4 GiB zip limit, export fewer rows or columns");
+    }
+}
@@ -0,0 +1,104 @@
+use std::sync::{LazyLock, RwLock};
+use common::yaml::{Access, Exposure};
+use database::TableMeta;
+use crate::verify::{resolve_table, split_table_key};
+
+/// 库、表、字段的对外暴露配置
+static EXPOSURE_REGISTRY: LazyLock<RwLock<Exposure>> = LazyLock::new(|| RwLock::new(Exposure::default()));
+
+/// 加载 `restful.exposure` 配置
+pub fn init_exposure(exposure: &Exposure) {
+    *EXPOSURE_REGISTRY.write().unwrap() = exposure.clone();
+}
+
+/// 表的访问级别: 表的配置优先，其次是库的配置，最后是默认级别
+pub fn table_access(schema: &str, table: &str) -> Access {
+    let registry = EXPOSURE_REGISTRY.read().unwrap();
+    let table_key = format!("{}.{}", schema, table);
+    registry.tables.iter()
+        .find(|exposure| exposure.name == table_key)
+        .and_then(|exposure| exposure.access)
+        .or_else(|| registry.schemas.iter().find(|exposure| exposure.name == schema).map(|exposure| exposure.access))
+        .unwrap_or(registry.default)
+}
+
+/// 表是否对外可见
+pub fn is_table_visible(schema: &str, table: &str) -> bool {
+    table_access(schema, table) != Access::Hidden
+}
+
+/// 配置为隐藏或只读的字段
+fn table_columns(schema: &str, table: &str, readonly: bool) -> Vec<String> {
+    let registry = EXPOSURE_REGISTRY.read().unwrap();
+    let table_key = format!("{}.{}", schema, table);
+    registry.tables.iter()
+        .filter(|exposure| exposure.name == table_key)
+        .flat_map(|exposure| if readonly { &exposure.readonly_columns } else { &exposure.hidden_columns })
+        .cloned()
+        .collect()
+}
+
+/// 解析 "schema.table" 键，隐藏的表视为不存在，返回的元数据中不含隐藏字段
+pub fn visible_table(table_key: &str) -> Result<TableMeta, String> {
+    let (schema, table) = split_table_key(table_key)?;
+    if !is_table_visible(schema, table) {
+        return Err(format!("table {} not exists", table_key));
+    }
+    let mut table = resolve_table(table_key)?;
+    let hidden_columns = table_columns(&table.schema, &table.name, false);
+    table.columns.retain(|name, _| !hidden_columns.iter().any(|hidden| hidden.eq_ignore_ascii_case(name)));
+    Ok(table)
+}
+
+/// 表中是否有隐藏字段，查询时不能使用 `*`
+pub fn has_hidden_columns(table: &TableMeta) -> bool {
+    !table_columns(&table.schema, &table.name, false).is_empty()
+}
+
+/// 解析可写入的表，只读的表返回错误
+pub fn writable_table(table_key: &str) -> Result<TableMeta, String> {
+    let table = visible_table(table_key)?;
+    if table_access(&table.schema, &table.name) == Access::ReadOnly {
+        return Err(format!("table {} is read-only", table_key));
+    }
+    Ok(table)
+}
+
+/// 检查写入的字段中是否有只读字段
+pub fn check_writable_columns(table: &TableMeta, columns: &[String]) -> Result<(), String> {
+    let readonly_columns = table_columns(&table.schema, &table.name, true);
+    match columns.iter().find(|column| readonly_columns.iter().any(|readonly| readonly.eq_ignore_ascii_case(column))) {
+        Some(column) => Err(format!("column '{}' is read-only", column)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::yaml::{Access, Exposure, SchemaExposure, TableExposure};
+    use crate::exposure::{check_writable_columns, table_access, EXPOSURE_REGISTRY};
+    use crate::verify::tests::{lock_registries, test_table};
+
+    #[test]
+    fn test_exposure() {
+        let _lock = lock_registries();
+        *EXPOSURE_REGISTRY.write().unwrap() = Exposure {
+            default: Access::Public,
+            schemas: vec![SchemaExposure { name: "ideabase".to_string(), access: Access::ReadOnly }],
+            tables: vec![
+                TableExposure { name: "ideabase.account".to_string(), access: None, hidden_columns: vec!["password".to_string()], readonly_columns: vec![] },
+                TableExposure { name: "ideabase.secret".to_string(), access: Some(Access::Hidden), hidden_columns: vec![], readonly_columns: vec![] },
+                TableExposure { name: "ecommerce.product".to_string(), access: None, hidden_columns: vec![], readonly_columns: vec!["Price".to_string()] },
+            ],
+        };
+        assert_eq!(table_access("ideabase", "account"), Access::ReadOnly);
+        assert_eq!(table_access("ideabase", "secret"), Access::Hidden);
+        assert_eq!(table_access("ideabase", "project"), Access::ReadOnly);
+        assert_eq!(table_access("timeline", "Moment"), Access::Public);
+
+        let table = test_table();
+        assert!(check_writable_columns(&table, &["name".to_string()]).is_ok());
+        assert_eq!(check_writable_columns(&table, &["name".to_string(), "price".to_string()]).unwrap_err(), "column 'price' is read-only");
+        *EXPOSURE_REGISTRY.write().unwrap() = Exposure::default();
+    }
+}
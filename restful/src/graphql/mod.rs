@@ -0,0 +1,677 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{LazyLock, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use async_graphql::dynamic::{Enum, Field, FieldFuture, FieldValue, InputObject, InputValue, Object, ObjectAccessor, ResolverContext, Scalar, Schema, TypeRef};
+use async_graphql::parser::types::{ExecutableDocument, OperationType, Selection, SelectionSet};
+use async_graphql::Name;
+use http::StatusCode;
+use common::rpc::RpcResult;
+use common::yaml::{Access, Graphql, Limits};
+use database::TableMeta;
+use database::core::{get_table_list, DBConn};
+use crate::access::{is_allowed, ANONYMOUS_ROLE};
+use crate::db::query_executor::{QueryExecutor, DEFAULT_MAX_COUNT};
+use crate::exposure::{is_table_visible, table_access, visible_table};
+use crate::handler::delete::handle_delete;
+use crate::handler::post::handle_post;
+use crate::handler::put::handle_put;
+use crate::limits::query_limits;
+use crate::policy::{Auth, PolicyAction};
+use crate::verify::is_identifier;
+
+type Row = HashMap<String, serde_json::Value>;
+
+/// 由表元数据生成的 GraphQL schema，未开启时为 None
+static GRAPHQL_SCHEMA: LazyLock<RwLock<Option<Schema>>> = LazyLock::new(|| RwLock::new(None));
+
+// 自定义标量
+const BIGINT: &str = "BigInt";
+const DECIMAL: &str = "Decimal";
+const DATETIME: &str = "DateTime";
+const JSON: &str = "JSON";
+const ORDER_DIRECTION: &str = "OrderDirection";
+
+/// 生成 GraphQL schema，需在表元数据、暴露配置加载之后调用，元数据变化后重新调用
+pub fn init_graphql(conf: &Graphql) -> Result<(), String> {
+    let schema = if conf.enabled { Some(build_schema(conf)?) } else { None };
+    *GRAPHQL_SCHEMA.write().unwrap() = schema;
+    Ok(())
+}
+
+/// 执行 GraphQL 请求，`request` 为 `{"query", "variables", "operationName"}`
+pub async fn execute_graphql(db: &DBConn, auth: &Auth, request: serde_json::Value) -> Result<serde_json::Value, String> {
+    let schema = GRAPHQL_SCHEMA.read().unwrap().clone().ok_or_else(|| "graphql is disabled".to_string())?;
+    let request: async_graphql::Request = serde_json::from_value(request).map_err(|err| format!("malformed graphql request: {}", err))?;
+    let limits = query_limits();
+    check_document(&request.query, &limits)?;
+    let response = schema.execute(request.data(db.clone()).data(auth.clone()).data(RowBudget::new(limits.max_rows))).await;
+    serde_json::to_value(response).map_err(|err| err.to_string())
+}
+
+/// 一个请求中所有查询合计的行数，关联字段按上级的每一行查询，以此限制扇出
+struct RowBudget {
+    max_rows: usize,
+    rows: AtomicUsize,
+}
+
+impl RowBudget {
+    fn new(max_rows: usize) -> Self {
+        Self { max_rows, rows: AtomicUsize::new(0) }
+    }
+
+    /// 已超出时不再查询
+    fn check(&self, table_key: &str) -> Result<(), String> {
+        let rows = self.rows.load(Ordering::Relaxed);
+        if self.max_rows > 0 && rows > self.max_rows {
+            return Err(format!("{}: {} rows exceed the request row budget {}", table_key, rows, self.max_rows));
+        }
+        Ok(())
+    }
+
+    fn consume(&self, table_key: &str, rows: usize) -> Result<(), String> {
+        self.rows.fetch_add(rows, Ordering::Relaxed);
+        self.check(table_key)
+    }
+}
+
+/// 按 `restful.limits` 检查查询文档中表节点(带子字段的字段)的嵌套深度和数量，不计 introspection 字段，语法错误留给执行时报告
+fn check_document(query: &str, limits: &Limits) -> Result<(), String> {
+    let Ok(document) = async_graphql::parser::parse_query(query) else { return Ok(()) };
+    let mut nodes = 0;
+    for (_, operation) in document.operations.iter() {
+        check_selection(&document, &operation.node.selection_set.node, 1, &mut vec![], &mut nodes, limits)?;
+    }
+    Ok(())
+}
+
+fn check_selection(document: &ExecutableDocument, selection_set: &SelectionSet, depth: usize, fragments: &mut Vec<Name>, nodes: &mut usize, limits: &Limits) -> Result<(), String> {
+    for selection in &selection_set.items {
+        match &selection.node {
+            Selection::Field(field) => {
+                let field = &field.node;
+                let name = field.response_key().node.as_str();
+                if field.name.node.starts_with("__") || field.selection_set.node.items.is_empty() {
+                    continue;
+                }
+                if limits.max_depth > 0 && depth > limits.max_depth {
+                    return Err(format!("{}: depth {} exceeds the limit {}", name, depth, limits.max_depth));
+                }
+                *nodes += 1;
+                if limits.max_nodes > 0 && *nodes > limits.max_nodes {
+                    return Err(format!("{}: {} query nodes exceed the limit {}", name, nodes, limits.max_nodes));
+                }
+                check_selection(document, &field.selection_set.node, depth + 1, fragments, nodes, limits)?;
+            }
+            Selection::FragmentSpread(spread) => {
+                let fragment_name = &spread.node.fragment_name.node;
+                // 循环引用的片段由 GraphQL 校验报错
+                if fragments.contains(fragment_name) {
+                    continue;
+                }
+                if let Some(fragment) = document.fragments.get(fragment_name) {
+                    fragments.push(fragment_name.clone());
+                    check_selection(document, &fragment.node.selection_set.node, depth, fragments, nodes, limits)?;
+                    fragments.pop();
+                }
+            }
+            Selection::InlineFragment(fragment) => check_selection(document, &fragment.node.selection_set.node, depth, fragments, nodes, limits)?,
+        }
+    }
+    Ok(())
+}
+
+/// 请求中是否有查询(非 mutation)操作，无法解析的请求按查询处理
+pub fn has_query_operation(request: &serde_json::Value) -> bool {
+    let document = request.get("query").and_then(serde_json::Value::as_str).map(async_graphql::parser::parse_query);
+    match document {
+        Some(Ok(document)) => document.operations.iter().any(|(_, operation)| operation.node.ty != OperationType::Mutation),
+        _ => true,
+    }
+}
+
+/// 表对应的 GraphQL 类型名，如 `timeline_Moment`
+fn type_name(table: &TableMeta) -> String {
+    format!("{}_{}", table.schema, table.name)
+}
+
+/// MySQL 字段类型对应的 GraphQL 标量
+fn scalar_type(type_name: &str) -> &'static str {
+    let type_name = type_name.to_lowercase();
+    if type_name.starts_with("tinyint(1)") {
+        TypeRef::BOOLEAN
+    } else if type_name.starts_with("bigint") || type_name.starts_with("bit") || (type_name.contains("int") && type_name.contains("unsigned")) {
+        BIGINT
+    } else if ["tinyint", "smallint", "mediumint", "int", "year"].iter().any(|prefix| type_name.starts_with(prefix)) {
+        TypeRef::INT
+    } else if type_name.starts_with("decimal") || type_name.starts_with("numeric") {
+        DECIMAL
+    } else if ["float", "double", "real"].iter().any(|prefix| type_name.starts_with(prefix)) {
+        TypeRef::FLOAT
+    } else if type_name.starts_with("datetime") || type_name.starts_with("timestamp") {
+        DATETIME
+    } else if type_name.starts_with("json") {
+        JSON
+    } else {
+        TypeRef::STRING
+    }
+}
+
+/// 可以作为 GraphQL 字段名的字段，按名称排序
+fn graphql_columns(table: &TableMeta) -> Vec<(&String, &'static str)> {
+    let mut columns: Vec<(&String, &'static str)> = table.columns.iter()
+        .filter(|(name, _)| is_identifier(name) && !name.starts_with("__"))
+        .map(|(name, column)| (name, scalar_type(&column.type_name)))
+        .collect();
+    columns.sort();
+    columns
+}
+
+/// 单一主键
+fn primary_key(table: &TableMeta) -> Option<&String> {
+    let mut keys = table.columns.values().filter(|column| column.key.as_deref() == Some("PRI"));
+    match (keys.next(), keys.next()) {
+        (Some(key), None) => Some(&key.field),
+        _ => None,
+    }
+}
+
+/// 主键是否为整数 `id`: REST 的更新、删除按 `id` 整数定位，只有这样的表才生成 `update_`、`delete_`
+fn has_integer_id(table: &TableMeta) -> bool {
+    primary_key(table).is_some_and(|key| key == "id") && [TypeRef::INT, BIGINT].contains(&scalar_type(&table.columns["id"].type_name))
+}
+
+/// 表之间的关联字段
+#[derive(Debug, Clone)]
+struct Relation {
+    // 关联字段名
+    name: String,
+    // 本表字段
+    column: String,
+    // 关联的表
+    target: String,
+    // 关联表的字段
+    target_column: String,
+    // 是否返回列表
+    list: bool,
+}
+
+/// 由外键及配置推断关联关系: 外键在本表生成单条关联，在被引用的表生成列表关联
+fn build_relations(conf: &Graphql, tables: &BTreeMap<String, TableMeta>) -> BTreeMap<String, Vec<Relation>> {
+    let mut relations: BTreeMap<String, Vec<Relation>> = BTreeMap::new();
+    let has_column = |table_key: &str, column: &str| tables.get(table_key).is_some_and(|table| table.columns.contains_key(column));
+    let mut add_relation = |table_key: &str, relation: Relation| {
+        // 字段名需合法且不能与字段或其他关联重名
+        let used = has_column(table_key, &relation.name)
+            || relations.get(table_key).is_some_and(|list| list.iter().any(|r| r.name == relation.name));
+        if !is_identifier(&relation.name) || used || !has_column(table_key, &relation.column) || !has_column(&relation.target, &relation.target_column) {
+            log::warn!("graphql.relation {}.{} skipped", table_key, relation.name);
+            return;
+        }
+        relations.entry(table_key.to_string()).or_default().push(relation);
+    };
+
+    for (table_key, table) in tables {
+        for foreign_key in &table.foreign_keys {
+            let target = format!("{}.{}", foreign_key.referenced_schema, foreign_key.referenced_table);
+            if !tables.contains_key(&target) {
+                continue;
+            }
+            // user_id -> user
+            let name = match foreign_key.column.strip_suffix("_id") {
+                Some(name) if !name.is_empty() && !table.columns.contains_key(name) => name.to_string(),
+                _ => format!("{}_ref", foreign_key.column),
+            };
+            add_relation(table_key, Relation {
+                name, column: foreign_key.column.clone(), target: target.clone(), target_column: foreign_key.referenced_column.clone(), list: false,
+            });
+            // timeline_Comment_by_moment_id
+            add_relation(&target, Relation {
+                name: format!("{}_by_{}", type_name(table), foreign_key.column),
+                column: foreign_key.referenced_column.clone(), target: table_key.clone(), target_column: foreign_key.column.clone(), list: true,
+            });
+        }
+    }
+    for relation in &conf.relations {
+        let list = tables.get(&relation.target).and_then(primary_key).is_none_or(|key| key != &relation.target_column);
+        add_relation(&relation.table, Relation {
+            name: relation.name.clone(), column: relation.column.clone(), target: relation.target.clone(), target_column: relation.target_column.clone(), list,
+        });
+    }
+    relations
+}
+
+fn build_schema(conf: &Graphql) -> Result<Schema, String> {
+    // 对外可见且至少有一个合法字段的表
+    let tables: BTreeMap<String, TableMeta> = get_table_list().into_iter()
+        .filter(|table| is_table_visible(&table.schema, &table.name))
+        .filter_map(|table| visible_table(&format!("{}.{}", table.schema, table.name)).ok())
+        .filter(|table| !graphql_columns(table).is_empty())
+        .map(|table| (format!("{}.{}", table.schema, table.name), table))
+        .collect();
+    let relations = build_relations(conf, &tables);
+
+    let mut query = Object::new("Query");
+    let mut mutation = Object::new("Mutation");
+    let mut has_mutation = false;
+    let mut types: Vec<async_graphql::dynamic::Type> = vec![
+        Scalar::new(BIGINT).description("64位整数").into(),
+        Scalar::new(DECIMAL).description("定点数，按配置输出为字符串或数字").into(),
+        Scalar::new(DATETIME).description("ISO-8601 时间").into(),
+        Scalar::new(JSON).description("JSON 值").into(),
+        Enum::new(ORDER_DIRECTION).item("ASC").item("DESC").into(),
+    ];
+    for scalar in [TypeRef::INT, BIGINT, TypeRef::FLOAT, DECIMAL, TypeRef::STRING, TypeRef::BOOLEAN, DATETIME, JSON] {
+        types.push(filter_input(scalar).into());
+    }
+
+    // 已生成的表名列表，没有表时 Query 也至少有一个字段
+    let table_keys: Vec<String> = tables.keys().cloned().collect();
+    query = query.field(Field::new("_tables", TypeRef::named_nn_list_nn(TypeRef::STRING), move |_| {
+        FieldFuture::from_value(Some(async_graphql::Value::List(table_keys.iter().map(|key| async_graphql::Value::from(key.as_str())).collect())))
+    }));
+
+    for (table_key, table) in &tables {
+        let name = type_name(table);
+        let columns = graphql_columns(table);
+
+        // 对象类型，字段及关联
+        let mut object = Object::new(&name);
+        if let Some(comment) = table.comment.as_ref().filter(|comment| !comment.is_empty()) {
+            object = object.description(comment);
+        }
+        for (column, scalar) in &columns {
+            object = object.field(column_field(column, scalar));
+        }
+        for relation in relations.get(table_key).into_iter().flatten() {
+            let target_name = type_name(&tables[&relation.target]);
+            object = object.field(relation_field(relation, &target_name));
+        }
+        types.push(object.into());
+
+        // 过滤、排序
+        let mut filter = InputObject::new(format!("{}_filter", name));
+        let mut order_by = InputObject::new(format!("{}_order_by", name));
+        for (column, scalar) in &columns {
+            filter = filter.field(InputValue::new(column.as_str(), TypeRef::named(format!("{}Filter", scalar))));
+            order_by = order_by.field(InputValue::new(column.as_str(), TypeRef::named(ORDER_DIRECTION)));
+        }
+        types.push(filter.into());
+        types.push(order_by.into());
+
+        // 查询
+        query = query.field(list_field(&name, table_key, &name, None));
+        query = query.field(count_field(&name, table_key));
+        if let Some(key) = primary_key(table).filter(|key| columns.iter().any(|(column, _)| column == key)) {
+            query = query.field(by_id_field(&name, table_key, key, scalar_type(&table.columns[key].type_name)));
+        }
+
+        // 写操作: 只读的表不生成；整数 `id` 主键的表写入的字段不含 `id`，其余表只能新增，主键在 data 中给出
+        if table_access(&table.schema, &table.name) == Access::ReadOnly {
+            continue;
+        }
+        let integer_id = has_integer_id(table);
+        let writable: Vec<&(&String, &'static str)> = columns.iter().filter(|(column, _)| !integer_id || column.as_str() != "id").collect();
+        if writable.is_empty() {
+            continue;
+        }
+        let mut input = InputObject::new(format!("{}_input", name));
+        for (column, scalar) in writable {
+            input = input.field(InputValue::new(column.as_str(), TypeRef::named(*scalar)));
+        }
+        types.push(input.into());
+        for field in mutation_fields(&name, table_key, integer_id) {
+            mutation = mutation.field(field);
+        }
+        has_mutation = true;
+    }
+
+    let mut builder = Schema::build("Query", has_mutation.then_some("Mutation"), None).register(query);
+    if has_mutation {
+        builder = builder.register(mutation);
+    }
+    for ty in types {
+        builder = builder.register(ty);
+    }
+    if !conf.introspection {
+        builder = builder.disable_introspection();
+    }
+    builder.finish().map_err(|err| format!("graphql schema error: {}", err))
+}
+
+/// 标量的过滤条件，对应 APIJSON 的条件键
+fn filter_input(scalar: &str) -> InputObject {
+    let mut filter = InputObject::new(format!("{}Filter", scalar))
+        .field(InputValue::new("is_null", TypeRef::named(TypeRef::BOOLEAN)));
+    if scalar == JSON {
+        return filter;
+    }
+    filter = filter
+        .field(InputValue::new("eq", TypeRef::named(scalar)))
+        .field(InputValue::new("ne", TypeRef::named(scalar)));
+    if scalar == TypeRef::BOOLEAN {
+        return filter;
+    }
+    for op in ["gt", "gte", "lt", "lte"] {
+        filter = filter.field(InputValue::new(op, TypeRef::named(scalar)));
+    }
+    filter = filter
+        .field(InputValue::new("in", TypeRef::named_nn_list(scalar)))
+        .field(InputValue::new("nin", TypeRef::named_nn_list(scalar)));
+    if scalar == TypeRef::STRING {
+        filter = filter.field(InputValue::new("like", TypeRef::named(TypeRef::STRING)));
+    }
+    filter
+}
+
+/// 过滤条件转换为 APIJSON 条件: `{"price": {"gte": 10}}` -> `"price>=": 10`
+fn filter_conditions(filter: &serde_json::Value) -> Vec<(String, serde_json::Value)> {
+    let mut conditions = Vec::new();
+    let Some(filter) = filter.as_object() else { return conditions };
+    for (column, ops) in filter {
+        for (op, value) in ops.as_object().into_iter().flatten() {
+            let condition = match (op.as_str(), value) {
+                ("is_null", serde_json::Value::Bool(true)) => (column.clone(), serde_json::Value::Null),
+                ("is_null", serde_json::Value::Bool(false)) => (format!("{}!", column), serde_json::Value::Null),
+                (_, serde_json::Value::Null) => continue,
+                ("eq", _) => (column.clone(), value.clone()),
+                ("ne", _) => (format!("{}!", column), value.clone()),
+                ("gt", _) => (format!("{}>", column), value.clone()),
+                ("gte", _) => (format!("{}>=", column), value.clone()),
+                ("lt", _) => (format!("{}<", column), value.clone()),
+                ("lte", _) => (format!("{}<=", column), value.clone()),
+                ("in", _) => (format!("{}{{}}", column), value.clone()),
+                ("nin", _) => (format!("{}!{{}}", column), value.clone()),
+                ("like", _) => (format!("{}$", column), value.clone()),
+                _ => continue,
+            };
+            conditions.push(condition);
+        }
+    }
+    conditions
+}
+
+/// 排序转换为 `@order`: `[{"date": DESC}, {"id": ASC}]` -> `"date desc,id asc"`
+fn order_expr(order_by: &serde_json::Value) -> Option<String> {
+    let items: Vec<String> = order_by.as_array().into_iter().flatten()
+        .filter_map(serde_json::Value::as_object)
+        .flat_map(|item| item.iter())
+        .filter_map(|(column, direction)| direction.as_str().map(|direction| format!("{} {}", column, direction.to_lowercase())))
+        .collect();
+    if items.is_empty() { None } else { Some(items.join(",")) }
+}
+
+fn arg_json(args: &ObjectAccessor, name: &str) -> async_graphql::Result<serde_json::Value> {
+    match args.get(name) {
+        Some(value) => Ok(value.as_value().clone().into_json()?),
+        None => Ok(serde_json::Value::Null),
+    }
+}
+
+fn check_allowed(auth: &Auth, table_key: &str, method: &str) -> async_graphql::Result<()> {
+    let role = auth.role.as_deref().unwrap_or(ANONYMOUS_ROLE);
+    if is_allowed(table_key, method, role) {
+        Ok(())
+    } else {
+        Err(async_graphql::Error::new(format!("role '{}' is not allowed to {} {}", role, method, table_key)))
+    }
+}
+
+/// 按 `where`、`order_by`、`count`、`page` 参数及附加条件构建查询
+fn build_executor(auth: &Auth, table_key: &str, args: &ObjectAccessor, conditions: Vec<(String, serde_json::Value)>) -> async_graphql::Result<QueryExecutor> {
+    check_allowed(auth, table_key, "get")?;
+    let mut executor = QueryExecutor::new();
+    executor.parse_table(table_key)?;
+    executor.apply_policy(PolicyAction::Select, auth)?;
+    for (key, value) in conditions.into_iter().chain(filter_conditions(&arg_json(args, "where")?)) {
+        executor.parse_condition(&key, &value)?;
+    }
+    if let Some(order) = order_expr(&arg_json(args, "order_by")?) {
+        executor.parse_condition("@order", &serde_json::Value::String(order))?;
+    }
+    let page = arg_json(args, "page")?;
+    let count = match arg_json(args, "count")? {
+        serde_json::Value::Null => serde_json::json!(DEFAULT_MAX_COUNT),
+        count => count,
+    };
+    let limits = query_limits();
+    if limits.max_count > 0 && count.as_f64().is_some_and(|n| n < 1.0 || n > limits.max_count as f64) {
+        return Err(async_graphql::Error::new(format!("{}: count {} is out of range 1..={}", table_key, count, limits.max_count)));
+    }
+    executor.page_size(page, count);
+    executor.max_execution_time(limits.max_execution_time);
+    Ok(executor)
+}
+
+async fn query_rows(ctx: &ResolverContext<'_>, table_key: &str, conditions: Vec<(String, serde_json::Value)>) -> async_graphql::Result<Vec<Row>> {
+    let db = ctx.data::<DBConn>()?;
+    let budget = ctx.data::<RowBudget>()?;
+    budget.check(table_key)?;
+    let executor = build_executor(ctx.data::<Auth>()?, table_key, &ctx.args, conditions)?;
+    let rows = executor.exec(db).await?;
+    budget.consume(table_key, rows.len())?;
+    Ok(rows)
+}
+
+/// 按单个条件查询一行
+async fn query_one(ctx: &ResolverContext<'_>, table_key: &str, column: &str, value: serde_json::Value) -> async_graphql::Result<Option<Row>> {
+    let db = ctx.data::<DBConn>()?;
+    let auth = ctx.data::<Auth>()?;
+    let budget = ctx.data::<RowBudget>()?;
+    check_allowed(auth, table_key, "get")?;
+    budget.check(table_key)?;
+    let mut executor = QueryExecutor::new();
+    executor.parse_table(table_key)?;
+    executor.apply_policy(PolicyAction::Select, auth)?;
+    executor.parse_condition(column, &value)?;
+    executor.max_execution_time(query_limits().max_execution_time);
+    let row = executor.exec(db).await?.into_iter().next();
+    budget.consume(table_key, row.iter().count())?;
+    Ok(row)
+}
+
+fn list_args(field: Field, type_name: &str) -> Field {
+    field
+        .argument(InputValue::new("where", TypeRef::named(format!("{}_filter", type_name))))
+        .argument(InputValue::new("order_by", TypeRef::named_nn_list(format!("{}_order_by", type_name))))
+        .argument(InputValue::new("count", TypeRef::named(TypeRef::INT)))
+        .argument(InputValue::new("page", TypeRef::named(TypeRef::INT)))
+}
+
+fn rows_value(rows: Vec<Row>) -> FieldValue<'static> {
+    FieldValue::list(rows.into_iter().map(FieldValue::owned_any))
+}
+
+fn column_field(column: &str, scalar: &'static str) -> Field {
+    let column_name = column.to_string();
+    Field::new(column, TypeRef::named(scalar), move |ctx| {
+        let value = ctx.parent_value.downcast_ref::<Row>().and_then(|row| row.get(&column_name)).cloned().unwrap_or_default();
+        let value = match (scalar, value) {
+            (_, serde_json::Value::Null) => None,
+            // tinyint1_as_bool 关闭时数据库返回数字
+            (TypeRef::BOOLEAN, serde_json::Value::Number(n)) => Some(async_graphql::Value::Boolean(n.as_f64() != Some(0.0))),
+            (_, value) => async_graphql::Value::from_json(value).ok(),
+        };
+        FieldFuture::from_value(value)
+    })
+}
+
+/// `{table}(where, order_by, count, page)` 列表查询，`relation` 为关联字段时附加关联条件
+fn list_field(field_name: &str, table_key: &str, type_name: &str, relation: Option<Relation>) -> Field {
+    let table_key = table_key.to_string();
+    let field = Field::new(field_name, TypeRef::named_nn_list_nn(type_name), move |ctx| {
+        let table_key = table_key.clone();
+        let relation = relation.clone();
+        FieldFuture::new(async move {
+            let mut conditions = Vec::new();
+            if let Some(relation) = relation {
+                let value = ctx.parent_value.try_downcast_ref::<Row>()?.get(&relation.column).cloned().unwrap_or_default();
+                if value.is_null() {
+                    return Ok(Some(rows_value(vec![])));
+                }
+                conditions.push((relation.target_column, value));
+            }
+            Ok(Some(rows_value(query_rows(&ctx, &table_key, conditions).await?)))
+        })
+    });
+    list_args(field, type_name)
+}
+
+fn count_field(type_name: &str, table_key: &str) -> Field {
+    let table_key = table_key.to_string();
+    Field::new(format!("{}_count", type_name), TypeRef::named_nn(BIGINT), move |ctx| {
+        let table_key = table_key.clone();
+        FieldFuture::new(async move {
+            let db = ctx.data::<DBConn>()?;
+            let executor = build_executor(ctx.data::<Auth>()?, &table_key, &ctx.args, vec![])?;
+            let count = executor.exec_count(db).await?;
+            Ok(Some(FieldValue::value(count)))
+        })
+    })
+    .argument(InputValue::new("where", TypeRef::named(format!("{}_filter", type_name))))
+}
+
+fn by_id_field(type_name: &str, table_key: &str, key: &str, scalar: &'static str) -> Field {
+    let table_key = table_key.to_string();
+    let key = key.to_string();
+    Field::new(format!("{}_by_id", type_name), TypeRef::named(type_name), move |ctx| {
+        let table_key = table_key.clone();
+        let key = key.clone();
+        FieldFuture::new(async move {
+            let id = arg_json(&ctx.args, "id")?;
+            Ok(query_one(&ctx, &table_key, &key, id).await?.map(FieldValue::owned_any))
+        })
+    })
+    .argument(InputValue::new("id", TypeRef::named_nn(scalar)))
+}
+
+fn relation_field(relation: &Relation, target_type: &str) -> Field {
+    if relation.list {
+        return list_field(&relation.name, &relation.target, target_type, Some(relation.clone()));
+    }
+    let relation = relation.clone();
+    Field::new(relation.name.clone(), TypeRef::named(target_type), move |ctx| {
+        let relation = relation.clone();
+        FieldFuture::new(async move {
+            let value = ctx.parent_value.try_downcast_ref::<Row>()?.get(&relation.column).cloned().unwrap_or_default();
+            if value.is_null() {
+                return Ok(None);
+            }
+            Ok(query_one(&ctx, &relation.target, &relation.target_column, value).await?.map(FieldValue::owned_any))
+        })
+    })
+}
+
+/// 写操作结果: 成功时为表对应的结果，失败时为错误信息
+fn write_result(rpc_result: RpcResult<HashMap<String, serde_json::Value>>, table_key: &str) -> async_graphql::Result<Option<FieldValue<'static>>> {
+    let value = rpc_result.payload.and_then(|mut payload| payload.remove(table_key)).unwrap_or_default();
+    match value {
+        serde_json::Value::Number(_) if rpc_result.code == StatusCode::OK => Ok(Some(FieldValue::value(async_graphql::Value::from_json(value)?))),
+        serde_json::Value::String(err) => Err(async_graphql::Error::new(err)),
+        _ => Err(async_graphql::Error::new(rpc_result.msg.unwrap_or_else(|| format!("{} write failed", table_key)))),
+    }
+}
+
+/// `insert_{table}(data)`、`update_{table}(id, data)`、`delete_{table}(id)`，经由 REST 的写操作处理，`by_id` 为 false 时只有新增
+fn mutation_fields(type_name: &str, table_key: &str, by_id: bool) -> Vec<Field> {
+    let input_type = format!("{}_input", type_name);
+
+    let key = table_key.to_string();
+    let insert = Field::new(format!("insert_{}", type_name), TypeRef::named(BIGINT), move |ctx| {
+        let table_key = key.clone();
+        FieldFuture::new(async move {
+            let (db, auth) = (ctx.data::<DBConn>()?, ctx.data::<Auth>()?);
+            check_allowed(auth, &table_key, "post")?;
+            let data = arg_json(&ctx.args, "data")?;
+            let result = handle_post(db, HashMap::from([(table_key.clone(), data)]), auth).await;
+            write_result(result, &table_key)
+        })
+    })
+    .argument(InputValue::new("data", TypeRef::named_nn(&input_type)));
+    if !by_id {
+        return vec![insert];
+    }
+
+    let key = table_key.to_string();
+    let update = Field::new(format!("update_{}", type_name), TypeRef::named(BIGINT), move |ctx| {
+        let table_key = key.clone();
+        FieldFuture::new(async move {
+            let (db, auth) = (ctx.data::<DBConn>()?, ctx.data::<Auth>()?);
+            check_allowed(auth, &table_key, "put")?;
+            let mut data = arg_json(&ctx.args, "data")?;
+            if let Some(data) = data.as_object_mut() {
+                data.insert("id".to_string(), arg_json(&ctx.args, "id")?);
+            }
+            let result = handle_put(db, HashMap::from([(table_key.clone(), data)]), auth).await;
+            write_result(result, &table_key)
+        })
+    })
+    .argument(InputValue::new("id", TypeRef::named_nn(BIGINT)))
+    .argument(InputValue::new("data", TypeRef::named_nn(&input_type)));
+
+    let key = table_key.to_string();
+    let delete = Field::new(format!("delete_{}", type_name), TypeRef::named(BIGINT), move |ctx| {
+        let table_key = key.clone();
+        FieldFuture::new(async move {
+            let (db, auth) = (ctx.data::<DBConn>()?, ctx.data::<Auth>()?);
+            check_allowed(auth, &table_key, "delete")?;
+            let data = serde_json::json!({ "id": arg_json(&ctx.args, "id")? });
+            let result = handle_delete(db, HashMap::from([(table_key.clone(), data)]), auth).await;
+            write_result(result, &table_key)
+        })
+    })
+    .argument(InputValue::new("id", TypeRef::named_nn(BIGINT)));
+
+    vec![insert, update, delete]
+}
+
+#[cfg(test)]
+mod tests {
+    use common::yaml::Limits;
+    use crate::graphql::{check_document, filter_conditions, has_integer_id, has_query_operation, order_expr, scalar_type};
+    use crate::verify::tests::{test_table, test_table_with};
+
+    #[test]
+    fn test_graphql_conditions() {
+        assert_eq!(scalar_type("tinyint(1)"), "Boolean");
+        assert_eq!(scalar_type("int(10) unsigned"), "BigInt");
+        assert_eq!(scalar_type("int"), "Int");
+        assert_eq!(scalar_type("decimal(10,2)"), "Decimal");
+        assert_eq!(scalar_type("varchar(64)"), "String");
+
+        let conditions = filter_conditions(&serde_json::json!({
+            "price": { "gte": 10, "lt": 20 },
+            "status": { "in": ["on", "off"], "ne": null },
+            "deleted_at": { "is_null": true }
+        }));
+        assert_eq!(conditions, vec![
+            ("deleted_at".to_string(), serde_json::Value::Null),
+            ("price>=".to_string(), serde_json::json!(10)),
+            ("price<".to_string(), serde_json::json!(20)),
+            ("status{}".to_string(), serde_json::json!(["on", "off"])),
+        ]);
+        assert_eq!(order_expr(&serde_json::json!([{ "date": "DESC" }, { "id": "ASC" }])).unwrap(), "date desc,id asc");
+        assert!(order_expr(&serde_json::Value::Null).is_none());
+
+        assert!(has_query_operation(&serde_json::json!({ "query": "{ timeline_Moment_list { id } }" })));
+        assert!(has_query_operation(&serde_json::json!({ "query": "mutation m { delete_timeline_Moment(id: 1) } query q { __typename }" })));
+        assert!(!has_query_operation(&serde_json::json!({ "query": "mutation { delete_timeline_Moment(id: 1) }" })));
+        assert!(has_query_operation(&serde_json::json!({ "query": "{" })));
+
+        // 只有整数 id 主键的表生成更新、删除
+        assert!(has_integer_id(&test_table()));
+        assert!(!has_integer_id(&test_table_with("ecommerce", "sku", &[("id", "varchar(32)"), ("name", "varchar(64)")])));
+        let mut code = test_table_with("ecommerce", "coupon", &[("id", "bigint"), ("code", "varchar(32)")]);
+        code.columns.get_mut("id").unwrap().key = None;
+        code.columns.get_mut("code").unwrap().key = Some("PRI".to_string());
+        assert!(!has_integer_id(&code));
+    }
+
+    #[test]
+    fn test_check_document() {
+        let limits = Limits { max_depth: 2, max_nodes: 3, ..Limits::default() };
+        assert!(check_document("{ moments { id user { name } } __schema { types { fields { type { ofType { name } } } } } }", &limits).is_ok());
+        assert_eq!(check_document("{ moments { user { moments { id } } } }", &limits).unwrap_err(), "moments: depth 3 exceeds the limit 2");
+        assert_eq!(check_document("query { a: moments { id } ...f } fragment f on Query { b: moments { id } c: moments { user { id } } }", &limits).unwrap_err(), "user: 4 query nodes exceed the limit 3");
+        assert!(check_document("{ moments { user { moments { id } } } }", &Limits { max_depth: 0, ..limits }).is_ok());
+    }
+}
@@ -1,14 +1,21 @@
 use std::collections::HashMap;
 use http::StatusCode;
 use common::rpc::RpcResult;
-use database::core::{is_table_exists, DBConn};
+use database::TableMeta;
+use database::core::DBConn;
+use database::param::SqlParam;
+use crate::db::query_executor::QueryExecutor;
+use crate::policy::{policy_filter, Auth, PolicyAction};
+use crate::exposure::writable_table;
+use crate::verify::quote_table;
 
 /// 处理删除数据的请求
 /// 
 /// # 参数
 /// * `body_map` - 包含删除请求的数据映射，key为表名，value为删除条件
+/// * `auth` - 请求者身份，用于行级安全策略
 ///
-pub async fn handle_delete(db: &DBConn, body_map: HashMap<String, serde_json::Value>) -> RpcResult::<HashMap<String, serde_json::Value>> {
+pub async fn handle_delete(db: &DBConn, body_map: HashMap<String, serde_json::Value>, auth: &Auth) -> RpcResult::<HashMap<String, serde_json::Value>> {
     let mut rpc_result = RpcResult::<HashMap<String, serde_json::Value>>{ code: StatusCode::OK, msg: None, payload: None };
 
     // 初始化结果映射，用于存储每个表的处理结果
@@ -18,27 +25,18 @@ pub async fn handle_delete(db: &DBConn, body_map: HashMap<String, serde_json::Va
         // 检查参数是否为JSON对象格式
         match param.as_object() {
             Some(param_map) => {
-                // 解析 schema & table
-                let schema: &str;
-                let table: &str;
-                let schema_table_vec = table_key.split('.').collect::<Vec<&str>>();
-                if schema_table_vec.len() == 2 {
-                    schema = schema_table_vec[0];
-                    table = schema_table_vec[1];
-                } else {
-                    rpc_result.code = StatusCode::BAD_REQUEST;
-                    result_payload.insert((&table_key).to_string(), serde_json::json!(format!("{}'s schema empty", &table_key)));
-                    continue;
-                }
-                // 检查表是否存在，不存在则记录错误
-                if !is_table_exists(&schema, &table) {
-                    rpc_result.code = StatusCode::BAD_REQUEST;
-                    result_payload.insert((&table_key).to_string(), serde_json::json!(format!("table {} not exists", &table_key)));
-                    continue;
-                }
+                // 解析 schema & table，并检查表是否存在且可写
+                let table_meta = match writable_table(&table_key) {
+                    Ok(table_meta) => table_meta,
+                    Err(err) => {
+                        rpc_result.code = StatusCode::BAD_REQUEST;
+                        result_payload.insert(table_key.clone(), serde_json::json!(err));
+                        continue;
+                    }
+                };
 
                 // 删除操作
-                match do_delete(db, &schema, &table, param_map).await {
+                match do_delete(db, &table_meta, param_map, auth).await {
                     Ok(n) => { // 删除成功，记录影响行数
                         result_payload.insert((&table_key).to_string(), serde_json::json!(n));
                     },
@@ -63,41 +61,49 @@ pub async fn handle_delete(db: &DBConn, body_map: HashMap<String, serde_json::Va
 /// 
 /// # 参数
 /// * `db` - 数据库连接实例
-/// * `table` - 要操作的表名
+/// * `table` - 要操作的表元数据
 /// * `kvs` - 删除条件，支持两种格式：
 ///   * `{"id": number}` - 删除单条记录
 ///   * `{"id{}": [number]}` - 批量删除多条记录
+/// * `auth` - 请求者身份，只删除策略允许的行
 /// 
 /// # 返回值
 /// * `Ok(u64)` - 成功时返回受影响的行数
 /// * `Err(String)` - 失败时返回错误信息
 /// 
 /// # 错误情况
-/// * id 值类型不是数字
-/// * id{} 值类型不是数组
+/// * id 值类型不是整数
+/// * id{} 值类型不是整数数组
 /// * 没有提供 id 或 id{} 字段
-async fn do_delete(db: &DBConn, schema: &str, table: &str, kvs: &serde_json::Map<String, serde_json::Value>) -> Result<u64, String> {
+async fn do_delete(db: &DBConn, table: &TableMeta, kvs: &serde_json::Map<String, serde_json::Value>, auth: &Auth) -> Result<u64, String> {
+    let table_name = quote_table(&table.schema, &table.name);
+    // 行级安全策略条件
+    let filter = policy_filter(table, PolicyAction::Delete, auth);
+    let policy_sql: String = filter.clauses.iter().map(|clause| format!(" and {}", clause)).collect();
+    let policy_params = QueryExecutor::to_sql_params(&filter.params);
     if let Some(id_value) = kvs.get("id") {
-        // 处理单个 ID 删除
-        if !id_value.is_number() {
+        // 处理单个 ID 删除，id 必须为整数
+        let Some(id) = id_value.as_i64() else {
             log::warn!("delete.do_delete id: {:?}", id_value);
-            return Err(format!("'id' type is not num, key: {}, kvs: {:?}", table, kvs));
-        }
-        let sql = format!("delete from {}.{} where id={}", schema, table, id_value.to_string());
-        execute_delete(db, &sql).await
+            return Err(format!("'id' type is not integer, key: {}, kvs: {:?}", table.name, kvs));
+        };
+        let sql = format!("delete from {} where id=?{}", table_name, policy_sql);
+        let params = std::iter::once(SqlParam::Int(id)).chain(policy_params).collect();
+        execute_delete(db, &sql, params).await
     } else if let Some(id_array) = kvs.get("id{}") {
-        // 处理批量 ID 删除
-        if !id_array.is_array() {
+        // 处理批量 ID 删除，数组元素必须全部为整数
+        let ids: Option<Vec<i64>> = id_array.as_array().filter(|id_arr| !id_arr.is_empty())
+            .and_then(|id_arr| id_arr.iter().map(serde_json::Value::as_i64).collect());
+        let Some(ids) = ids else {
             log::warn!("wrong id array: {:?}", id_array);
-            return Err(format!("'id{{}}' type is not num array, key: {}, kvs: {:?}", table, kvs));
-        }
-        let id_arr = id_array.as_array().unwrap();
-        let placeholders = (0..id_arr.len()).map(|_| "?").collect::<Vec<_>>().join(",");
-        let sql = format!("delete from {}.{} where id in({})", schema, table, placeholders);
-        execute_delete(db, &sql).await
+            return Err(format!("'id{{}}' type is not integer array, key: {}, kvs: {:?}", table.name, kvs));
+        };
+        let sql = format!("delete from {} where id in({}){}", table_name, vec!["?"; ids.len()].join(","), policy_sql);
+        let params = ids.into_iter().map(SqlParam::Int).chain(policy_params).collect();
+        execute_delete(db, &sql, params).await
     } else {
         // 没有提供有效的 ID
-        Err(format!("data delete must have field 'id' or 'id{{}}', key: {}, kvs: {:?}", table, kvs))
+        Err(format!("data delete must have field 'id' or 'id{{}}', key: {}, kvs: {:?}", table.name, kvs))
     }
 }
 
@@ -106,6 +112,7 @@ async fn do_delete(db: &DBConn, schema: &str, table: &str, kvs: &serde_json::Map
 /// # 参数
 /// * `db` - 数据库连接实例
 /// * `sql` - 要执行的删除 SQL 语句
+/// * `params` - 绑定参数
 /// 
 /// # 返回值
 /// * `Ok(u64)` - 成功时返回受影响的行数
@@ -113,8 +120,8 @@ async fn do_delete(db: &DBConn, schema: &str, table: &str, kvs: &serde_json::Map
 /// 
 /// # 错误处理
 /// 会记录执行错误的日志，并将错误信息转换为字符串返回
-async fn execute_delete(db: &DBConn, sql: &str) -> Result<u64, String> {
-    match db.delete(sql).await {
+async fn execute_delete(db: &DBConn, sql: &str, params: Vec<SqlParam>) -> Result<u64, String> {
+    match db.delete(sql, params).await {
         Ok(row) => Ok(row),
         Err(err) => {
             log::error!("sql.delete error {} {:?}", sql, err);
@@ -1,42 +1,67 @@
 use std::collections::HashMap;
+use std::time::Instant;
 use fnv::FnvHashMap;
+use futures_util::future::join_all;
 use http::StatusCode;
 use common::rpc::RpcResult;
 use database::core::DBConn;
+use crate::db::join_executor::{JoinExecutor, JoinOutput, JoinRef};
 use crate::db::query_executor::DEFAULT_MAX_COUNT;
-use crate::db::query_context::{get_parent_node_path, QueryContext, QueryNode, RATIO_PRIMARY};
+use crate::db::query_context::{get_parent_node_path, ExplainMode, NamespaceQuery, QueryContext, QueryNode};
+use crate::limits::{query_error, query_limits};
+use crate::policy::{Auth, PolicyAction};
 use crate::utils::transform::transform_salve_value;
 
 /// 处理GET请求的异步方法
 ///
 /// # 参数
 /// * `body_map` - 包含请求参数的HashMap，键为String类型，值为serde_json::Value类型
+/// * `auth` - 请求者身份，用于行级安全策略
+/// * `explain_allowed` - 是否允许 `@explain` 调试输出，由调用方根据配置和角色判断
 ///
 /// # 返回值
 /// 返回serde_json::Value类型的JSON响应数据
-pub async fn handle_get(db: &DBConn, body_map: HashMap<String, serde_json::Value>) -> RpcResult::<HashMap<String, serde_json::Value>> {
+pub async fn handle_get(db: &DBConn, body_map: HashMap<String, serde_json::Value>, auth: &Auth, explain_allowed: bool) -> RpcResult::<HashMap<String, serde_json::Value>> {
     let mut ctx = QueryContext::from_json(body_map);
+    ctx.auth = auth.clone();
+    if ctx.explain != ExplainMode::Off && !explain_allowed {
+        return RpcResult::<HashMap<String, serde_json::Value>>{ code: StatusCode::FORBIDDEN, msg: Some("@explain is disabled".to_string()), payload: None };
+    }
+    // 查询开销限制: 深度、节点数、列表 count
+    ctx.limits = query_limits();
+    if let (StatusCode::OK, Err(err)) = (ctx.code, ctx.check_limits(&ctx.limits)) {
+        return RpcResult::<HashMap<String, serde_json::Value>>{ code: StatusCode::BAD_REQUEST, msg: Some(err), payload: None };
+    }
     ctx.response(db).await
 }
 
 impl QueryContext {
     async fn response(&mut self, db: &DBConn) -> RpcResult::<HashMap<String, serde_json::Value>> {
-        // 克隆 query_node 以避免借用冲突
-        let query_node = self.layer_query_node.clone();
-
-        // 按层级遍历节点并处理
-        for nodes in query_node.values() {
-            // 按权重降序排序
-            let mut sorted_nodes = nodes.clone();
-            sorted_nodes.sort_unstable_by(|a, b| b.borrow().weight.cmp(&a.borrow().weight));
-            for node in sorted_nodes {
-                let mut node_rc = node.borrow_mut();
-                if node_rc.weight >= RATIO_PRIMARY {
-                    self.query_primary_node(&mut *node_rc, db).await;
-                } else {
-                    self.query_relate_node(&mut *node_rc, db).await;
+        // 执行计划构建失败: 循环引用或引用了不存在的节点
+        if self.code != StatusCode::OK {
+            return RpcResult::<HashMap<String, serde_json::Value>>{ code: self.code, msg: self.err_msg.clone(), payload: None };
+        }
+
+        let started = Instant::now();
+        // 按执行计划逐阶段处理节点，节点依赖的数据均已在之前的阶段查出，同一阶段的节点并发查询
+        let stages = self.plan.stages.clone();
+        for stage in stages {
+            // 并入 JOIN 查询的从节点不单独查询
+            let stage: Vec<String> = stage.into_iter().filter(|node_path| !self.is_joined(node_path)).collect();
+            let mut nodes: Vec<QueryNode> = stage.iter().filter_map(|node_path| self.query_node.remove(node_path)).collect();
+            let outputs = join_all(nodes.iter_mut().map(|node| self.query_node_data(node, db))).await;
+            for (node, output) in nodes.into_iter().zip(outputs) {
+                match output {
+                    Ok(output) => self.apply_node_output(&node, output),
+                    Err((code, err_msg)) if self.code == StatusCode::OK => {
+                        self.code = code;
+                        self.err_msg = Some(err_msg);
+                    }
+                    Err(_) => {}
                 }
+                self.query_node.insert(node.path.clone(), node);
             }
+            if self.code != StatusCode::OK { break; }
         }
 
         // 构建响应结果映射
@@ -44,28 +69,30 @@ impl QueryContext {
         // 遍历所有主节点数据（每个主节点路径及其对应的查询结果）
         for (node_path, results) in &self.primary_node_data {
             // 获取当前主节点的引用
-            let node_ref = self.query_node.get(node_path).unwrap().borrow();
+            let node_ref = self.query_node.get(node_path).unwrap();
             // 获取命名空间（父节点路径）
             let namespace = get_parent_node_path(node_path);
             // 获取主节点名称
             let node_name = &node_ref.name;
             // 判断主节点是否为列表类型
             let is_list = node_ref.is_list;
-            // 获取主节点与从节点的关联字段映射关系
-            let primary_relate_kv = self.primary_relate_kv.get(node_path).cloned().unwrap_or_default();
 
             if is_list {
                 // 如果主节点是列表类型，遍历每个结果，构建主节点及其关联从节点的嵌套结构
-                let primary_node_result_list: Vec<_> = results.iter()
-                    .map(|result| self.build_primary_value(&namespace, node_name, result, &primary_relate_kv))
+                let mut primary_node_result_list: Vec<_> = results.iter()
+                    .map(|result| self.build_primary_value(&namespace, node_path, node_name, result))
                     .collect();
+                // RIGHT JOIN 中没有主节点数据的结果排在最后
+                if let Some(orphans) = self.join_orphans.get(node_path) {
+                    primary_node_result_list.extend(orphans.iter().cloned());
+                }
                 // 将结果列表插入到响应映射中，键为命名空间
                 response_payload.insert(namespace, serde_json::json!(primary_node_result_list));
             } else {
                 // 如果主节点不是列表类型，取第一个结果（若无则用默认值）
                 let result = results.first().cloned().unwrap_or_default();
                 // 构建主节点及其关联从节点的嵌套结构
-                let primary_value = self.build_primary_value(&namespace, node_name, &result, &primary_relate_kv);
+                let primary_value = self.build_primary_value(&namespace, node_path, node_name, &result);
                 // 将主节点及其关联从节点的所有键值对插入到响应映射中
                 for (key, value) in primary_value {
                     response_payload.insert(key, value);
@@ -73,111 +100,292 @@ impl QueryContext {
             }
         }
 
+        // 分页信息: 未被引用的命名空间默认以 total、info 返回，游标分页额外返回 next_cursor；
+        // 多个命名空间未被引用时以 `命名空间@total` 等区分，如 `[]@info`
+        let mut namespace_ref_kv = self.namespace_ref_kv.clone();
+        let mut unreferenced: Vec<&String> = self.namespace_info.keys()
+            .filter(|namespace| !namespace_ref_kv.values().any(|ref_path| &&get_parent_node_path(ref_path) == namespace))
+            .collect();
+        unreferenced.sort();
+        let prefixed = unreferenced.len() > 1;
+        for namespace in unreferenced {
+            let info = &self.namespace_info[namespace];
+            for field in ["total", "info", "next_cursor"] {
+                if field == "info" || info.get(field).is_some() {
+                    let key = if prefixed { format!("{}@{}", namespace, field) } else { field.to_string() };
+                    namespace_ref_kv.insert(key, format!("{}/{}", namespace, field));
+                }
+            }
+        }
+        for (key, ref_path) in namespace_ref_kv {
+            let namespace = get_parent_node_path(&ref_path);
+            let field = ref_path.rsplit('/').next().unwrap_or_default();
+            let value = match self.namespace_info.get(&namespace) {
+                Some(info) if field == "info" => info.clone(),
+                Some(info) => info.get(field).cloned().unwrap_or(serde_json::Value::Null),
+                None => serde_json::Value::Null,
+            };
+            response_payload.insert(key, value);
+        }
+
+        // 调试信息
+        if self.explain != ExplainMode::Off {
+            let explain = serde_json::json!({
+                "stages": self.plan.stages,
+                "nodes": self.explain_nodes,
+                "elapsed_ms": started.elapsed().as_secs_f64() * 1000.0,
+            });
+            response_payload.insert("@explain".to_string(), explain);
+        }
+
         let status_code = self.code;
         let err_msg = &self.err_msg;
         RpcResult::<HashMap<String, serde_json::Value>>{ code: status_code, msg: err_msg.to_owned(), payload: Some(response_payload) }
     }
 
-    fn build_primary_value(&self, namespace: &str, primary_node_name: &str, primary_node_data: &HashMap<String, serde_json::Value>, primary_relate_kv: &HashMap<String, String>) -> HashMap<String, serde_json::Value> {
+    fn build_primary_value(&self, namespace: &str, primary_node_path: &str, primary_node_name: &str, primary_node_data: &HashMap<String, serde_json::Value>) -> HashMap<String, serde_json::Value> {
         let mut result_map = HashMap::<String, serde_json::Value>::new();
 
         // 主节点数据
         result_map.insert(primary_node_name.to_string(), serde_json::to_value(primary_node_data.clone()).unwrap());
 
         // 从节点数据
-        for (primary_field, slave_node_field_path) in primary_relate_kv {
-            // 获取主节点中关联字段的值，用于查询从节点数据
-            let primary_field_value = primary_node_data.get(primary_field).unwrap();
-            // 从路径中提取从节点字段名称（取最后一个斜杠后的部分）
-            let slave_node_field = slave_node_field_path.split("/").last().unwrap();
-            // 构建从节点字段值的键，格式为"字段名/字段值"
-            let slave_node_field_value_key = format!("{}/{}", slave_node_field, primary_field_value);
-            // 根据从节点字段路径和值键获取对应的从节点数据
-            let slave_node_field_data_opt = self.get_slave_node_data(slave_node_field_path, &slave_node_field_value_key);
-            if slave_node_field_data_opt.is_some() {
-                // 计算从节点数据的相对路径
-                // 1. 首先获取从节点字段路径的父路径
-                // 2. 尝试去除命名空间前缀
-                // 3. 如果去除成功且结果为空，则使用原始父路径
-                // 4. 如果去除失败，也使用原始父路径
-                let node_data_relative_path = get_parent_node_path(slave_node_field_path)
-                    .strip_prefix(&format!("{}/", namespace))
-                    .map_or_else(
-                        || get_parent_node_path(slave_node_field_path),  // 前缀去除失败时的处理
-                        |stripped| if stripped.is_empty() {  // 前缀去除成功但结果为空的处理
-                            get_parent_node_path(slave_node_field_path) 
-                        } else {  // 前缀去除成功且结果非空的处理
-                            stripped.to_string() 
+        self.attach_slave_value(namespace, primary_node_path, primary_node_data, &mut result_map);
+
+        result_map
+    }
+
+    /// 将节点一行数据关联的从节点数据挂载到结果中，从节点又被其他节点依赖时递归挂载
+    ///
+    /// # 参数
+    /// * `namespace` - 结果所在的命名空间
+    /// * `node_path` - 当前节点路径
+    /// * `node_data` - 当前节点的一行数据
+    /// * `result_map` - 命名空间下的结果对象
+    fn attach_slave_value(&self, namespace: &str, node_path: &str, node_data: &HashMap<String, serde_json::Value>, result_map: &mut HashMap<String, serde_json::Value>) {
+        let Some(primary_relate_kv) = self.primary_relate_kv.get(node_path) else { return };
+        for (primary_field, slave_node_field_paths) in primary_relate_kv {
+            // 获取当前节点中关联字段的值，用于查找从节点数据
+            let Some(primary_field_value) = node_data.get(primary_field) else { continue };
+            for slave_node_field_path in slave_node_field_paths {
+                let slave_node_path = get_parent_node_path(slave_node_field_path);
+                // 从路径中提取从节点字段名称，构建 "字段名/字段值" 查找从节点数据
+                let slave_node_field = slave_node_field_path.rsplit('/').next().unwrap_or_default();
+                let slave_node_field_value_key = format!("{}/{}", slave_node_field, primary_field_value);
+                let Some(slave_rows) = self.get_slave_node_rows(&slave_node_path, &slave_node_field_value_key) else { continue };
+                let Some(slave_node) = self.query_node.get(&slave_node_path) else { continue };
+                let (slave_node_name, slave_is_list) = (&slave_node.name, slave_node.is_list);
+
+                // 计算从节点相对命名空间的路径，不在命名空间内时使用完整路径
+                let node_data_relative_path = if namespace.is_empty() {
+                    slave_node_path.clone()
+                } else {
+                    slave_node_path.strip_prefix(&format!("{}/", namespace)).unwrap_or(&slave_node_path).to_string()
+                };
+
+                match node_data_relative_path.split_once('/') {
+                    // 同一命名空间: 列表直接挂载数组，单条数据继续挂载其下游节点
+                    None if slave_is_list => {
+                        result_map.insert(node_data_relative_path, serde_json::to_value(slave_rows).unwrap());
+                    }
+                    None => {
+                        result_map.insert(node_data_relative_path, serde_json::to_value(&slave_rows[0]).unwrap());
+                        self.attach_slave_value(namespace, &slave_node_path, &slave_rows[0], result_map);
+                    }
+                    // 子命名空间: 每条数据作为数组中的一项，并在该项中挂载其下游节点
+                    Some((sub_namespace, name)) if !name.contains('/') => {
+                        let sub_namespace_path = if namespace.is_empty() { sub_namespace.to_string() } else { format!("{}/{}", namespace, sub_namespace) };
+                        let rows = if slave_is_list { &slave_rows[..] } else { &slave_rows[..1] };
+                        let items = rows.iter().map(|row| {
+                            let mut item_map = HashMap::new();
+                            item_map.insert(slave_node_name.to_string(), serde_json::to_value(row).unwrap());
+                            self.attach_slave_value(&sub_namespace_path, &slave_node_path, row, &mut item_map);
+                            serde_json::to_value(item_map).unwrap()
+                        });
+                        if let serde_json::Value::Array(array) = result_map.entry(sub_namespace.to_string()).or_insert_with(|| serde_json::json!([])) {
+                            array.extend(items);
                         }
-                    );
-                
-                // 根据相对路径的格式决定如何处理从节点数据
-                if node_data_relative_path.contains("/") {
-                    // 如果路径包含"/"，表示需要进行嵌套结构转换
-                    if let Some(slave_data) = slave_node_field_data_opt {
-                        // 创建一个只有一个键值对的映射，用于转换
+                    }
+                    // 多层嵌套的命名空间: 将扁平结构转换为嵌套结构
+                    Some(_) => {
+                        let slave_data = if slave_is_list { serde_json::to_value(slave_rows).unwrap() } else { serde_json::to_value(&slave_rows[0]).unwrap() };
                         let slave_field_value_map = std::iter::once((node_data_relative_path, slave_data)).collect::<HashMap<_, _>>();
-                        // 使用transform_salve_value函数将扁平结构转换为嵌套结构
                         result_map.extend(transform_salve_value(slave_field_value_map));
                     }
-                } else {
-                    // 如果路径不包含"/"，直接将数据添加到结果映射中
-                    result_map.insert(node_data_relative_path, slave_node_field_data_opt.unwrap());
                 }
             }
         }
-        
-        result_map
     }
 
     /// 获取从节点关联数据
     ///
     /// # 参数
-    /// * `slave_node_field_path` - 从节点字段路径，格式为"命名空间/字段名"
+    /// * `slave_node_path` - 从节点路径
     /// * `slave_node_field_value_key` - 从节点字段值键，格式为"字段名/字段值"
     ///
     /// # 返回值
-    /// 返回Option<serde_json::Value>，包含从节点数据的JSON值或None
-    fn get_slave_node_data(&self, slave_node_field_path: &str, slave_node_field_value_key: &str) -> Option<serde_json::Value> {
-        // 获取从节点路径（去除字段名部分）
-        let slave_node_path = get_parent_node_path(slave_node_field_path);
-        
-        // 链式操作获取从节点数据：
-        // 1. 首先从slave_node_relate_data获取路径对应的字段映射表
-        // 2. 然后从字段映射表获取指定键的值
-        // 3. 最后处理获取到的关联数据
-        self.slave_node_relate_data.get(&slave_node_path)
+    /// 返回匹配的从节点数据，没有数据时返回None
+    fn get_slave_node_rows(&self, slave_node_path: &str, slave_node_field_value_key: &str) -> Option<&Vec<HashMap<String, serde_json::Value>>> {
+        self.slave_node_relate_data.get(slave_node_path)
             .and_then(|field_map| field_map.get(slave_node_field_value_key))
-            .and_then(|relate_field_data| {
-                // 检查从节点是否为列表类型
-                let is_list = self.query_node.get(&slave_node_path)?.borrow().is_list;
-                
+            .filter(|relate_field_data| {
                 if relate_field_data.is_empty() { // 记录空数据日志
-                    log::debug!("slave.data: {}.{} is empty", &slave_node_path, slave_node_field_value_key);
-                    None
-                } else if is_list { // 列表类型：直接序列化整个数组
-                    Some(serde_json::to_value(relate_field_data).unwrap())
-                } else { // 非列表类型：只取第一个元素序列化
-                    Some(serde_json::to_value(&relate_field_data[0]).unwrap())
+                    log::debug!("slave.data: {}.{} is empty", slave_node_path, slave_node_field_value_key);
                 }
-            }
-        )
+                !relate_field_data.is_empty()
+            })
     }
 
-    async fn query_primary_node(&mut self, node: &mut QueryNode, db: &DBConn) {
-        // 添加关联字段到查询列
-        if let Some(primary_relate_kv) = self.primary_relate_kv.get(&node.path) {
-            for (column, _) in primary_relate_kv {
-                node.sql_executor.add_column(column);
+    /// 查询单个节点的数据，只读取上下文，结果由 `apply_node_output` 写回，便于同一阶段的节点并发执行
+    async fn query_node_data(&self, node: &mut QueryNode, db: &DBConn) -> Result<NodeOutput, (StatusCode, String)> {
+        let node_path = node.path.clone();
+        let bad_request = |err: String| (StatusCode::BAD_REQUEST, format!("{}: {}", node_path, err));
+        self.prepare_node_query(node).map_err(|err| (StatusCode::BAD_REQUEST, err))?;
+
+        // 调试信息: 依赖的节点
+        let mut output = NodeOutput::default();
+        if self.explain != ExplainMode::Off {
+            let depends_on = self.plan.dependencies.get(&node_path).cloned().unwrap_or_default();
+            output.explain = Some(serde_json::json!({ "depends_on": depends_on, "skipped": true }));
+        }
+
+        // 从节点: 以上游节点的字段值作为查询条件
+        if let Some(node_relate_kv) = self.slave_relate_kv.get(&node_path) {
+            for (field_name, primary_node_field_path) in node_relate_kv {
+                // 上游节点没有数据则无需查询
+                let value = match self.primary_node_related_field_values.get(primary_node_field_path) {
+                    Some(value) if !value.is_null() => value,
+                    _ => return Ok(output),
+                };
+                // 如果是数组类型，非列表节点设置分页大小为数组长度，列表节点沿用命名空间的分页参数按上级数据分区截取
+                if let (false, serde_json::Value::Array(array)) = (node.is_list, value) {
+                    node.sql_executor.page_size(serde_json::json!(0), serde_json::json!(array.len()));
+                }
+                node.sql_executor.parse_condition(field_name, value).map_err(bad_request)?;
+                // 确保关联字段在查询字段列表中
+                node.sql_executor.add_column(field_name).map_err(bad_request)?;
+            }
+            // 列表从节点按关联字段分区，每个上级数据各取 count 条
+            if node.is_list {
+                let partition: Vec<&String> = node_relate_kv.keys().collect();
+                node.sql_executor.partition_by(&partition).map_err(bad_request)?;
             }
         }
 
-        // 查询主节点数据
-        let Some(node_results) = self.query_node_data(node, db).await else { return };
+        // 当前节点被其他节点依赖时，添加关联字段到查询列
+        if let Some(primary_relate_kv) = self.primary_relate_kv.get(&node_path) {
+            for column in primary_relate_kv.keys() {
+                node.sql_executor.add_column(column).map_err(bad_request)?;
+            }
+        }
 
-        // 保存查询结果
-        self.primary_node_data.insert(node.path.clone(), node_results.clone());
+        // JOIN 模式: 从节点并入主节点的查询
+        let join = match self.joins.get(&node_path) {
+            Some(joins) => Some(self.prepare_join(node, joins).map_err(bad_request)?),
+            None => None,
+        };
+
+        // 列表主节点按需统计总数
+        let namespace = get_parent_node_path(&node.path);
+        if node.is_list && self.plan.is_primary(&node.path) {
+            // 游标分页: "cursor": "" 为第一页，之后传入上一页返回的 next_cursor
+            match self.namespace_node.get(&namespace).and_then(|attrs| attrs.get("cursor")) {
+                Some(_) if join.is_some() => return Err((StatusCode::BAD_REQUEST, format!("{}: cursor pagination does not support join", namespace))),
+                Some(serde_json::Value::String(cursor)) => node.sql_executor.keyset_page(cursor)
+                    .map_err(|err| (StatusCode::BAD_REQUEST, format!("{}: {}", namespace, err)))?,
+                Some(_) => return Err((StatusCode::BAD_REQUEST, format!("{}: 'cursor' expects a string", namespace))),
+                None => {}
+            }
+            let query_value = self.namespace_node.get(&namespace).and_then(|attrs| attrs.get("query"));
+            let namespace_query = NamespaceQuery::from_value(query_value)
+                .map_err(|err| (StatusCode::BAD_REQUEST, format!("{}: {}", namespace, err)))?;
+            let is_referenced = self.namespace_ref_kv.values().any(|ref_path| get_parent_node_path(ref_path) == namespace);
+            if namespace_query != NamespaceQuery::Data || is_referenced {
+                output.namespace_info = Some((namespace.clone(), Self::query_namespace_info(node, join.as_ref(), db).await.map_err(|e| query_error(&namespace, e))?));
+                if let Some(explain) = output.explain.as_mut() {
+                    let count_sql = join.as_ref().map_or_else(|| node.sql_executor.to_count_sql(), JoinExecutor::to_count_sql);
+                    explain["count_sql"] = serde_json::json!(count_sql);
+                }
+            }
+            if namespace_query == NamespaceQuery::Total { return Ok(output); }
+        }
+
+        // 查询节点数据
+        let started = Instant::now();
+        let results = match &join {
+            Some(join) => {
+                let mut join_output = join.exec(db).await.map_err(|e| query_error(&node_path, e))?;
+                let results = std::mem::take(&mut join_output.primary);
+                output.join = Some(join_output);
+                results
+            }
+            None => Self::exec_node_query(node, db).await?,
+        };
+        // 游标分页的分页信息: 页码无意义，以 next_cursor 表示是否有下一页
+        if node.sql_executor.is_keyset() {
+            let next_cursor = node.sql_executor.next_cursor(&results);
+            let (_, info) = output.namespace_info.get_or_insert_with(|| (namespace.clone(), serde_json::json!({ "count": node.sql_executor.limit() })));
+            if let Some(info) = info.as_object_mut() {
+                info.remove("page");
+                info.remove("max");
+                info.insert("more".to_string(), serde_json::json!(next_cursor.is_some()));
+                info.insert("next_cursor".to_string(), serde_json::json!(next_cursor));
+            }
+        }
+        if let Some(explain) = output.explain.as_mut() {
+            explain["skipped"] = serde_json::json!(false);
+            match &join {
+                Some(join) => {
+                    explain["sql"] = serde_json::json!(join.to_sql());
+                    explain["params"] = serde_json::json!(join.bind_params());
+                }
+                None => {
+                    explain["sql"] = serde_json::json!(node.sql_executor.to_sql());
+                    explain["params"] = serde_json::json!(node.sql_executor.bind_params());
+                }
+            }
+            explain["rows"] = serde_json::json!(results.len());
+            explain["elapsed_ms"] = serde_json::json!(started.elapsed().as_secs_f64() * 1000.0);
+            if self.explain == ExplainMode::Mysql {
+                let plan = match &join {
+                    Some(join) => join.explain(db).await,
+                    None => node.sql_executor.explain(db).await,
+                };
+                explain["explain"] = serde_json::json!(plan.map_err(|e| query_error(&node_path, e))?);
+            }
+        }
+        output.results = Some(results);
+        Ok(output)
+    }
 
+    /// 将节点的查询结果写回上下文
+    fn apply_node_output(&mut self, node: &QueryNode, output: NodeOutput) {
+        if let Some(explain) = output.explain {
+            self.explain_nodes.insert(node.path.clone(), explain);
+        }
+        if let Some((namespace, info)) = output.namespace_info {
+            self.namespace_info.insert(namespace, info);
+        }
+        // 行数预算: 所有节点合计的数据行数，含 JOIN 的从节点数据
+        let rows = output.results.as_ref().map_or(0, Vec::len)
+            + output.join.as_ref().map_or(0, |join| join.joined.iter().chain(join.orphans.iter().flatten()).map(Vec::len).sum());
+        self.rows += rows;
+        if self.limits.max_rows > 0 && self.rows > self.limits.max_rows && self.code == StatusCode::OK {
+            self.code = StatusCode::PAYLOAD_TOO_LARGE;
+            self.err_msg = Some(format!("{}: {} rows exceed the request row budget {}", node.path, self.rows, self.limits.max_rows));
+            return;
+        }
+        if let Some(join_output) = output.join {
+            self.apply_join_output(node, join_output);
+        }
+        let Some(node_results) = output.results else { return };
+
+        if !self.plan.is_primary(&node.path) {
+            self.process_relate_results(node, node_results);
+            return;
+        }
+        // 保存主节点查询结果
+        self.primary_node_data.insert(node.path.clone(), node_results.clone());
         // 处理查询结果
         if node.is_list {
             self.process_list_results(node, node_results);
@@ -186,6 +394,30 @@ impl QueryContext {
         }
     }
 
+    /// 将 JOIN 查询中从节点的数据按原有的关联方式写回，没有主节点数据的结果直接组装
+    fn apply_join_output(&mut self, node: &QueryNode, join_output: JoinOutput) {
+        let joins = self.joins.get(&node.path).cloned().unwrap_or_default();
+        for (join, rows) in joins.iter().zip(join_output.joined) {
+            if self.explain != ExplainMode::Off {
+                self.explain_nodes.insert(join.node_path.clone(), serde_json::json!({ "joined_into": node.path, "rows": rows.len() }));
+            }
+            let Some(joined_node) = self.query_node.remove(&join.node_path) else { continue };
+            self.process_relate_results(&joined_node, rows);
+            self.query_node.insert(joined_node.path.clone(), joined_node);
+        }
+
+        let orphans = join_output.orphans.into_iter().map(|orphan| {
+            let mut item = HashMap::from([(node.name.clone(), serde_json::Value::Null)]);
+            for (join, rows) in joins.iter().zip(orphan).filter(|(_, rows)| !rows.is_empty()) {
+                let Some(joined_node) = self.query_node.get(&join.node_path) else { continue };
+                let value = if joined_node.is_list { serde_json::to_value(rows).unwrap() } else { serde_json::to_value(&rows[0]).unwrap() };
+                item.insert(joined_node.name.clone(), value);
+            }
+            item
+        }).collect();
+        self.join_orphans.insert(node.path.clone(), orphans);
+    }
+
     // 处理列表类型结果
     fn process_list_results(&mut self, node: &QueryNode, results: Vec<HashMap<String, serde_json::Value>>) {
         for result in results {
@@ -212,75 +444,67 @@ impl QueryContext {
         }
     }
 
-    async fn query_relate_node(&mut self, node: &mut QueryNode, db: &DBConn) {
-        // 获取当前节点的路径和关联字段映射关系
-        let node_path = node.path.clone();
-        let node_relate_kv = self.slave_relate_kv.get(&node_path).cloned().unwrap_or_default();
-
-        // 处理每个关联字段的查询条件
-        for (field_name, primary_node_field_path) in &node_relate_kv {
-            // 从主节点获取关联字段的值
-            if let Some(value) = self.primary_node_related_field_values.get(primary_node_field_path) {
-                // 如果值为空则直接返回
-                if value.is_null() {
-                    return;
+    // 处理从节点结果
+    fn process_relate_results(&mut self, node: &QueryNode, node_results: Vec<HashMap<String, serde_json::Value>>) {
+        let node_relate_kv = self.slave_relate_kv.get(&node.path).cloned().unwrap_or_default();
+        // 处理每个关联字段的查询结果
+        for field in node_relate_kv.keys() {
+            let mut field_map = FnvHashMap::<String, Vec<HashMap<String, serde_json::Value>>>::default();
+            // 遍历查询结果，构建字段映射关系
+            for result in &node_results {
+                if let Some(field_value) = result.get(field) {
+                    // 构建字段路径格式：字段名/字段值
+                    let field_path = format!("{}/{}", field, field_value);
+                    // 将结果存入字段映射表
+                    field_map.entry(field_path).or_default().push(result.clone());
                 }
-                // 如果是数组类型，设置分页大小为数组长度
-                if let serde_json::Value::Array(array) = value {
-                    node.sql_executor.page_size(serde_json::json!(0), serde_json::json!(array.len()));
-                }
-                // 解析查询条件
-                node.sql_executor.parse_condition(field_name, value);
-            } else {
-                continue;
             }
-            // 确保关联字段在查询字段列表中
-            node.sql_executor.add_column(field_name);
+            // 将字段映射表存入从节点关联数据，多个关联字段的键以字段名区分
+            self.slave_node_relate_data.entry(node.path.clone()).or_default().extend(field_map);
         }
+        // 收集下游节点依赖的字段值
+        self.process_list_results(node, node_results);
+    }
 
-        // 执行节点数据查询
-        if let Some(node_results) = self.query_node_data(node, db).await {
-            // 处理每个关联字段的查询结果
-            for (field, _) in &node_relate_kv {
-                let mut field_map = FnvHashMap::<String, Vec<HashMap<String, serde_json::Value>>>::default();
-                // 处理字段名后缀@的情况
-                let field_key = if field.ends_with('@') {
-                    &field[..field.len() - 1]
-                } else {
-                    field.as_str()
-                };
-                // 遍历查询结果，构建字段映射关系
-                for result in &node_results {
-                    if let Some(field_value) = result.get(field_key) {
-                        // 构建字段路径格式：字段名/字段值
-                        let field_path = format!("{}/{}", field_key, field_value);
-                        // 将结果存入字段映射表
-                        field_map.entry(field_path).or_insert_with(Vec::new).push(result.clone());
-                    }
-                }
-                // 将字段映射表存入从节点关联数据
-                self.slave_node_relate_data.insert(node.path.clone(), field_map);
-            }
-        }
+    /// 统计列表主节点的总数，生成命名空间分页信息
+    async fn query_namespace_info(node: &QueryNode, join: Option<&JoinExecutor>, db: &DBConn) -> Result<serde_json::Value, sqlx::Error> {
+        let total = match join {
+            Some(join) => join.exec_count(db).await,
+            None => node.sql_executor.exec_count(db).await,
+        }?;
+        let count = node.sql_executor.limit().max(1) as i64;
+        let page = node.sql_executor.page() as i64;
+        let max = if total > 0 { (total - 1) / count } else { 0 };
+        Ok(serde_json::json!({ "total": total, "count": count, "page": page, "max": max, "more": page < max }))
     }
 
-    async fn query_node_data(&mut self, node: &mut QueryNode, db: &DBConn) -> Option<Vec<HashMap<String, serde_json::Value>>> {
+    /// 解析节点的表名、查询条件和分页参数
+    fn prepare_node_query(&self, node: &mut QueryNode) -> Result<(), String> {
         // 准备SQL查询的基本参数
         let node_name = &node.name.to_lowercase();
         let node_path = &node.path;
         let node_attrs = &node.attributes;
         // 设置查询的表名
-        let _ = node.sql_executor.parse_table(node_name);
-        // 解析节点属性中的查询条件
-        for (key, value) in node_attrs {
-            let _ = node.sql_executor.parse_condition(key, value);
+        node.sql_executor.parse_table(node_name)?;
+        // 行级安全策略
+        node.sql_executor.apply_policy(PolicyAction::Select, &self.auth)?;
+        node.sql_executor.max_execution_time(self.limits.max_execution_time);
+        // 解析节点属性中的查询条件，按键排序保证生成的SQL及参数顺序稳定
+        let mut attr_keys: Vec<&String> = node_attrs.keys().collect();
+        attr_keys.sort();
+        for key in attr_keys {
+            let parsed = match &node_attrs[key] {
+                value @ serde_json::Value::Object(_) if key.ends_with('@') => node.sql_executor.parse_subquery(key, value, &self.auth),
+                value => node.sql_executor.parse_condition(key, value),
+            };
+            parsed.map_err(|err| format!("{}: {}", node_path, err))?;
         }
-        
+
         // 处理列表查询的分页逻辑
         if node.is_list {
             let parent_path = get_parent_node_path(node_path);
             // 尝试从父节点获取分页参数
-            if let Some(parent_node_attrs) = self.namespace_node.get(&parent_path).cloned() {
+            if let Some(parent_node_attrs) = self.namespace_node.get(&parent_path) {
                 // 获取页码和每页数量，如果不存在则使用默认值
                 let page = parent_node_attrs.get("page").cloned().unwrap_or_else(|| serde_json::json!(0));
                 let count = parent_node_attrs.get("count").cloned().unwrap_or_else(|| serde_json::json!(DEFAULT_MAX_COUNT));
@@ -289,15 +513,42 @@ impl QueryContext {
                 node.sql_executor.page_size(serde_json::json!(0), serde_json::json!(DEFAULT_MAX_COUNT));
             }
         }
-        
-        // 执行SQL查询并处理结果
-        match node.sql_executor.exec(db).await {
-            Ok(results) => { Some(results) },
-            Err(e) => { // 保存错误信息到上下文
-                self.err_msg = Some(e.to_string());
-                self.code = StatusCode::INTERNAL_SERVER_ERROR;
-                None
+        Ok(())
+    }
+
+    /// 准备 JOIN 查询: 从节点与单独查询时一样解析表名、条件和行级安全策略，关联条件改为 ON 子句
+    fn prepare_join(&self, node: &QueryNode, joins: &[JoinRef]) -> Result<JoinExecutor, String> {
+        let mut tables = Vec::with_capacity(joins.len());
+        for join in joins {
+            let mut joined_node = self.query_node.get(&join.node_path).cloned()
+                .ok_or_else(|| format!("join node '{}' not exists", join.node_path))?;
+            self.prepare_node_query(&mut joined_node)?;
+            // 从节点被其他节点依赖时，添加关联字段到查询列
+            if let Some(primary_relate_kv) = self.primary_relate_kv.get(&join.node_path) {
+                for column in primary_relate_kv.keys() {
+                    joined_node.sql_executor.add_column(column)?;
+                }
             }
+            tables.push((join.clone(), joined_node.sql_executor));
         }
+        JoinExecutor::new(node.sql_executor.clone(), tables)
     }
-}
\ No newline at end of file
+
+    async fn exec_node_query(node: &QueryNode, db: &DBConn) -> Result<Vec<HashMap<String, serde_json::Value>>, (StatusCode, String)> {
+        // 执行SQL查询，错误信息由调用方保存到上下文
+        node.sql_executor.exec(db).await.map_err(|e| query_error(&node.path, e))
+    }
+}
+
+/// 单个节点的查询结果
+#[derive(Debug, Default)]
+struct NodeOutput {
+    // 查询到的数据，None 表示未查询(上游无数据或只统计总数)
+    results: Option<Vec<HashMap<String, serde_json::Value>>>,
+    // 命名空间分页信息(命名空间路径, 分页信息)
+    namespace_info: Option<(String, serde_json::Value)>,
+    // @explain 调试信息
+    explain: Option<serde_json::Value>,
+    // JOIN 查询中从节点的数据
+    join: Option<JoinOutput>,
+}
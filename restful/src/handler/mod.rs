@@ -2,4 +2,5 @@ pub mod head;
 pub mod get;
 pub mod put;
 pub mod post;
-pub mod delete;
\ No newline at end of file
+pub mod delete;
+pub mod resource;
\ No newline at end of file
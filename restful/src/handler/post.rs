@@ -2,57 +2,70 @@ use std::collections::HashMap;
 use http::StatusCode;
 use common::rpc::RpcResult;
 use common::utils::get_next_id;
-use database::core::{is_table_exists, DBConn};
+use database::TableMeta;
+use database::core::DBConn;
+use database::param::SqlParam;
+use crate::db::query_executor::QueryExecutor;
+use crate::policy::{check_policy, Auth, PolicyAction};
+use crate::exposure::{check_writable_columns, writable_table};
+use crate::limits::query_limits;
+use crate::verify::{quote_ident, quote_table, resolve_columns};
+
+/// 插入后返回整行
+const RETURN_KEY: &str = "@return";
 
 /// 处理数据插入请求
 /// 
 /// # 参数
-/// * `body_map` - 包含插入请求的数据映射，key为表名，value为要插入的数据
+/// * `body_map` - 包含插入请求的数据映射，key为表名，value为要插入的数据；`schema.table[]` 为批量插入，value为对象数组
+/// * `auth` - 请求者身份，用于行级安全策略
 /// 
 /// # 返回值
 /// 返回 JSON 格式的处理结果：
-/// * 成功：返回插入后的完整记录数据
-/// * 失败：`{"code": 400, "msg": "错误信息"}`
-pub async fn handle_post(db: &DBConn, body_map: HashMap<String, serde_json::Value>) -> RpcResult::<HashMap<String, serde_json::Value>> {
+/// * 成功：返回插入后的完整记录数据，批量插入时为每行的 `{"index": 0, "id": 1}`
+/// * 失败：`{"code": 400, "msg": "错误信息"}`，批量插入时失败的行为 `{"index": 1, "error": "错误信息"}`
+pub async fn handle_post(db: &DBConn, body_map: HashMap<String, serde_json::Value>, auth: &Auth) -> RpcResult::<HashMap<String, serde_json::Value>> {
     let mut rpc_result = RpcResult::<HashMap<String, serde_json::Value>>{ code: StatusCode::OK, msg: None, payload: None };
 
     let mut result_payload = HashMap::new();
     for (table_key, param) in body_map {
-        match param.as_object() {
-            Some(param_map) => {
-                // 解析 schema & table
-                let schema: &str;
-                let table: &str;
-                let schema_table_vec = table_key.split('.').collect::<Vec<&str>>();
-                if schema_table_vec.len() == 2 {
-                    schema = schema_table_vec[0];
-                    table = schema_table_vec[1];
-                } else {
-                    rpc_result.code = StatusCode::BAD_REQUEST;
-                    result_payload.insert((&table_key).to_string(), serde_json::json!(format!("{}'s schema should be specified", &table_key)));
-                    continue;
-                }
-                // 检查表是否存在，不存在则记录错误
-                if !is_table_exists(&schema, &table) {
-                    rpc_result.code = StatusCode::BAD_REQUEST;
-                    result_payload.insert((&table_key).to_string(), serde_json::json!(format!("table {} not exists", &table_key)));
-                    continue;
-                }
+        let table_name = table_key.strip_suffix("[]");
+        let well_formed = match table_name {
+            Some(_) => param.is_array(),
+            None => param.is_object(),
+        };
+        if !well_formed {
+            rpc_result.code = StatusCode::BAD_REQUEST;
+            rpc_result.msg = Some("parameter format error".to_string());
+            continue;
+        }
 
-                // 写入数据
-                match insert_one(db, &schema, &table, param_map).await {
-                    Ok(id) => {
-                        result_payload.insert(table_key.clone(), serde_json::json!(id));
-                    },
-                    Err(err) => {
-                        rpc_result.code = StatusCode::BAD_REQUEST;
-                        result_payload.insert(table_key.clone(), serde_json::Value::String(err));
-                    }
-                }
+        // 解析 schema & table，并检查表是否存在且可写
+        let table_meta = match writable_table(table_name.unwrap_or(&table_key)) {
+            Ok(table_meta) => table_meta,
+            Err(err) => {
+                rpc_result.code = StatusCode::BAD_REQUEST;
+                result_payload.insert(table_key.clone(), serde_json::json!(err));
+                continue;
             }
-            None => {
+        };
+
+        // 写入数据
+        let result = match &param {
+            serde_json::Value::Array(rows) => insert_rows(db, &table_meta, rows, auth).await,
+            _ => insert_one(db, &table_meta, param.as_object().unwrap(), auth).await,
+        };
+        match result {
+            Ok(result) => {
+                // 批量插入中有失败的行
+                if result.as_array().is_some_and(|rows| rows.iter().any(|row| row.get("error").is_some())) {
+                    rpc_result.code = StatusCode::BAD_REQUEST;
+                }
+                result_payload.insert(table_key.clone(), result);
+            },
+            Err(err) => {
                 rpc_result.code = StatusCode::BAD_REQUEST;
-                rpc_result.msg = Some("parameter format error".to_string());
+                result_payload.insert(table_key.clone(), serde_json::Value::String(err));
             }
         }
     }
@@ -66,36 +79,186 @@ pub async fn handle_post(db: &DBConn, body_map: HashMap<String, serde_json::Valu
 /// 
 /// # 参数
 /// * `db` - 数据库连接实例
-/// * `table` - 要插入数据的表名
-/// * `kvs` - 包含要插入的字段和值的键值对映射
+/// * `table` - 要插入数据的表元数据
+/// * `kvs` - 包含要插入的字段和值的键值对映射，`"@return": "*"` 时返回插入的整行
+/// * `auth` - 请求者身份，写入的值需满足行级安全策略
 /// 
 /// # 返回值
-/// * `Ok(i64)` - 成功时返回插入记录的 ID
+/// * `Ok(Value)` - 成功时返回插入记录的 ID，或插入后的整行
 /// * `Err(String)` - 失败时返回错误信息
 /// 
 /// # 实现细节
-/// 将传入的键值对转换为 SQL INSERT 语句，格式为：
+/// 值按字段类型转换后以参数绑定，格式为：
 /// ```sql
-/// INSERT INTO table_name(field1,field2) VALUES(value1,value2)
+/// INSERT INTO `schema`.`table`(`id`,`field1`,`field2`) VALUES(?,?,?)
 /// ```
-async fn insert_one(db: &DBConn, schema: &str, table: &str, kvs: &serde_json::Map<String, serde_json::Value>) -> Result<i64, String> {
-    let data_id = get_next_id();
-    let mut fields = Vec::new();
-    let mut values = Vec::new();
-
-    // 自动生成 ID
-    fields.push("id");
-    values.push(data_id.to_string());
-    for (field, value) in kvs {
-        fields.push(field.as_str());
-        values.push(value.to_string());
-    }
-    let sql = format!("INSERT INTO {}.{}({}) VALUES({})", schema, table, fields.join(","), values.join(","));
-    match db.insert(&sql).await {
+async fn insert_one(db: &DBConn, table: &TableMeta, kvs: &serde_json::Map<String, serde_json::Value>, auth: &Auth) -> Result<serde_json::Value, String> {
+    let mut kvs = kvs.clone();
+    let return_row = match kvs.remove(RETURN_KEY) {
+        None => false,
+        Some(serde_json::Value::String(columns)) if columns == "*" => true,
+        Some(_) => return Err(format!("'{}' only supports \"*\"", RETURN_KEY)),
+    };
+    let row = prepare_row(db, table, 0, &kvs, auth)?;
+    let (sql, params) = insert_sql(table, std::slice::from_ref(&row));
+    log::info!("sql.insert: {}", sql);
+    match db.insert(&sql, params).await {
+        Ok(cnt) if cnt > 0 && return_row => fetch_inserted(db, table, row.id, auth).await,
         Ok(cnt) => {
-            let result_id = if cnt > 0 { data_id as i64 } else { -1i64 };
-            Ok(result_id)
+            let result_id = if cnt > 0 { row.id } else { -1i64 };
+            Ok(serde_json::json!(result_id))
         },
         Err(e) => Err(e.to_string())
     }
-}
\ No newline at end of file
+}
+
+/// 批量插入多行，按 `restful.limits.insert_batch_size` 分批生成多行 INSERT，每行生成各自的 ID
+///
+/// 返回与请求顺序一致的每行结果，校验失败的行不参与插入；一批插入失败时逐行重试，定位失败的行，
+/// 非事务引擎(如 MyISAM)中失败的批次可能已写入部分行，按生成的 ID 查出已写入的行视为成功，不再重试
+async fn insert_rows(db: &DBConn, table: &TableMeta, rows: &[serde_json::Value], auth: &Auth) -> Result<serde_json::Value, String> {
+    let limits = query_limits();
+    if limits.max_insert_rows > 0 && rows.len() > limits.max_insert_rows {
+        return Err(format!("{} rows exceed the limit of {}", rows.len(), limits.max_insert_rows));
+    }
+    let mut results: Vec<serde_json::Value> = (0..rows.len()).map(|index| serde_json::json!({ "index": index })).collect();
+    let mut prepared = Vec::with_capacity(rows.len());
+    for (index, row) in rows.iter().enumerate() {
+        let row = match row.as_object() {
+            Some(kvs) => prepare_row(db, table, index, kvs, auth),
+            None => Err("row expects an object".to_string()),
+        };
+        match row {
+            Ok(row) => prepared.push(row),
+            Err(err) => results[index]["error"] = serde_json::json!(err),
+        }
+    }
+
+    for batch in prepared.chunks(limits.insert_batch_size.max(1)) {
+        let (sql, params) = insert_sql(table, batch);
+        log::info!("sql.insert: {}, rows: {}", sql, batch.len());
+        let outcomes = match db.insert(&sql, params).await {
+            Ok(_) => batch.iter().map(|row| (row, Ok(()))).collect(),
+            Err(err) if batch.len() == 1 => vec![(&batch[0], Err(err.to_string()))],
+            Err(err) => {
+                log::warn!("sql.insert batch error, retry row by row: {}", err);
+                let inserted = inserted_ids(db, table, batch).await.map_err(|err| err.to_string());
+                let mut outcomes = Vec::with_capacity(batch.len());
+                for row in batch {
+                    let outcome = match &inserted {
+                        Ok(ids) if ids.contains(&row.id) => Ok(()),
+                        Ok(_) => {
+                            let (sql, params) = insert_sql(table, std::slice::from_ref(row));
+                            db.insert(&sql, params).await.map(|_| ()).map_err(|err| err.to_string())
+                        }
+                        Err(err) => Err(err.clone()),
+                    };
+                    outcomes.push((row, outcome));
+                }
+                outcomes
+            }
+        };
+        for (row, outcome) in outcomes {
+            match outcome {
+                Ok(()) => results[row.index]["id"] = serde_json::json!(row.id),
+                Err(err) => results[row.index]["error"] = serde_json::json!(err),
+            }
+        }
+    }
+    Ok(serde_json::Value::Array(results))
+}
+
+/// 一批行中已写入表中的 ID
+async fn inserted_ids(db: &DBConn, table: &TableMeta, rows: &[InsertRow]) -> Result<Vec<i64>, sqlx::Error> {
+    let sql = format!("select id from {} where id in({})", quote_table(&table.schema, &table.name), vec!["?"; rows.len()].join(","));
+    let existing = db.query_list(&sql, rows.iter().map(|row| SqlParam::Int(row.id)).collect()).await?;
+    Ok(existing.iter().filter_map(|row| row.get("id").and_then(serde_json::Value::as_i64)).collect())
+}
+
+/// 校验并转换后待插入的一行
+struct InsertRow {
+    // 在请求中的序号
+    index: usize,
+    id: i64,
+    // 字段名及转换后的值，第一个为 `id`
+    values: Vec<(String, SqlParam)>,
+}
+
+/// 检查行级安全策略和字段，按字段类型转换值，并生成 ID
+fn prepare_row(db: &DBConn, table: &TableMeta, index: usize, kvs: &serde_json::Map<String, serde_json::Value>, auth: &Auth) -> Result<InsertRow, String> {
+    check_policy(table, PolicyAction::Insert, auth, kvs, false)?;
+    let id = get_next_id() as i64;
+    // 字段名必须存在于表元数据中，且不能是只读字段
+    let columns = resolve_columns(table, kvs.keys())?;
+    check_writable_columns(table, &columns)?;
+    let mut values = vec![("id".to_string(), SqlParam::Int(id))];
+    for (column, value) in columns.into_iter().zip(kvs.values()) {
+        let param = db.column_param(&table.columns[&column], value)?;
+        values.push((column, param));
+    }
+    Ok(InsertRow { index, id, values })
+}
+
+/// 生成 INSERT 语句，多行时字段取各行字段的并集，行中没有的字段为 `DEFAULT`
+fn insert_sql(table: &TableMeta, rows: &[InsertRow]) -> (String, Vec<SqlParam>) {
+    let mut columns: Vec<&str> = Vec::new();
+    for (column, _) in rows.iter().flat_map(|row| row.values.iter()) {
+        if !columns.contains(&column.as_str()) {
+            columns.push(column);
+        }
+    }
+    let mut params = Vec::new();
+    let mut values = Vec::with_capacity(rows.len());
+    for row in rows {
+        let placeholders: Vec<&str> = columns.iter().map(|column| match row.values.iter().find(|(name, _)| name == column) {
+            Some((_, param)) => {
+                params.push(param.clone());
+                "?"
+            }
+            None => "DEFAULT",
+        }).collect();
+        values.push(format!("({})", placeholders.join(",")));
+    }
+    let fields: Vec<String> = columns.iter().map(|column| quote_ident(column)).collect();
+    let sql = format!("INSERT INTO {}({}) VALUES{}", quote_table(&table.schema, &table.name), fields.join(","), values.join(","));
+    (sql, params)
+}
+
+/// 按 id 查询刚插入的行，隐藏字段及查询策略与 GET 一致，查不到时返回 id
+async fn fetch_inserted(db: &DBConn, table: &TableMeta, id: i64, auth: &Auth) -> Result<serde_json::Value, String> {
+    let mut executor = QueryExecutor::new();
+    executor.parse_table(&format!("{}.{}", table.schema, table.name))?;
+    executor.apply_policy(PolicyAction::Select, auth)?;
+    executor.parse_condition("id", &serde_json::json!(id))?;
+    match executor.exec(db).await {
+        Ok(mut rows) if !rows.is_empty() => Ok(serde_json::json!(rows.swap_remove(0))),
+        Ok(_) => Ok(serde_json::json!(id)),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use database::param::SqlParam;
+    use crate::handler::post::{insert_sql, InsertRow};
+    use crate::verify::tests::test_table;
+
+    #[test]
+    fn test_insert_sql() {
+        let row = |id: i64, values: &[(&str, SqlParam)]| InsertRow {
+            index: 0,
+            id,
+            values: [("id", SqlParam::Int(id))].iter().chain(values).map(|(column, param)| (column.to_string(), param.clone())).collect(),
+        };
+        let rows = vec![
+            row(1, &[("name", SqlParam::from("a"))]),
+            row(2, &[("like_cnt", SqlParam::Int(3)), ("name", SqlParam::Null)]),
+        ];
+        let (sql, params) = insert_sql(&test_table(), &rows[..1]);
+        assert_eq!(sql, "INSERT INTO `ecommerce`.`product`(`id`,`name`) VALUES(?,?)");
+        assert_eq!(params, vec![SqlParam::Int(1), SqlParam::from("a")]);
+        let (sql, params) = insert_sql(&test_table(), &rows);
+        assert_eq!(sql, "INSERT INTO `ecommerce`.`product`(`id`,`name`,`like_cnt`) VALUES(?,?,DEFAULT),(?,?,?)");
+        assert_eq!(params, vec![SqlParam::Int(1), SqlParam::from("a"), SqlParam::Int(2), SqlParam::Null, SqlParam::Int(3)]);
+    }
+}
@@ -1,12 +1,19 @@
 use std::collections::HashMap;
 use http::StatusCode;
 use common::rpc::RpcResult;
-use database::core::{is_table_exists, DBConn};
+use database::TableMeta;
+use database::core::DBConn;
+use database::param::SqlParam;
+use crate::db::query_executor::QueryExecutor;
+use crate::policy::{check_policy, policy_filter, Auth, PolicyAction};
+use crate::exposure::{check_writable_columns, writable_table};
+use crate::verify::{quote_ident, quote_table, resolve_column};
 
 /// 处理数据更新请求
 /// 
 /// # 参数
 /// * `body_map` - 包含更新请求的数据映射，key为表名，value为更新数据
+/// * `auth` - 请求者身份，用于行级安全策略
 /// 
 /// # 返回值
 /// 返回 JSON 格式的处理结果：
@@ -23,34 +30,25 @@ use database::core::{is_table_exists, DBConn};
 ///   }
 /// }
 /// ```
-pub async fn handle_put(db: &DBConn, body_map: HashMap<String, serde_json::Value>) -> RpcResult::<HashMap<String, serde_json::Value>> {
+pub async fn handle_put(db: &DBConn, body_map: HashMap<String, serde_json::Value>, auth: &Auth) -> RpcResult::<HashMap<String, serde_json::Value>> {
     let mut rpc_result = RpcResult::<HashMap<String, serde_json::Value>>{ code: StatusCode::OK, msg: None, payload: None };
 
     let mut result_payload = HashMap::new();
     for (table_key, param) in body_map {
         match param.as_object() {
             Some(param_map) => {
-                // 解析 schema & table
-                let schema: &str;
-                let table: &str;
-                let schema_table_vec = table_key.split('.').collect::<Vec<&str>>();
-                if schema_table_vec.len() == 2 {
-                    schema = schema_table_vec[0];
-                    table = schema_table_vec[1];
-                } else {
-                    rpc_result.code = StatusCode::BAD_REQUEST;
-                    result_payload.insert((&table_key).to_string(), serde_json::json!(format!("{}'s schema should be specified", &table_key)));
-                    continue;
-                }
-                // 检查表是否存在，不存在则记录错误
-                if !is_table_exists(&schema, &table) {
-                    rpc_result.code = StatusCode::BAD_REQUEST;
-                    result_payload.insert((&table_key).to_string(), serde_json::json!(format!("table {} not exists", &table_key)));
-                    continue;
-                }
+                // 解析 schema & table，并检查表是否存在且可写
+                let table_meta = match writable_table(&table_key) {
+                    Ok(table_meta) => table_meta,
+                    Err(err) => {
+                        rpc_result.code = StatusCode::BAD_REQUEST;
+                        result_payload.insert(table_key.clone(), serde_json::json!(err));
+                        continue;
+                    }
+                };
 
                 // 更新数据
-                match update_one(db, &schema, &table, param_map).await {
+                match update_one(db, &table_meta, param_map, auth).await {
                     Ok(id) => {
                         result_payload.insert(table_key.clone(), serde_json::json!(id));
                     },
@@ -74,32 +72,44 @@ pub async fn handle_put(db: &DBConn, body_map: HashMap<String, serde_json::Value
 
 // updateOne 执行单条记录的更新操作
 // 参数：
-//   - table: 要更新的表名
+//   - table: 要更新的表元数据
 //   - kvs: 包含更新字段和值的键值对映射，必须包含 id 字段
+//   - auth: 请求者身份，只能更新策略允许的行，且更新后的值仍需满足策略
 //
 // 返回：
 //   - int64: 更新记录的 id，如果出错则返回负数错误码
 //   - error: 错误信息，如果成功则为 nil
-pub async fn update_one(db: &DBConn, schema: &str, table: &str, kvs: &serde_json::Map<String, serde_json::Value>) -> Result<i64, String> {
+pub async fn update_one(db: &DBConn, table: &TableMeta, kvs: &serde_json::Map<String, serde_json::Value>, auth: &Auth) -> Result<i64, String> {
     if let Some(id_value) = kvs.get("id") {
-        // 检查 id 是否为数字类型
-        if !id_value.is_number() {
-            return Err(format!("'id' type is not num, key: {}, kvs: {:?}", table, kvs));
-        }
-        let id = id_value.as_i64().unwrap();
-        // 构建更新字段和参数
+        // 检查 id 是否为整数
+        let id = id_value.as_i64().ok_or_else(|| format!("'id' type is not integer, key: {}, kvs: {:?}", table.name, kvs))?;
+        // 构建更新字段和参数，字段名必须存在于表元数据中，且不能是只读字段，值按字段类型转换后绑定
         let mut fields = Vec::new();
+        let mut columns = Vec::new();
+        let mut params = Vec::new();
         for (k, v) in kvs.iter() {
             if k != "id" {
-                fields.push(format!("`{}`={}", k, v));
+                let column = resolve_column(table, k)?;
+                fields.push(format!("{}=?", quote_ident(&column)));
+                params.push(db.column_param(&table.columns[&column], v)?);
+                columns.push(column);
             }
         }
-        let sql = format!("update {}.{} set {} where id={}", schema, table, fields.join(","), id);
-        match db.update(&sql).await {
+        check_writable_columns(table, &columns)?;
+        check_policy(table, PolicyAction::Update, auth, kvs, true)?;
+        let filter = policy_filter(table, PolicyAction::Update, auth);
+        let mut sql = format!("update {} set {} where id=?", quote_table(&table.schema, &table.name), fields.join(","));
+        params.push(SqlParam::Int(id));
+        for clause in &filter.clauses {
+            sql.push_str(" and ");
+            sql.push_str(clause);
+        }
+        params.extend(QueryExecutor::to_sql_params(&filter.params));
+        match db.update(&sql, params).await {
             Ok(cnt) => if cnt > 0 { Ok(id) } else { Ok(-1) },
             Err(e) => Err(e.to_string())
         }
     } else {
-        Err(format!("data update must have 'id' field, key: {}, kvs: {:?}", table, kvs))
+        Err(format!("data update must have 'id' field, key: {}, kvs: {:?}", table.name, kvs))
     }
-}
\ No newline at end of file
+}
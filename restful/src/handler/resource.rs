@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use http::StatusCode;
+use common::rpc::RpcResult;
+
+/// PostgREST 风格的请求转换后的 APIJSON 请求，`/{schema}/{table}` 及 `/{schema}/{table}/{id}`
+#[derive(Debug, PartialEq)]
+pub struct ResourceRequest {
+    // APIJSON 方法: get/post/put/delete
+    pub method: &'static str,
+    // schema.table
+    pub table_key: String,
+    // 路径中的 id，或写操作中 `id=eq.` 过滤的 id
+    pub id: Option<i64>,
+    // 请求体，与 `/{method}.json` 的请求体一致
+    pub body_map: HashMap<String, serde_json::Value>,
+}
+
+/// 转换 PostgREST 风格的请求
+///
+/// # 参数
+/// * `http_method` - GET/POST/PATCH/DELETE
+/// * `table_key` - `schema.table`
+/// * `id` - 路径中的 id
+/// * `query` - 查询参数，如 `status=eq.paid&price=gt.10&order=id.desc&limit=20&select=id,name`
+/// * `body` - POST、PATCH 的请求体
+pub fn to_apijson_request(http_method: &str, table_key: &str, id: Option<&str>, query: &[(String, String)], body: Option<serde_json::Value>) -> Result<ResourceRequest, String> {
+    let mut id = id.map(|id| id.parse::<i64>().map_err(|_| format!("invalid id '{}'", id))).transpose()?;
+    let mut node = serde_json::Map::new();
+    let mut namespace = serde_json::Map::new();
+    let (mut limit, mut offset) = (None, None);
+    for (key, value) in query {
+        match key.as_str() {
+            "select" => { node.insert("@column".to_string(), serde_json::json!(value)); }
+            "order" => { node.insert("@order".to_string(), serde_json::json!(parse_order(value)?)); }
+            "limit" => limit = Some(value.parse::<i64>().ok().filter(|limit| *limit > 0).ok_or_else(|| format!("invalid limit '{}'", value))?),
+            "offset" => offset = Some(value.parse::<i64>().ok().filter(|offset| *offset >= 0).ok_or_else(|| format!("invalid offset '{}'", value))?),
+            _ => {
+                let (condition_key, condition_value) = parse_filter(key, value)?;
+                node.insert(condition_key, condition_value);
+            }
+        }
+    }
+
+    let method = match http_method {
+        "GET" => "get",
+        "POST" => "post",
+        "PATCH" => "put",
+        "DELETE" => "delete",
+        _ => return Err(format!("method {} is not supported", http_method)),
+    };
+    if method != "get" {
+        // 写操作只能按 id 定位，与 `/{method}.json` 一致
+        if node.keys().any(|key| key.starts_with('@')) || limit.is_some() || offset.is_some() {
+            return Err("select, order, limit and offset are only supported by GET".to_string());
+        }
+        if let Some(key) = node.keys().find(|key| *key != "id" && *key != "id{}") {
+            return Err(format!("filter '{}' is not supported by {}, only id=eq. or id=in.", key.trim_end_matches(['!', '{', '}', '$', '>', '<', '=']), http_method));
+        }
+        match (node.remove("id"), node.contains_key("id{}")) {
+            (None, false) => {}
+            (Some(serde_json::Value::Number(n)), false) if id.is_none() => id = n.as_i64(),
+            (None, true) if id.is_none() && method == "delete" => {}
+            _ => return Err(format!("id filter is not supported by {}", http_method)),
+        }
+    } else if node.contains_key("id{}") && id.is_some() {
+        return Err("id filter is not supported with /{id}".to_string());
+    }
+
+    let table_key = table_key.to_string();
+    let mut body_map = HashMap::new();
+    match (method, id) {
+        ("get", Some(id)) => {
+            node.insert("id".to_string(), serde_json::json!(id));
+            body_map.insert(table_key.clone(), serde_json::Value::Object(node));
+        }
+        ("get", None) => {
+            // 列表查询，offset 需为 limit 的整数倍
+            let count = limit.unwrap_or(crate::db::query_executor::DEFAULT_MAX_COUNT as i64);
+            let offset = offset.unwrap_or_default();
+            if offset % count != 0 {
+                return Err(format!("offset {} is not a multiple of limit {}", offset, count));
+            }
+            namespace.insert("count".to_string(), serde_json::json!(count));
+            namespace.insert("page".to_string(), serde_json::json!(offset / count));
+            namespace.insert(table_key.clone(), serde_json::Value::Object(node));
+            body_map.insert("[]".to_string(), serde_json::Value::Object(namespace));
+        }
+        ("post", Some(_)) => return Err("POST does not accept an id".to_string()),
+        ("post", None) => {
+            let data = body.filter(serde_json::Value::is_object).ok_or_else(|| "request body must be a JSON object".to_string())?;
+            body_map.insert(table_key.clone(), data);
+        }
+        ("put", Some(id)) => {
+            let mut data = body.and_then(|body| body.as_object().cloned()).ok_or_else(|| "request body must be a JSON object".to_string())?;
+            data.insert("id".to_string(), serde_json::json!(id));
+            body_map.insert(table_key.clone(), serde_json::Value::Object(data));
+        }
+        ("delete", Some(id)) => {
+            body_map.insert(table_key.clone(), serde_json::json!({ "id": id }));
+        }
+        ("delete", None) if node.contains_key("id{}") => {
+            body_map.insert(table_key.clone(), serde_json::Value::Object(node));
+        }
+        _ => return Err(format!("{} requires /{{id}} or an id filter", http_method)),
+    }
+    Ok(ResourceRequest { method, table_key, id, body_map })
+}
+
+/// 过滤条件转换为 APIJSON 条件: `price=gt.10` -> `"price>": "10"`，`not.` 取反
+fn parse_filter(column: &str, value: &str) -> Result<(String, serde_json::Value), String> {
+    let malformed = || format!("malformed filter '{}={}'", column, value);
+    let (negate, expr) = match value.strip_prefix("not.") {
+        Some(expr) => (true, expr),
+        None => (false, value),
+    };
+    let (op, operand) = expr.split_once('.').ok_or_else(malformed)?;
+    let (suffix, operand) = match op {
+        "eq" => ("", serde_json::json!(operand)),
+        "neq" => ("!", serde_json::json!(operand)),
+        "gt" => (">", serde_json::json!(operand)),
+        "gte" => (">=", serde_json::json!(operand)),
+        "lt" => ("<", serde_json::json!(operand)),
+        "lte" => ("<=", serde_json::json!(operand)),
+        // PostgREST 以 * 作为通配符
+        "like" | "ilike" => ("$", serde_json::json!(operand.replace('*', "%"))),
+        "in" => {
+            let items = operand.strip_prefix('(').and_then(|items| items.strip_suffix(')')).ok_or_else(malformed)?;
+            let items: Vec<&str> = items.split(',').map(|item| item.trim().trim_matches('"')).filter(|item| !item.is_empty()).collect();
+            ("{}", serde_json::json!(items))
+        }
+        "is" => match operand {
+            "null" => ("", serde_json::Value::Null),
+            "true" => ("", serde_json::json!(1)),
+            "false" => ("", serde_json::json!(0)),
+            _ => return Err(malformed()),
+        },
+        _ => return Err(format!("unknown operator '{}' in filter '{}={}'", op, column, value)),
+    };
+    // id 按数字传入，与写操作的要求一致
+    let operand = match (column, operand) {
+        ("id", serde_json::Value::String(id)) => serde_json::json!(id.parse::<i64>().map_err(|_| malformed())?),
+        ("id", serde_json::Value::Array(ids)) => serde_json::Value::Array(ids.iter()
+            .map(|id| id.as_str().and_then(|id| id.parse::<i64>().ok()).map(serde_json::Value::from).ok_or_else(malformed))
+            .collect::<Result<_, _>>()?),
+        (_, operand) => operand,
+    };
+    // neq 取反后即为 eq
+    let key = match (negate, suffix) {
+        (true, "!") => column.to_string(),
+        (true, _) => format!("{}!{}", column, suffix),
+        (false, _) => format!("{}{}", column, suffix),
+    };
+    Ok((key, operand))
+}
+
+/// 排序转换为 `@order`: `id.desc,name` -> `id desc,name asc`
+fn parse_order(order: &str) -> Result<String, String> {
+    order.split(',').map(str::trim).filter(|item| !item.is_empty()).map(|item| {
+        match item.split_once('.') {
+            Some((column, direction @ ("asc" | "desc"))) => Ok(format!("{} {}", column, direction)),
+            Some(_) => Err(format!("malformed order '{}'", item)),
+            None => Ok(format!("{} asc", item)),
+        }
+    }).collect::<Result<Vec<_>, _>>().map(|items| items.join(","))
+}
+
+/// APIJSON 的结果转换为 PostgREST 风格的结果: 列表为行数组，单条为行对象，写操作为 id、插入的行或删除行数
+pub fn to_resource_response(request: &ResourceRequest, rpc_result: RpcResult<HashMap<String, serde_json::Value>>) -> RpcResult<serde_json::Value> {
+    let not_found = || RpcResult { code: StatusCode::NOT_FOUND, msg: Some(format!("{} {} not found", request.table_key, request.id.unwrap_or_default())), payload: None };
+    let mut payload = rpc_result.payload.unwrap_or_default();
+    if request.method == "get" && rpc_result.code != StatusCode::OK {
+        return RpcResult { code: rpc_result.code, msg: rpc_result.msg, payload: None };
+    }
+    match (request.method, payload.remove(&request.table_key)) {
+        // 写操作失败时为错误信息
+        (_, Some(serde_json::Value::String(err))) => RpcResult { code: StatusCode::BAD_REQUEST, msg: Some(err), payload: None },
+        ("get", None) if request.id.is_none() => {
+            let rows: Vec<serde_json::Value> = match payload.remove("[]") {
+                Some(serde_json::Value::Array(items)) => items.into_iter()
+                    .filter_map(|mut item| item.get_mut(&request.table_key).map(serde_json::Value::take))
+                    .collect(),
+                _ => vec![],
+            };
+            RpcResult { code: StatusCode::OK, msg: None, payload: Some(serde_json::Value::Array(rows)) }
+        }
+        ("get", Some(row)) if row.as_object().is_some_and(|row| !row.is_empty()) => RpcResult { code: StatusCode::OK, msg: None, payload: Some(row) },
+        // `"@return": "*"` 时为插入的整行
+        ("post", Some(row)) if rpc_result.code == StatusCode::OK && row.is_object() => RpcResult { code: StatusCode::CREATED, msg: None, payload: Some(row) },
+        ("post", Some(id)) if rpc_result.code == StatusCode::OK => RpcResult { code: StatusCode::CREATED, msg: None, payload: Some(serde_json::json!({ "id": id })) },
+        // 更新时 -1 表示没有被更新的行
+        ("put", Some(id)) if rpc_result.code == StatusCode::OK => match id.as_i64() {
+            Some(-1) => not_found(),
+            _ => RpcResult { code: StatusCode::OK, msg: None, payload: Some(serde_json::json!({ "id": id })) },
+        },
+        ("delete", Some(deleted)) if rpc_result.code == StatusCode::OK => match deleted.as_u64() {
+            Some(0) if request.id.is_some() => not_found(),
+            _ => RpcResult { code: StatusCode::OK, msg: None, payload: Some(serde_json::json!({ "deleted": deleted })) },
+        },
+        ("get", _) => not_found(),
+        _ => RpcResult { code: rpc_result.code, msg: rpc_result.msg, payload: None },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use http::StatusCode;
+    use common::rpc::RpcResult;
+    use crate::handler::resource::{to_apijson_request, to_resource_response};
+
+    fn query(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_to_apijson_request() {
+        let request = to_apijson_request("GET", "ecommerce.product", None, &query(&[
+            ("status", "eq.paid"), ("price", "gt.10"), ("name", "not.like.*phone*"), ("id", "in.(1,2)"),
+            ("deleted_at", "is.null"), ("order", "id.desc,name"), ("limit", "20"), ("offset", "40"), ("select", "id,name"),
+        ]), None).unwrap();
+        assert_eq!(request.method, "get");
+        assert_eq!(serde_json::json!(request.body_map), serde_json::json!({
+            "[]": {
+                "count": 20,
+                "page": 2,
+                "ecommerce.product": {
+                    "status": "paid", "price>": "10", "name!$": "%phone%", "id{}": [1, 2], "deleted_at": null,
+                    "@order": "id desc,name asc", "@column": "id,name"
+                }
+            }
+        }));
+
+        let request = to_apijson_request("PATCH", "ecommerce.product", Some("7"), &[], Some(serde_json::json!({ "price": 9 }))).unwrap();
+        assert_eq!((request.method, request.id), ("put", Some(7)));
+        assert_eq!(request.body_map["ecommerce.product"], serde_json::json!({ "id": 7, "price": 9 }));
+        let request = to_apijson_request("DELETE", "ecommerce.product", None, &query(&[("id", "in.(3,4)")]), None).unwrap();
+        assert_eq!(request.body_map["ecommerce.product"], serde_json::json!({ "id{}": [3, 4] }));
+
+        assert!(to_apijson_request("GET", "ecommerce.product", Some("x"), &[], None).is_err());
+        assert!(to_apijson_request("GET", "ecommerce.product", None, &query(&[("price", "between.1")]), None).is_err());
+        assert!(to_apijson_request("GET", "ecommerce.product", None, &query(&[("limit", "20"), ("offset", "5")]), None).is_err());
+        assert!(to_apijson_request("DELETE", "ecommerce.product", None, &query(&[("status", "eq.paid")]), None).is_err());
+        assert!(to_apijson_request("DELETE", "ecommerce.product", None, &[], None).is_err());
+        assert!(to_apijson_request("PATCH", "ecommerce.product", None, &query(&[("id", "in.(3,4)")]), Some(serde_json::json!({}))).is_err());
+    }
+
+    #[test]
+    fn test_to_resource_response() {
+        let rpc_result = |code: StatusCode, payload: serde_json::Value| RpcResult::<HashMap<String, serde_json::Value>> { code, msg: None, payload: serde_json::from_value(payload).ok() };
+
+        let request = to_apijson_request("GET", "ecommerce.product", None, &[], None).unwrap();
+        let response = to_resource_response(&request, rpc_result(StatusCode::OK, serde_json::json!({ "[]": [{ "ecommerce.product": { "id": 1 } }] })));
+        assert_eq!(response.payload.unwrap(), serde_json::json!([{ "id": 1 }]));
+
+        let request = to_apijson_request("GET", "ecommerce.product", Some("1"), &[], None).unwrap();
+        assert_eq!(to_resource_response(&request, rpc_result(StatusCode::OK, serde_json::json!({}))).code, StatusCode::NOT_FOUND);
+
+        let request = to_apijson_request("POST", "ecommerce.product", None, &[], Some(serde_json::json!({ "name": "a" }))).unwrap();
+        let response = to_resource_response(&request, rpc_result(StatusCode::OK, serde_json::json!({ "ecommerce.product": 9 })));
+        assert_eq!((response.code, response.payload.unwrap()), (StatusCode::CREATED, serde_json::json!({ "id": 9 })));
+        let response = to_resource_response(&request, rpc_result(StatusCode::OK, serde_json::json!({ "ecommerce.product": { "id": 9, "name": "a" } })));
+        assert_eq!((response.code, response.payload.unwrap()), (StatusCode::CREATED, serde_json::json!({ "id": 9, "name": "a" })));
+        let response = to_resource_response(&request, rpc_result(StatusCode::BAD_REQUEST, serde_json::json!({ "ecommerce.product": "table ecommerce.product is read-only" })));
+        assert_eq!(response.msg.unwrap(), "table ecommerce.product is read-only");
+    }
+}
@@ -1,4 +1,12 @@
 pub mod db;
 pub mod handler;
 pub mod verify;
+pub mod policy;
+pub mod exposure;
+pub mod access;
+pub mod limits;
+pub mod template;
+pub mod graphql;
+pub mod openapi;
+pub mod export;
 pub mod utils;
\ No newline at end of file
@@ -0,0 +1,31 @@
+use std::sync::{LazyLock, RwLock};
+use http::StatusCode;
+use sqlx::mysql::MySqlDatabaseError;
+use common::yaml::Limits;
+
+/// MySQL 错误码: 语句执行超过 MAX_EXECUTION_TIME 被中断
+const ER_QUERY_TIMEOUT: u16 = 3024;
+
+/// 查询开销限制
+static QUERY_LIMITS: LazyLock<RwLock<Limits>> = LazyLock::new(|| RwLock::new(Limits::default()));
+
+/// 加载 `restful.limits` 配置
+pub fn init_limits(limits: &Limits) {
+    *QUERY_LIMITS.write().unwrap() = limits.clone();
+}
+
+pub fn query_limits() -> Limits {
+    QUERY_LIMITS.read().unwrap().clone()
+}
+
+/// 查询出错时的状态码和错误信息，语句超过 MAX_EXECUTION_TIME 视为请求的开销过大
+pub fn query_error(node_path: &str, err: sqlx::Error) -> (StatusCode, String) {
+    let is_timeout = err.as_database_error()
+        .and_then(|err| err.try_downcast_ref::<MySqlDatabaseError>())
+        .is_some_and(|err| err.number() == ER_QUERY_TIMEOUT);
+    if is_timeout {
+        (StatusCode::BAD_REQUEST, format!("{}: statement exceeded MAX_EXECUTION_TIME", node_path))
+    } else {
+        (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+    }
+}
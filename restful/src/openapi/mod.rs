@@ -0,0 +1,315 @@
+use std::sync::{LazyLock, RwLock};
+use serde_json::{json, Map, Value};
+use common::yaml::{Access, DecimalFormat, Decode};
+use database::{ColumnMeta, TableMeta};
+use database::core::{get_table_list, meta_version};
+use crate::exposure::{is_table_visible, table_access, visible_table};
+
+/// 已生成的 OpenAPI 文档及对应的元数据版本
+static OPENAPI_CACHE: LazyLock<RwLock<Option<(u64, Value)>>> = LazyLock::new(|| RwLock::new(None));
+
+/// 由表元数据生成的 OpenAPI 3.1 文档，元数据重新加载后重新生成
+pub fn openapi_document(decode: &Decode) -> Value {
+    let version = meta_version();
+    if let Some((_, document)) = OPENAPI_CACHE.read().unwrap().as_ref().filter(|(cached_version, _)| *cached_version == version) {
+        return document.clone();
+    }
+    let tables: Vec<TableMeta> = get_table_list().into_iter()
+        .filter(|table| is_table_visible(&table.schema, &table.name))
+        .filter_map(|table| visible_table(&format!("{}.{}", table.schema, table.name)).ok())
+        .collect();
+    let document = build_document(&tables, decode);
+    *OPENAPI_CACHE.write().unwrap() = Some((version, document.clone()));
+    document
+}
+
+/// 字段类型对应的 JSON Schema，可为空的字段类型中加入 null
+fn column_schema(column: &ColumnMeta, decode: &Decode) -> Value {
+    let type_name = column.type_name.to_lowercase();
+    let unsigned = type_name.contains("unsigned");
+    let mut schema = if type_name.starts_with("tinyint(1)") && decode.tinyint1_as_bool {
+        json!({ "type": "boolean" })
+    } else if type_name.starts_with("bigint") {
+        json!({ "type": "integer", "format": "int64" })
+    } else if ["tinyint", "smallint", "mediumint", "int", "year", "bit"].iter().any(|prefix| type_name.starts_with(prefix)) {
+        json!({ "type": "integer", "format": if unsigned { "int64" } else { "int32" } })
+    } else if type_name.starts_with("decimal") || type_name.starts_with("numeric") {
+        match decode.decimal {
+            DecimalFormat::String => json!({ "type": "string", "format": "decimal" }),
+            DecimalFormat::Number => json!({ "type": "number" }),
+        }
+    } else if type_name.starts_with("float") || type_name.starts_with("real") {
+        json!({ "type": "number", "format": "float" })
+    } else if type_name.starts_with("double") {
+        json!({ "type": "number", "format": "double" })
+    } else if type_name.starts_with("datetime") || type_name.starts_with("timestamp") {
+        json!({ "type": "string", "format": "date-time" })
+    } else if type_name.starts_with("date") {
+        json!({ "type": "string", "format": "date" })
+    } else if type_name.starts_with("json") {
+        // 任意 JSON 值
+        json!({})
+    } else if type_name.starts_with("enum(") && type_name.ends_with(')') {
+        let items: Vec<&str> = column.type_name[5..column.type_name.len() - 1].split(',').map(|item| item.trim().trim_matches('\'')).collect();
+        json!({ "type": "string", "enum": items })
+    } else {
+        let mut schema = json!({ "type": "string" });
+        if let Some(length) = type_name.strip_prefix("varchar(").or_else(|| type_name.strip_prefix("char("))
+            .and_then(|length| length.strip_suffix(')'))
+            .and_then(|length| length.parse::<u64>().ok()) {
+            schema["maxLength"] = json!(length);
+        }
+        schema
+    };
+    if unsigned && schema["type"] == "integer" {
+        schema["minimum"] = json!(0);
+    }
+    if let Some(ty) = schema.get("type").cloned().filter(|_| column.null.as_deref() == Some("YES")) {
+        schema["type"] = json!([ty, "null"]);
+    }
+    if let Some(comment) = column.comment.as_ref().filter(|comment| !comment.is_empty()) {
+        schema["description"] = json!(comment);
+    }
+    schema
+}
+
+/// 按名称排序的字段
+fn sorted_columns(table: &TableMeta) -> Vec<&ColumnMeta> {
+    let mut columns: Vec<&ColumnMeta> = table.columns.values().collect();
+    columns.sort_by(|a, b| a.field.cmp(&b.field));
+    columns
+}
+
+fn component_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+/// 单个表的 schema: 行、查询条件、新增、更新
+fn table_schemas(table_key: &str, table: &TableMeta, decode: &Decode, schemas: &mut Map<String, Value>) {
+    let columns = sorted_columns(table);
+    let properties: Map<String, Value> = columns.iter().map(|column| (column.field.clone(), column_schema(column, decode))).collect();
+
+    let mut row = json!({ "type": "object", "properties": properties });
+    if let Some(comment) = table.comment.as_ref().filter(|comment| !comment.is_empty()) {
+        row["description"] = json!(comment);
+    }
+    schemas.insert(table_key.to_string(), row);
+
+    // 查询条件: 字段值为等值条件，其余条件见 README
+    let mut query = properties.clone();
+    for (key, description) in [
+        ("@column", "返回的字段，如 `id,name,count(id):cnt`"),
+        ("@order", "排序，如 `date-,id+` 或 `date desc`"),
+        ("@group", "分组字段"),
+        ("@having", "分组条件，多个以 ; 分隔"),
+    ] {
+        query.insert(key.to_string(), json!({ "type": "string", "description": description }));
+    }
+    schemas.insert(format!("{}.query", table_key), json!({
+        "type": "object",
+        "properties": query,
+        "additionalProperties": true,
+        "description": "查询条件，键可带比较符后缀，如 `price>=`、`name$`、`id{}`",
+    }));
+
+    if table_access(&table.schema, &table.name) == Access::ReadOnly {
+        return;
+    }
+    // 新增时 id 自动生成
+    let mut insert = properties.clone();
+    insert.remove("id");
+    let required: Vec<&String> = columns.iter()
+        .filter(|column| column.field != "id" && column.null.as_deref() == Some("NO") && column.default.is_none()
+            && !column.extra.as_deref().unwrap_or_default().contains("auto_increment"))
+        .map(|column| &column.field)
+        .collect();
+    schemas.insert(format!("{}.insert", table_key), json!({ "type": "object", "properties": insert, "required": required, "additionalProperties": false }));
+    schemas.insert(format!("{}.update", table_key), json!({ "type": "object", "properties": properties, "required": ["id"], "additionalProperties": false }));
+}
+
+/// REST 接口: 请求体的键为 `schema.table`，值为该表的条件或数据
+fn rest_path(summary: &str, request_properties: Map<String, Value>, response_properties: Map<String, Value>) -> Value {
+    json!({
+        "post": {
+            "tags": ["rest"],
+            "summary": summary,
+            "security": [{}, { "bearerAuth": [] }, { "apiKey": [] }],
+            "requestBody": {
+                "required": true,
+                "content": { "application/json": { "schema": { "type": "object", "properties": request_properties } } },
+            },
+            "responses": {
+                "200": {
+                    "description": "以表名为键的结果",
+                    "content": { "application/json": { "schema": { "type": "object", "properties": response_properties } } },
+                },
+                "400": component_ref_response("Error"),
+                "401": component_ref_response("Error"),
+                "403": component_ref_response("Error"),
+            },
+        }
+    })
+}
+
+fn component_ref_response(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/responses/{}", name) })
+}
+
+/// PostgREST 风格的资源接口 `/{schema}/{table}`、`/{schema}/{table}/{id}`
+fn resource_paths(table: &TableMeta, table_key: &str, writable: bool) -> Map<String, Value> {
+    let security = json!([{}, { "bearerAuth": [] }, { "apiKey": [] }]);
+    let query_param = |name: &str, description: &str| json!({ "name": name, "in": "query", "schema": { "type": "string" }, "description": description });
+    let id_param = json!({ "name": "id", "in": "path", "required": true, "schema": { "type": "integer", "format": "int64" } });
+    let id_response = |description: &str| json!({ "description": description, "content": { "application/json": { "schema": { "type": "object", "properties": { "id": { "type": "integer", "format": "int64" } } } } } });
+    let deleted_response = json!({ "description": "删除的行数", "content": { "application/json": { "schema": { "type": "object", "properties": { "deleted": { "type": "integer" } } } } } });
+    let row_response = json!({ "description": "行数据", "content": { "application/json": { "schema": component_ref(table_key) } } });
+    let body = |schema: &str| json!({ "required": true, "content": { "application/json": { "schema": component_ref(&format!("{}.{}", table_key, schema)) } } });
+    let errors = |mut responses: Value| {
+        for code in ["400", "401", "403"] {
+            responses[code] = component_ref_response("Error");
+        }
+        responses
+    };
+
+    let mut collection = json!({
+        "get": {
+            "tags": [table_key], "summary": "列表查询", "security": security,
+            "parameters": [
+                query_param("select", "返回的字段，如 `id,name`"),
+                query_param("order", "排序，如 `id.desc,name.asc`"),
+                { "name": "limit", "in": "query", "schema": { "type": "integer" } },
+                { "name": "offset", "in": "query", "schema": { "type": "integer" }, "description": "需为 limit 的整数倍" },
+            ],
+            "responses": errors(json!({ "200": { "description": "行数据列表", "content": { "application/json": { "schema": { "type": "array", "items": component_ref(table_key) } } } } })),
+        }
+    });
+    // 字段过滤: `status=eq.paid`、`price=gt.10`、`id=in.(1,2)`、`deleted_at=is.null`
+    if let Some(parameters) = collection["get"]["parameters"].as_array_mut() {
+        parameters.extend(sorted_columns(table).iter().map(|column| query_param(&column.field, "过滤条件，如 `eq.1`、`gt.10`、`in.(1,2)`、`like.*a*`、`is.null`，`not.` 取反")));
+    }
+    let mut item = json!({
+        "get": { "tags": [table_key], "summary": "按 id 查询", "security": security, "parameters": [id_param], "responses": errors(json!({ "200": row_response, "404": component_ref_response("Error") })) }
+    });
+    if writable {
+        collection["post"] = json!({ "tags": [table_key], "summary": "新增", "security": security, "requestBody": body("insert"), "responses": errors(json!({ "201": id_response("新增的 id") })) });
+        collection["delete"] = json!({
+            "tags": [table_key], "summary": "按 id 删除", "security": security,
+            "parameters": [{ "name": "id", "in": "query", "required": true, "schema": { "type": "string" }, "description": "`eq.1` 或 `in.(1,2)`" }],
+            "responses": errors(json!({ "200": deleted_response })),
+        });
+        item["patch"] = json!({ "tags": [table_key], "summary": "按 id 更新", "security": security, "parameters": [id_param], "requestBody": body("update"), "responses": errors(json!({ "200": id_response("更新的 id"), "404": component_ref_response("Error") })) });
+        item["delete"] = json!({ "tags": [table_key], "summary": "按 id 删除", "security": security, "parameters": [id_param], "responses": errors(json!({ "200": deleted_response, "404": component_ref_response("Error") })) });
+    }
+    let path = format!("/api/v1/rest/{}/{}", table.schema, table.name);
+    Map::from_iter([(format!("{}/{{id}}", path), item), (path, collection)])
+}
+
+fn build_document(tables: &[TableMeta], decode: &Decode) -> Value {
+    let mut schemas = Map::new();
+    let (mut get_req, mut get_resp) = (Map::new(), Map::new());
+    let (mut head_req, mut head_resp) = (Map::new(), Map::new());
+    let (mut post_req, mut put_req, mut delete_req) = (Map::new(), Map::new(), Map::new());
+    let mut write_resp = Map::new();
+    let mut paths = Map::new();
+
+    for table in tables {
+        let table_key = format!("{}.{}", table.schema, table.name);
+        table_schemas(&table_key, table, decode, &mut schemas);
+
+        get_req.insert(table_key.clone(), component_ref(&format!("{}.query", table_key)));
+        get_resp.insert(table_key.clone(), component_ref(&table_key));
+        head_req.insert(table_key.clone(), component_ref(&format!("{}.query", table_key)));
+        head_resp.insert(table_key.clone(), json!({ "type": "integer", "description": "记录数" }));
+        if schemas.contains_key(&format!("{}.insert", table_key)) {
+            post_req.insert(table_key.clone(), component_ref(&format!("{}.insert", table_key)));
+            put_req.insert(table_key.clone(), component_ref(&format!("{}.update", table_key)));
+            delete_req.insert(table_key.clone(), json!({ "type": "object", "properties": { "id": { "type": "integer", "format": "int64" } }, "required": ["id"] }));
+            write_resp.insert(table_key.clone(), json!({ "type": ["integer", "string"], "description": "新增、更新时为 id，删除时为影响行数，失败时为错误信息" }));
+        }
+        let writable = schemas.contains_key(&format!("{}.insert", table_key));
+        paths.extend(resource_paths(table, &table_key, writable));
+    }
+    // 列表查询 `[]`
+    get_req.insert("[]".to_string(), json!({
+        "type": "object",
+        "description": "列表查询，`count`、`page` 分页，其下的表节点为每一行的查询条件",
+        "properties": { "count": { "type": "integer" }, "page": { "type": "integer" }, "query": { "type": "integer", "enum": [0, 1, 2] } },
+        "additionalProperties": true,
+    }));
+    get_resp.insert("[]".to_string(), json!({ "type": "array", "items": { "type": "object" } }));
+
+    paths.insert("/api/v1/rest/get.json".to_string(), rest_path("查询", get_req, get_resp));
+    paths.insert("/api/v1/rest/head.json".to_string(), rest_path("计数", head_req, head_resp));
+    paths.insert("/api/v1/rest/post.json".to_string(), rest_path("新增", post_req, write_resp.clone()));
+    paths.insert("/api/v1/rest/put.json".to_string(), rest_path("按 id 更新", put_req, write_resp.clone()));
+    paths.insert("/api/v1/rest/delete.json".to_string(), rest_path("按 id 删除", delete_req, write_resp));
+    paths.insert("/api/v1/rest/{schema}/tables.json".to_string(), json!({
+        "get": {
+            "tags": ["meta"],
+            "summary": "库中的表及注释",
+            "parameters": [{ "name": "schema", "in": "path", "required": true, "schema": { "type": "string" } }],
+            "responses": { "200": { "description": "表名 -> 注释", "content": { "application/json": { "schema": { "type": "object", "additionalProperties": { "type": "string" } } } } } },
+        }
+    }));
+    paths.insert("/api/v1/rest/{schema}/{table}.json".to_string(), json!({
+        "get": {
+            "tags": ["meta"],
+            "summary": "表的元数据",
+            "parameters": [
+                { "name": "schema", "in": "path", "required": true, "schema": { "type": "string" } },
+                { "name": "table", "in": "path", "required": true, "schema": { "type": "string" } },
+            ],
+            "responses": { "200": { "description": "表元数据", "content": { "application/json": { "schema": { "type": "object" } } } }, "400": component_ref_response("Error") },
+        }
+    }));
+
+    json!({
+        "openapi": "3.1.0",
+        "info": { "title": "IDEA-BASE API", "version": env!("CARGO_PKG_VERSION") },
+        "paths": paths,
+        "components": {
+            "schemas": schemas,
+            "responses": {
+                "Error": {
+                    "description": "错误信息",
+                    "content": { "application/json": { "schema": { "type": "object", "properties": { "err_msg": { "type": ["string", "null"] } } } } },
+                }
+            },
+            "securitySchemes": {
+                "bearerAuth": { "type": "apiKey", "in": "header", "name": "Authentication", "description": "`Bearer {token}`，token 由 /api/v1/auth/logon.json 获取" },
+                "apiKey": { "type": "apiKey", "in": "header", "name": "X-API-Key" },
+            },
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use common::yaml::{DecimalFormat, Decode};
+    use crate::openapi::build_document;
+    use crate::verify::tests::{lock_registries, test_table};
+
+    #[test]
+    fn test_build_document() {
+        let _lock = lock_registries();
+        let mut table = test_table();
+        table.columns.get_mut("status").unwrap().type_name = "enum('on','off')".to_string();
+        table.columns.get_mut("name").unwrap().null = Some("NO".to_string());
+        let decode = Decode { decimal: DecimalFormat::Number, ..Default::default() };
+        let document = build_document(&[table], &decode);
+
+        let row = &document["components"]["schemas"]["ecommerce.product"]["properties"];
+        assert_eq!(row["id"]["type"], serde_json::json!(["integer", "null"]));
+        assert_eq!(row["name"]["type"], "string");
+        assert_eq!(row["name"]["maxLength"], 64);
+        assert_eq!(row["price"]["type"], serde_json::json!(["number", "null"]));
+        assert_eq!(row["status"]["enum"], serde_json::json!(["on", "off"]));
+        assert_eq!(row["deleted_at"]["format"], "date-time");
+        assert_eq!(document["components"]["schemas"]["ecommerce.product.insert"]["required"], serde_json::json!(["name"]));
+        assert_eq!(
+            document["paths"]["/api/v1/rest/get.json"]["post"]["requestBody"]["content"]["application/json"]["schema"]["properties"]["ecommerce.product"]["$ref"],
+            "#/components/schemas/ecommerce.product.query"
+        );
+        assert!(document["paths"]["/api/v1/rest/ecommerce/product/{id}"]["patch"].is_object());
+    }
+}
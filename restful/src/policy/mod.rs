@@ -0,0 +1,268 @@
+use std::sync::{LazyLock, RwLock};
+use fnv::FnvHashMap;
+use common::yaml::Rls;
+use database::TableMeta;
+use crate::db::query_executor::{parse_range_value, RANGE_COMPARES};
+use crate::verify::{quote_ident, resolve_column, resolve_table};
+
+/// 已编译的行级安全策略
+static POLICY_REGISTRY: LazyLock<RwLock<PolicyRegistry>> = LazyLock::new(|| RwLock::new(PolicyRegistry::default()));
+
+/// 请求者身份，取自 `JwtToken`，匿名请求时均为 None
+#[derive(Debug, Clone, Default)]
+pub struct Auth {
+    pub sub: Option<i64>,
+    pub role: Option<String>,
+}
+
+/// 策略生效的操作
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PolicyAction {
+    Select,
+    Insert,
+    Update,
+    Delete,
+}
+
+impl PolicyAction {
+    fn parse(action: &str) -> Result<Self, String> {
+        match action.to_lowercase().as_str() {
+            "select" => Ok(PolicyAction::Select),
+            "insert" => Ok(PolicyAction::Insert),
+            "update" => Ok(PolicyAction::Update),
+            "delete" => Ok(PolicyAction::Delete),
+            _ => Err(format!("unknown policy action '{}'", action)),
+        }
+    }
+}
+
+/// 策略谓词右侧的值
+#[derive(Debug, Clone, PartialEq)]
+enum PolicyValue {
+    // $auth.sub
+    AuthSub,
+    // $auth.role
+    AuthRole,
+    Literal(serde_json::Value),
+}
+
+/// 单个谓词，如 `user_id = $auth.sub`
+#[derive(Debug, Clone, PartialEq)]
+struct PolicyCondition {
+    column: String,
+    compare: &'static str,
+    value: PolicyValue,
+}
+
+#[derive(Debug, Clone)]
+struct TablePolicy {
+    check: String,
+    actions: Vec<PolicyAction>,
+    conditions: Vec<PolicyCondition>,
+}
+
+#[derive(Debug, Default)]
+struct PolicyRegistry {
+    bypass_roles: Vec<String>,
+    // schema.table -> 策略列表
+    tables: FnvHashMap<String, Vec<TablePolicy>>,
+}
+
+/// 策略生成的 WHERE 条件及绑定参数
+#[derive(Debug, Default, PartialEq)]
+pub struct PolicyFilter {
+    pub clauses: Vec<String>,
+    pub params: Vec<serde_json::Value>,
+}
+
+/// 加载 `restful.rls` 配置，表和字段需在元数据中存在，启动时及元数据重新加载后调用
+pub fn init_policies(rls: &Rls) -> Result<(), String> {
+    let mut registry = PolicyRegistry { bypass_roles: rls.bypass_roles.clone(), tables: FnvHashMap::default() };
+    for policy in &rls.policies {
+        let table = resolve_table(&policy.table)?;
+        let actions = match policy.actions.is_empty() {
+            true => vec![PolicyAction::Select, PolicyAction::Insert, PolicyAction::Update, PolicyAction::Delete],
+            false => policy.actions.iter().map(|action| PolicyAction::parse(action)).collect::<Result<_, _>>()?,
+        };
+        let conditions = parse_check(&table, &policy.check).map_err(|err| format!("{}: {}", policy.table, err))?;
+        let table_policy = TablePolicy { check: policy.check.clone(), actions, conditions };
+        registry.tables.entry(format!("{}.{}", table.schema, table.name)).or_default().push(table_policy);
+    }
+    *POLICY_REGISTRY.write().unwrap() = registry;
+    Ok(())
+}
+
+/// 解析策略谓词，多个条件以 and 连接: `user_id = $auth.sub and status != 'deleted'`
+fn parse_check(table: &TableMeta, check: &str) -> Result<Vec<PolicyCondition>, String> {
+    let mut conditions = Vec::new();
+    for item in split_and(check).into_iter().map(str::trim) {
+        let malformed = || format!("malformed policy '{}'", item);
+        let index = item.find(['>', '<', '=', '!']).ok_or_else(malformed)?;
+        let (column, rest) = (item[..index].trim(), &item[index..]);
+        let (compare, raw_value) = RANGE_COMPARES.iter()
+            .find_map(|compare| rest.strip_prefix(compare).map(|value| (*compare, value.trim())))
+            .ok_or_else(malformed)?;
+        if column.is_empty() || raw_value.is_empty() {
+            return Err(malformed());
+        }
+        let value = match raw_value {
+            "$auth.sub" => PolicyValue::AuthSub,
+            "$auth.role" => PolicyValue::AuthRole,
+            _ if raw_value.starts_with('$') => return Err(format!("unknown claim '{}'", raw_value)),
+            _ => PolicyValue::Literal(parse_range_value(raw_value)),
+        };
+        conditions.push(PolicyCondition { column: resolve_column(table, column)?, compare, value });
+    }
+    Ok(conditions)
+}
+
+/// 按 ` and ` 切分谓词，不区分大小写；逐字节比较，避免大小写转换改变字节位置
+fn split_and(check: &str) -> Vec<&str> {
+    const AND: &[u8] = b" and ";
+    let bytes = check.as_bytes();
+    let (mut items, mut start, mut index) = (Vec::new(), 0, 0);
+    while index + AND.len() <= bytes.len() {
+        if bytes[index..index + AND.len()].eq_ignore_ascii_case(AND) {
+            items.push(&check[start..index]);
+            index += AND.len();
+            start = index;
+        } else {
+            index += 1;
+        }
+    }
+    items.push(&check[start..]);
+    items
+}
+
+impl PolicyCondition {
+    /// 取出谓词右侧的值，请求中缺少对应的身份信息时返回 None
+    fn resolve(&self, auth: &Auth) -> Option<serde_json::Value> {
+        match &self.value {
+            PolicyValue::AuthSub => auth.sub.map(serde_json::Value::from),
+            PolicyValue::AuthRole => auth.role.clone().map(serde_json::Value::String),
+            PolicyValue::Literal(value) => Some(value.clone()),
+        }
+    }
+
+    /// 判断写入的值是否满足谓词
+    fn matches(&self, value: &serde_json::Value, expected: &serde_json::Value) -> bool {
+        let ordering = match (to_f64(value), to_f64(expected)) {
+            (Some(a), Some(b)) => a.partial_cmp(&b),
+            _ => match (value, expected) {
+                (serde_json::Value::String(a), serde_json::Value::String(b)) => Some(a.cmp(b)),
+                _ => None,
+            },
+        };
+        let Some(ordering) = ordering else { return false };
+        match self.compare {
+            "=" => ordering.is_eq(),
+            "!=" => ordering.is_ne(),
+            ">" => ordering.is_gt(),
+            ">=" => ordering.is_ge(),
+            "<" => ordering.is_lt(),
+            _ => ordering.is_le(),
+        }
+    }
+}
+
+fn to_f64(value: &serde_json::Value) -> Option<f64> {
+    match value {
+        serde_json::Value::Number(n) => n.as_f64(),
+        serde_json::Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+/// 对请求者生效的谓词，不受限的角色返回空
+fn table_conditions(table: &TableMeta, action: PolicyAction, auth: &Auth) -> Vec<(String, PolicyCondition)> {
+    let registry = POLICY_REGISTRY.read().unwrap();
+    if auth.role.as_ref().is_some_and(|role| registry.bypass_roles.contains(role)) {
+        return vec![];
+    }
+    registry.tables.get(&format!("{}.{}", table.schema, table.name))
+        .map(|policies| policies.iter()
+            .filter(|policy| policy.actions.contains(&action))
+            .flat_map(|policy| policy.conditions.iter().map(|condition| (policy.check.clone(), condition.clone())))
+            .collect())
+        .unwrap_or_default()
+}
+
+/// 生成需要追加到 WHERE 的策略条件，缺少身份信息时条件恒为假
+pub fn policy_filter(table: &TableMeta, action: PolicyAction, auth: &Auth) -> PolicyFilter {
+    let mut filter = PolicyFilter::default();
+    for (_, condition) in table_conditions(table, action, auth) {
+        match condition.resolve(auth) {
+            Some(value) => {
+                filter.clauses.push(format!("{}{}?", quote_ident(&condition.column), condition.compare));
+                filter.params.push(value);
+            }
+            None => filter.clauses.push("1=0".to_string()),
+        }
+    }
+    filter
+}
+
+/// 检查写入的值是否满足策略，`partial` 为 true 时只检查写入了的字段(更新)
+pub fn check_policy(table: &TableMeta, action: PolicyAction, auth: &Auth, kvs: &serde_json::Map<String, serde_json::Value>, partial: bool) -> Result<(), String> {
+    for (check, condition) in table_conditions(table, action, auth) {
+        let value = kvs.iter().find(|(key, _)| key.eq_ignore_ascii_case(&condition.column)).map(|(_, value)| value);
+        let allowed = match (value, condition.resolve(auth)) {
+            (None, _) if partial => true,
+            (Some(value), Some(expected)) => condition.matches(value, &expected),
+            _ => false,
+        };
+        if !allowed {
+            return Err(format!("row level security: {}.{} requires '{}'", table.schema, table.name, check));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use common::yaml::{Policy, Rls};
+    use crate::policy::{check_policy, parse_check, policy_filter, Auth, PolicyAction, PolicyCondition, PolicyValue, PolicyRegistry, TablePolicy, POLICY_REGISTRY};
+    use crate::verify::tests::{lock_registries, test_table};
+
+    #[test]
+    fn test_parse_check() {
+        let table = test_table();
+        let conditions = parse_check(&table, "Category = $auth.role AND like_cnt >= 10").unwrap();
+        assert_eq!(parse_check(&table, "name = 'İ' aNd like_cnt >= 10").unwrap()[1].column, "like_cnt");
+        assert_eq!(conditions, vec![
+            PolicyCondition { column: "category".to_string(), compare: "=", value: PolicyValue::AuthRole },
+            PolicyCondition { column: "like_cnt".to_string(), compare: ">=", value: PolicyValue::Literal(serde_json::json!(10)) },
+        ]);
+        assert!(parse_check(&table, "user_id = $auth.sub").is_err());
+        assert!(parse_check(&table, "id = $auth.email").is_err());
+        assert!(parse_check(&table, "id").is_err());
+        // 表不存在
+        assert!(crate::policy::init_policies(&Rls { bypass_roles: vec![], policies: vec![Policy { table: "ecommerce.nothing".to_string(), check: "id = 1".to_string(), actions: vec![] }] }).is_err());
+    }
+
+    #[test]
+    fn test_policy_filter() {
+        let _lock = lock_registries();
+        let table = test_table();
+        let policy = TablePolicy { check: "id = $auth.sub".to_string(), actions: vec![PolicyAction::Select, PolicyAction::Insert], conditions: parse_check(&table, "id = $auth.sub").unwrap() };
+        let mut registry = PolicyRegistry { bypass_roles: vec!["admin".to_string()], ..Default::default() };
+        registry.tables.insert("ecommerce.product".to_string(), vec![policy]);
+        *POLICY_REGISTRY.write().unwrap() = registry;
+
+        let user = Auth { sub: Some(7), role: Some("user".to_string()) };
+        let filter = policy_filter(&table, PolicyAction::Select, &user);
+        assert_eq!(filter.clauses, vec!["`id`=?"]);
+        assert_eq!(filter.params, vec![serde_json::json!(7)]);
+        assert_eq!(policy_filter(&table, PolicyAction::Select, &Auth::default()).clauses, vec!["1=0"]);
+        assert!(policy_filter(&table, PolicyAction::Delete, &user).clauses.is_empty());
+        assert!(policy_filter(&table, PolicyAction::Select, &Auth { sub: None, role: Some("admin".to_string()) }).clauses.is_empty());
+
+        let kvs = |value: serde_json::Value| value.as_object().unwrap().clone();
+        assert!(check_policy(&table, PolicyAction::Insert, &user, &kvs(serde_json::json!({"id": 7})), false).is_ok());
+        assert!(check_policy(&table, PolicyAction::Insert, &user, &kvs(serde_json::json!({"id": "7"})), false).is_ok());
+        assert!(check_policy(&table, PolicyAction::Insert, &user, &kvs(serde_json::json!({"id": 8})), false).is_err());
+        assert!(check_policy(&table, PolicyAction::Insert, &user, &kvs(serde_json::json!({"name": "a"})), false).is_err());
+        assert!(check_policy(&table, PolicyAction::Insert, &user, &kvs(serde_json::json!({"name": "a"})), true).is_ok());
+        *POLICY_REGISTRY.write().unwrap() = PolicyRegistry::default();
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, RwLock};
+use http::StatusCode;
+use common::yaml::{RequestTemplate, Templates};
+use crate::policy::Auth;
+use crate::verify::split_table_key;
+
+/// 必须包含的字段
+const MUST: &str = "MUST";
+/// 禁止的字段，`!` 表示 MUST 之外的字段都禁止
+const REFUSE: &str = "REFUSE";
+
+/// 查询模板配置
+static TEMPLATES: LazyLock<RwLock<Templates>> = LazyLock::new(|| RwLock::new(Templates::default()));
+
+/// 加载 `restful.templates` 配置，标签须由字母、数字、`_`、`-` 组成，同一标签的版本不能重复
+pub fn init_templates(templates: &Templates) -> Result<(), String> {
+    for (index, template) in templates.requests.iter().enumerate() {
+        if template.tag.is_empty() || !template.tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            return Err(format!("template tag '{}' is not valid", template.tag));
+        }
+        if templates.requests[..index].iter().any(|other| other.tag == template.tag && other.version == template.version) {
+            return Err(format!("template {} version {} is duplicated", template.tag, template.version));
+        }
+        let structure = template.structure.as_object().ok_or_else(|| format!("template {}: structure expects an object", template.tag))?;
+        check_structure(&template.tag, structure)?;
+    }
+    *TEMPLATES.write().unwrap() = templates.clone();
+    Ok(())
+}
+
+/// 是否接受任意结构的查询: 未开启 strict，或角色不受限制
+pub fn is_free_form_allowed(auth: &Auth) -> bool {
+    let templates = TEMPLATES.read().unwrap();
+    !templates.strict || auth.role.as_ref().is_some_and(|role| templates.bypass_roles.contains(role))
+}
+
+/// 按标签查找模板并校验请求体，返回合并了模板固定值的 APIJSON 请求
+///
+/// 请求体中的 `version` 指定模板版本，未指定时使用最大的版本；标签或版本不存在时返回 404，不符合模板时返回 400
+pub fn apply_template(tag: &str, mut body_map: HashMap<String, serde_json::Value>) -> Result<HashMap<String, serde_json::Value>, (StatusCode, String)> {
+    let version = match body_map.remove("version") {
+        None => None,
+        Some(version) => match version.as_u64().and_then(|version| u32::try_from(version).ok()) {
+            Some(version) => Some(version),
+            None => return Err((StatusCode::BAD_REQUEST, "'version' expects a positive integer".to_string())),
+        },
+    };
+    let template = find_template(tag, version).ok_or_else(|| match version {
+        Some(version) => (StatusCode::NOT_FOUND, format!("template {} version {} not exists", tag, version)),
+        None => (StatusCode::NOT_FOUND, format!("template {} not exists", tag)),
+    })?;
+    let rules = template.structure.as_object().cloned().unwrap_or_default();
+    let body = body_map.into_iter().collect();
+    let request = apply_node("", &rules, &body).map_err(|err| (StatusCode::BAD_REQUEST, format!("template {}: {}", tag, err)))?;
+    Ok(request.into_iter().collect())
+}
+
+fn find_template(tag: &str, version: Option<u32>) -> Option<RequestTemplate> {
+    TEMPLATES.read().unwrap().requests.iter()
+        .filter(|template| template.tag == tag && version.is_none_or(|version| template.version == version))
+        .max_by_key(|template| template.version)
+        .cloned()
+}
+
+/// 模板中值为对象的命名空间 `xxx[]` 及表 `schema.Table` 为子节点，其余为字段
+fn is_node(key: &str, value: &serde_json::Value) -> bool {
+    value.is_object() && (key.ends_with("[]") || split_table_key(key).is_ok())
+}
+
+/// 条件键对应的字段名: `password$` -> `password`，`attrs.color` -> `attrs`
+fn field_name(key: &str) -> &str {
+    let key = key.split('.').next().unwrap_or(key);
+    key.trim_end_matches(|c| "{}&|$~<>=!@".contains(c))
+}
+
+/// `@column`、`@order`、`@group`、`@having` 中引用的字段，按标识符拆分，函数名、别名也包含在内
+fn referenced_fields<'a>(key: &str, value: &'a serde_json::Value) -> Vec<&'a str> {
+    match (key, value.as_str()) {
+        ("@column" | "@order" | "@group" | "@having", Some(expr)) => expr
+            .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .filter(|field| !field.is_empty())
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// MUST、REFUSE 的字段列表，逗号分隔的字符串或字符串数组
+fn field_list(rules: &serde_json::Map<String, serde_json::Value>, keyword: &str) -> Result<Vec<String>, String> {
+    match rules.get(keyword) {
+        None => Ok(vec![]),
+        Some(serde_json::Value::String(fields)) => Ok(fields.split(',').map(str::trim).filter(|field| !field.is_empty()).map(str::to_string).collect()),
+        Some(serde_json::Value::Array(fields)) if fields.iter().all(serde_json::Value::is_string) => {
+            Ok(fields.iter().filter_map(serde_json::Value::as_str).map(str::to_string).collect())
+        }
+        Some(_) => Err(format!("'{}' expects a string or a string array", keyword)),
+    }
+}
+
+/// 加载时检查模板结构中的 MUST、REFUSE
+fn check_structure(path: &str, rules: &serde_json::Map<String, serde_json::Value>) -> Result<(), String> {
+    field_list(rules, MUST).and(field_list(rules, REFUSE)).map_err(|err| format!("{}: {}", path, err))?;
+    for (key, value) in rules.iter().filter(|(key, value)| is_node(key, value)) {
+        check_structure(&format!("{}/{}", path, key), value.as_object().unwrap())?;
+    }
+    Ok(())
+}
+
+/// 按模板节点校验请求节点:
+///
+/// * 请求中只能包含模板中的子节点，模板中有而请求中没有的子节点按空节点处理
+/// * MUST 中的字段必须出现，REFUSE 中的字段不能出现在条件及 `@column`、`@order`、`@group`、`@having` 中，`"REFUSE": "!"` 时只能出现 MUST 中的字段
+/// * 子查询 `"字段@": {...}` 只能由模板声明
+/// * 模板中的其余字段为固定值，覆盖请求中的同名字段
+fn apply_node(path: &str, rules: &serde_json::Map<String, serde_json::Value>, body: &serde_json::Map<String, serde_json::Value>) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    let node_path = |key: &str| if path.is_empty() { key.to_string() } else { format!("{}/{}", path, key) };
+    let node_err = |err: String| if path.is_empty() { err } else { format!("{}: {}", path, err) };
+    let must = field_list(rules, MUST).map_err(node_err)?;
+    let refuse = field_list(rules, REFUSE).map_err(node_err)?;
+    let refuse_all = refuse.iter().any(|field| field == "!");
+    if let Some(field) = must.iter().find(|field| !body.contains_key(*field)) {
+        return Err(node_err(format!("'{}' is required", field)));
+    }
+
+    let mut node = serde_json::Map::new();
+    for (key, value) in body {
+        match rules.get(key) {
+            Some(rule) if is_node(key, rule) => {
+                let child = value.as_object().ok_or_else(|| format!("{} expects an object", node_path(key)))?;
+                node.insert(key.clone(), serde_json::Value::Object(apply_node(&node_path(key), rule.as_object().unwrap(), child)?));
+            }
+            // 固定值，下面以模板中的值写入
+            Some(_) => {}
+            None if is_node(key, value) => return Err(format!("{} is not allowed by the template", node_path(key))),
+            // 子查询可以读取任意表，只能使用模板中声明的
+            None if key.ends_with('@') && value.is_object() => return Err(node_err(format!("subquery '{}' is not allowed by the template", key))),
+            None => {
+                let is_refused = |name: &str| refuse.iter().any(|field| field.eq_ignore_ascii_case(name));
+                let refused = if refuse_all {
+                    !must.contains(key)
+                } else {
+                    is_refused(field_name(key)) || referenced_fields(key, value).into_iter().any(is_refused)
+                };
+                if refused {
+                    return Err(node_err(format!("'{}' is not allowed", key)));
+                }
+                node.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    for (key, rule) in rules {
+        if key == MUST || key == REFUSE || node.contains_key(key) {
+            continue;
+        }
+        let value = match rule.as_object() {
+            Some(child_rules) if is_node(key, rule) => serde_json::Value::Object(apply_node(&node_path(key), child_rules, &serde_json::Map::new())?),
+            _ => rule.clone(),
+        };
+        node.insert(key.clone(), value);
+    }
+    Ok(node)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use common::yaml::{RequestTemplate, Templates};
+    use crate::template::{apply_template, init_templates, TEMPLATES};
+    use crate::verify::tests::lock_registries;
+
+    #[test]
+    fn test_apply_template() {
+        let _lock = lock_registries();
+        let template = |version: u32, structure: serde_json::Value| RequestTemplate { tag: "moment_list".to_string(), version, structure };
+        init_templates(&Templates {
+            strict: true,
+            bypass_roles: vec![],
+            requests: vec![
+                template(1, serde_json::json!({ "timeline.Moment": {} })),
+                template(2, serde_json::json!({
+                    "[]": {
+                        "count": 10,
+                        "timeline.Moment": { "MUST": "user_id", "REFUSE": "!", "@column": "id,content" },
+                        "timeline.User": { "REFUSE": "password", "id@": "[]/timeline.Moment/user_id" }
+                    }
+                })),
+            ],
+        }).unwrap();
+        let apply = |body: serde_json::Value| apply_template("moment_list", serde_json::from_value::<HashMap<String, serde_json::Value>>(body).unwrap());
+
+        // 固定值覆盖请求中的值，请求中没有的子节点按模板补全
+        let request = apply(serde_json::json!({ "[]": { "count": 1000, "page": 2, "timeline.Moment": { "user_id": 1 } } })).unwrap();
+        assert_eq!(serde_json::json!(request), serde_json::json!({
+            "[]": {
+                "count": 10,
+                "page": 2,
+                "timeline.Moment": { "user_id": 1, "@column": "id,content" },
+                "timeline.User": { "id@": "[]/timeline.Moment/user_id" }
+            }
+        }));
+        let request = apply(serde_json::json!({ "version": 1, "timeline.Moment": { "id": 1 } })).unwrap();
+        assert_eq!(serde_json::json!(request), serde_json::json!({ "timeline.Moment": { "id": 1 } }));
+
+        let err = |body: serde_json::Value| apply(body).unwrap_err().1;
+        assert_eq!(err(serde_json::json!({ "[]": { "timeline.Moment": {} } })), "template moment_list: []/timeline.Moment: 'user_id' is required");
+        assert_eq!(err(serde_json::json!({ "[]": { "timeline.Moment": { "user_id": 1, "content$": "%a%" } } })), "template moment_list: []/timeline.Moment: 'content$' is not allowed");
+        assert_eq!(err(serde_json::json!({ "[]": { "timeline.Moment": { "user_id": 1 }, "timeline.User": { "password$": "a%" } } })), "template moment_list: []/timeline.User: 'password$' is not allowed");
+        assert_eq!(err(serde_json::json!({ "[]": { "timeline.Moment": { "user_id": 1 } }, "ideabase.account": {} })), "template moment_list: ideabase.account is not allowed by the template");
+        // 功能键中引用的字段、子查询
+        assert_eq!(err(serde_json::json!({ "[]": { "timeline.Moment": { "user_id": 1 }, "timeline.User": { "@column": "id,Password:p" } } })), "template moment_list: []/timeline.User: '@column' is not allowed");
+        assert_eq!(err(serde_json::json!({ "[]": { "timeline.Moment": { "user_id": 1 }, "timeline.User": { "@order": "password-" } } })), "template moment_list: []/timeline.User: '@order' is not allowed");
+        assert_eq!(err(serde_json::json!({ "[]": { "timeline.Moment": { "user_id": 1 }, "timeline.User": { "id{}@": { "from": "ideabase.account", "ideabase.account": { "@column": "id" } } } } })), "template moment_list: []/timeline.User: subquery 'id{}@' is not allowed by the template");
+        assert!(apply(serde_json::json!({ "[]": { "timeline.Moment": { "user_id": 1 }, "timeline.User": { "@column": "id,name", "@order": "id-" } } })).is_ok());
+        assert_eq!(apply(serde_json::json!({ "version": 3 })).unwrap_err().0, http::StatusCode::NOT_FOUND);
+        assert_eq!(apply_template("other", HashMap::new()).unwrap_err().0, http::StatusCode::NOT_FOUND);
+        *TEMPLATES.write().unwrap() = Templates::default();
+    }
+}
@@ -0,0 +1,327 @@
+use database::TableMeta;
+use database::core::get_table;
+
+/// 聚合函数白名单
+pub const AGGREGATE_FUNCTIONS: &[&str] = &["count", "sum", "avg", "min", "max"];
+
+/// 查询列表达式，如 `count(id):cnt`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnExpr {
+    // 渲染后的SQL片段，如 `count(`id`) AS `cnt``
+    pub sql: String,
+    // 渲染后的聚合表达式，如 `count(`id`)`，普通列为None
+    pub aggregate: Option<String>,
+    // 别名
+    pub alias: Option<String>,
+    // 结果中的字段名: 别名、聚合表达式文本或字段名
+    pub name: String,
+}
+
+/// 判断是否为合法的SQL标识符: 字母或下划线开头，仅包含字母、数字、下划线
+pub fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => chars.all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        _ => false,
+    }
+}
+
+/// 使用反引号引用标识符
+pub fn quote_ident(name: &str) -> String {
+    format!("`{}`", name.replace('`', "``"))
+}
+
+/// 引用 `schema`.`table`
+pub fn quote_table(schema: &str, table: &str) -> String {
+    format!("{}.{}", quote_ident(schema), quote_ident(table))
+}
+
+/// 拆分 "schema.table" 键，两部分都必须是合法标识符
+pub fn split_table_key(table_key: &str) -> Result<(&str, &str), String> {
+    match table_key.split_once('.') {
+        Some((schema, table)) if is_identifier(schema) && is_identifier(table) => Ok((schema, table)),
+        Some(_) => Err(format!("'{}' is not a valid table name", table_key)),
+        None => Err(format!("{}'s schema should be specified", table_key)),
+    }
+}
+
+/// 解析 "schema.table" 键并从元数据中查找表
+pub fn resolve_table(table_key: &str) -> Result<TableMeta, String> {
+    let (schema, table) = split_table_key(table_key)?;
+    get_table(schema, table).ok_or_else(|| format!("table {} not exists", table_key))
+}
+
+/// 在表元数据中查找字段，返回表中定义的字段名(大小写不敏感)
+pub fn resolve_column(table: &TableMeta, column: &str) -> Result<String, String> {
+    if table.columns.contains_key(column) {
+        return Ok(column.to_string());
+    }
+    table.columns.keys()
+        .find(|name| name.eq_ignore_ascii_case(column))
+        .cloned()
+        .ok_or_else(|| format!("column '{}' not exists in {}.{}", column, table.schema, table.name))
+}
+
+/// 在表元数据中查找字段，并校验为 JSON 类型
+pub fn resolve_json_column(table: &TableMeta, column: &str) -> Result<String, String> {
+    let column = resolve_column(table, column)?;
+    if table.columns[&column].type_name.eq_ignore_ascii_case("json") {
+        Ok(column)
+    } else {
+        Err(format!("column '{}' is not a JSON column", column))
+    }
+}
+
+/// JSON 字段中的路径，如 `attrs.color` 为字段 `attrs` 中的 `$.color`
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath {
+    // JSON 字段名
+    pub column: String,
+    // MySQL JSON 路径，如 `$.color`、`$.tags[0]`
+    pub path: String,
+}
+
+impl JsonPath {
+    /// 取出路径上的值并去掉字符串的引号，用于比较
+    pub fn text_sql(&self) -> String {
+        format!("{}->>'{}'", quote_ident(&self.column), self.path)
+    }
+
+    /// 取出路径上的 JSON 值，保留值的类型
+    pub fn json_sql(&self) -> String {
+        format!("{}->'{}'", quote_ident(&self.column), self.path)
+    }
+}
+
+/// 解析 JSON 路径 `column.key.key[0]`: 字段须为 JSON 类型，键须为合法标识符，可以带数组下标
+pub fn resolve_json_path(table: &TableMeta, expr: &str) -> Result<JsonPath, String> {
+    let malformed = || format!("malformed JSON path '{}'", expr);
+    let (column, keys) = expr.split_once('.').ok_or_else(malformed)?;
+    let column = resolve_json_column(table, column)?;
+    let mut path = String::from("$");
+    for key in keys.split('.') {
+        let (name, indexes) = key.split_at(key.find('[').unwrap_or(key.len()));
+        if !is_identifier(name) || !is_array_indexes(indexes) {
+            return Err(malformed());
+        }
+        path.push('.');
+        path.push_str(key);
+    }
+    Ok(JsonPath { column, path })
+}
+
+/// 数组下标，如 `[0][1]`，可以为空
+fn is_array_indexes(indexes: &str) -> bool {
+    let mut rest = indexes;
+    while let Some(tail) = rest.strip_prefix('[') {
+        match tail.split_once(']') {
+            Some((index, tail)) if !index.is_empty() && index.bytes().all(|b| b.is_ascii_digit()) => rest = tail,
+            _ => return false,
+        }
+    }
+    rest.is_empty()
+}
+
+/// 校验写操作中的所有字段
+pub fn resolve_columns<'a>(table: &TableMeta, columns: impl Iterator<Item = &'a String>) -> Result<Vec<String>, String> {
+    columns.map(|column| resolve_column(table, column)).collect()
+}
+
+/// 解析聚合表达式: `count(*)`、`count(distinct id)`、`sum(price)`
+///
+/// 返回 (渲染后的SQL, 规范化的表达式文本)
+pub fn parse_aggregate(table: &TableMeta, expr: &str) -> Result<(String, String), String> {
+    let malformed = || format!("malformed aggregate '{}'", expr);
+    let (function, rest) = expr.split_once('(').ok_or_else(malformed)?;
+    let argument = rest.strip_suffix(')').ok_or_else(malformed)?.trim();
+    let function = function.trim().to_lowercase();
+    if !AGGREGATE_FUNCTIONS.contains(&function.as_str()) {
+        return Err(format!("function '{}' is not supported", function));
+    }
+
+    if argument == "*" {
+        return if function == "count" { Ok(("count(*)".to_string(), "count(*)".to_string())) } else { Err(malformed()) };
+    }
+    let (distinct, column) = match argument.split_once(char::is_whitespace) {
+        Some((keyword, column)) if keyword.eq_ignore_ascii_case("distinct") => (true, column.trim()),
+        Some(_) => return Err(malformed()),
+        None => (false, argument),
+    };
+    let column = resolve_column(table, column)?;
+    if distinct {
+        Ok((format!("{}(DISTINCT {})", function, quote_ident(&column)), format!("{}(DISTINCT {})", function, column)))
+    } else {
+        Ok((format!("{}({})", function, quote_ident(&column)), format!("{}({})", function, column)))
+    }
+}
+
+/// 解析 `@column` 中的一项: `column`、`column:alias`、`function(column):alias`、`json_column.key:alias`
+pub fn parse_column_expr(table: &TableMeta, item: &str) -> Result<ColumnExpr, String> {
+    let (expr, alias) = match item.rsplit_once(':') {
+        Some((expr, alias)) => (expr.trim(), Some(alias.trim())),
+        None => (item.trim(), None),
+    };
+    if let Some(alias) = alias.filter(|alias| !is_identifier(alias)) {
+        return Err(format!("alias '{}' is not a valid identifier", alias));
+    }
+
+    if expr.contains('(') {
+        // 未指定别名时以规范化的表达式文本作为结果字段名
+        let (aggregate, label) = parse_aggregate(table, expr)?;
+        let name = alias.unwrap_or(&label).to_string();
+        let sql = format!("{} AS {}", aggregate, quote_ident(&name));
+        return Ok(ColumnExpr { sql, aggregate: Some(aggregate), alias: alias.map(str::to_string), name });
+    }
+    if expr.contains('.') {
+        // JSON 路径，未指定别名时以路径文本作为结果字段名
+        let path = resolve_json_path(table, expr)?;
+        let name = alias.unwrap_or(expr).to_string();
+        let sql = format!("{} AS {}", path.json_sql(), quote_ident(&name));
+        return Ok(ColumnExpr { sql, aggregate: None, alias: alias.map(str::to_string), name });
+    }
+    let column = resolve_column(table, expr)?;
+    match alias {
+        Some(alias) => Ok(ColumnExpr { sql: format!("{} AS {}", quote_ident(&column), quote_ident(alias)), aggregate: None, alias: Some(alias.to_string()), name: alias.to_string() }),
+        None => Ok(ColumnExpr { sql: quote_ident(&column), aggregate: None, alias: None, name: column }),
+    }
+}
+
+/// 排序项
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderItem {
+    // 字段名或 `@column` 中的别名
+    pub column: String,
+    // 是否降序
+    pub desc: bool,
+    // 是否为别名
+    pub alias: bool,
+}
+
+impl OrderItem {
+    pub fn to_sql(&self) -> String {
+        format!("{} {}", quote_ident(&self.column), if self.desc { "DESC" } else { "ASC" })
+    }
+}
+
+/// 解析 `@order`: `"id desc,name"` 或 APIJSON 风格的 `"id-,name+"`，字段可以是 `@column` 中的别名
+pub fn parse_order_items(table: &TableMeta, aliases: &[String], order: &str) -> Result<Vec<OrderItem>, String> {
+    let mut items = Vec::new();
+    for item in order.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+        let malformed = || format!("malformed @order item '{}'", item);
+        let (column, desc) = if let Some(column) = item.strip_suffix('-') {
+            (column, true)
+        } else if let Some(column) = item.strip_suffix('+') {
+            (column, false)
+        } else {
+            let mut parts = item.split_whitespace();
+            let column = parts.next().ok_or_else(malformed)?;
+            let desc = match parts.next() {
+                None => false,
+                Some(direction) if direction.eq_ignore_ascii_case("asc") => false,
+                Some(direction) if direction.eq_ignore_ascii_case("desc") => true,
+                Some(_) => return Err(malformed()),
+            };
+            if parts.next().is_some() {
+                return Err(malformed());
+            }
+            (column, desc)
+        };
+
+        let item = match aliases.iter().find(|alias| alias.as_str() == column) {
+            Some(alias) => OrderItem { column: alias.clone(), desc, alias: true },
+            None => OrderItem { column: resolve_column(table, column)?, desc, alias: false },
+        };
+        items.push(item);
+    }
+    if items.is_empty() {
+        return Err("@order is empty".to_string());
+    }
+    Ok(items)
+}
+
+/// 解析 `@order` 并渲染为 ORDER BY 子句，如 "`id` DESC,`name` ASC"
+pub fn parse_order(table: &TableMeta, aliases: &[String], order: &str) -> Result<String, String> {
+    let items = parse_order_items(table, aliases, order)?;
+    Ok(items.iter().map(OrderItem::to_sql).collect::<Vec<_>>().join(","))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::{Mutex, MutexGuard};
+    use database::TableMeta;
+    use crate::verify::{parse_aggregate, parse_column_expr, parse_order, resolve_json_path, split_table_key};
+
+    pub(crate) fn test_table() -> TableMeta {
+        test_table_with("ecommerce", "product", &[
+            ("id", "bigint"),
+            ("category", "varchar(32)"),
+            ("name", "varchar(64)"),
+            ("status", "varchar(16)"),
+            ("price", "decimal(10,2)"),
+            ("like_cnt", "int"),
+            ("deleted_at", "datetime"),
+            ("attrs", "json"),
+        ])
+    }
+
+    static REGISTRY_LOCK: Mutex<()> = Mutex::new(());
+
+    /// 改写或读取全局注册表(暴露、权限、策略、模板)的测试须先持有此锁，避免并行测试互相覆盖
+    pub(crate) fn lock_registries() -> MutexGuard<'static, ()> {
+        REGISTRY_LOCK.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// 由字段名、类型生成表元数据，`id` 为主键
+    pub(crate) fn test_table_with(schema: &str, name: &str, columns: &[(&str, &str)]) -> TableMeta {
+        let columns: serde_json::Map<String, serde_json::Value> = columns.iter().map(|(column, type_name)| (column.to_string(), serde_json::json!({
+            "field": column, "type_name": type_name, "null": "YES", "default": null, "comment": "", "key": if *column == "id" { "PRI" } else { "" }, "extra": ""
+        }))).collect();
+        serde_json::from_value(serde_json::json!({ "schema": schema, "name": name, "columns": columns, "comment": null })).unwrap()
+    }
+
+    #[test]
+    fn test_parse_column_expr() {
+        let table = test_table();
+        assert_eq!(parse_column_expr(&table, "category").unwrap().sql, "`category`");
+        assert_eq!(parse_column_expr(&table, "Category:c").unwrap().sql, "`category` AS `c`");
+        assert_eq!(parse_column_expr(&table, "count(id):cnt").unwrap().sql, "count(`id`) AS `cnt`");
+        assert_eq!(parse_column_expr(&table, "SUM(price)").unwrap().sql, "sum(`price`) AS `sum(price)`");
+        assert_eq!(parse_aggregate(&table, "COUNT(distinct category)").unwrap().0, "count(DISTINCT `category`)");
+        assert_eq!(parse_aggregate(&table, "count(*)").unwrap().0, "count(*)");
+
+        assert!(parse_column_expr(&table, "password").is_err());
+        assert!(parse_column_expr(&table, "sleep(id)").is_err());
+        assert!(parse_column_expr(&table, "sum(*)").is_err());
+        assert!(parse_column_expr(&table, "count(id):c nt").is_err());
+        assert!(parse_column_expr(&table, "max(id) or 1").is_err());
+
+        assert_eq!(parse_column_expr(&table, "attrs.color:color").unwrap().sql, "`attrs`->'$.color' AS `color`");
+        assert_eq!(parse_column_expr(&table, "attrs.size.width").unwrap().name, "attrs.size.width");
+        assert_eq!(resolve_json_path(&table, "attrs.tags[0][1]").unwrap().path, "$.tags[0][1]");
+        assert!(resolve_json_path(&table, "name.color").is_err());
+        assert!(resolve_json_path(&table, "attrs.color'").is_err());
+        assert!(resolve_json_path(&table, "attrs.tags[a]").is_err());
+        assert!(resolve_json_path(&table, "attrs.").is_err());
+    }
+
+    #[test]
+    fn test_parse_order() {
+        let table = test_table();
+        let aliases = vec!["cnt".to_string()];
+        assert_eq!(parse_order(&table, &aliases, "id desc, name").unwrap(), "`id` DESC,`name` ASC");
+        assert_eq!(parse_order(&table, &aliases, "cnt-,price+").unwrap(), "`cnt` DESC,`price` ASC");
+
+        assert!(parse_order(&table, &aliases, "id; drop table x").is_err());
+        assert!(parse_order(&table, &aliases, "id desc limit 1").is_err());
+        assert!(parse_order(&table, &aliases, "rand()").is_err());
+        assert!(parse_order(&table, &aliases, "").is_err());
+    }
+
+    #[test]
+    fn test_split_table_key() {
+        assert_eq!(split_table_key("timeline.Moment").unwrap(), ("timeline", "Moment"));
+        assert!(split_table_key("Moment").is_err());
+        assert!(split_table_key("timeline.Moment;drop").is_err());
+        assert!(split_table_key("timeline.a.b").is_err());
+    }
+}
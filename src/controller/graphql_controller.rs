@@ -0,0 +1,43 @@
+use std::collections::HashMap;
+use actix_web::{post, web, Either, HttpRequest, HttpResponse, Responder};
+use http::StatusCode;
+
+use common::rpc::RpcResult;
+use restful::graphql::{execute_graphql, has_query_operation};
+use restful::template::is_free_form_allowed;
+use crate::controller::build_rpc_response;
+use crate::controller::rest_controller::{free_form_forbidden, request_auth};
+use crate::{G_DB, G_ENV};
+
+
+pub fn scope() -> actix_web::Scope {
+    web::scope("/graphql").service(graphql)
+}
+
+/// GraphQL 端点，schema 由表元数据生成
+///
+/// # 参数
+/// * `request_data` - `{"query": "...", "variables": {...}, "operationName": "..."}`
+///
+/// # 返回值
+/// 返回标准的 GraphQL 响应 `{"data": {...}, "errors": [...]}`；`restful.templates.strict` 时查询返回 403
+#[post("")]
+async fn graphql(req: HttpRequest, request_data: web::Json<serde_json::Value>) -> impl Responder {
+    if !G_ENV.restful.graphql.enabled {
+        return Either::Left(build_rpc_response(RpcResult::<HashMap<String, serde_json::Value>>{ code: StatusCode::NOT_FOUND, msg: Some("graphql is disabled".to_string()), payload: None }));
+    }
+    let auth = match request_auth(&req).await {
+        Ok(auth) => auth,
+        Err(rpc_result) => return Either::Left(build_rpc_response(rpc_result)),
+    };
+    // 只能通过模板查询时不接受 GraphQL 查询，mutation 与 REST 的写操作一致不受限制
+    let request_data = request_data.into_inner();
+    if !is_free_form_allowed(&auth) && has_query_operation(&request_data) {
+        return Either::Left(build_rpc_response(free_form_forbidden()));
+    }
+    let db = G_DB.get().unwrap();
+    match execute_graphql(db, &auth, request_data).await {
+        Ok(response) => Either::Right(HttpResponse::Ok().json(response)),
+        Err(err) => Either::Left(build_rpc_response(RpcResult::<HashMap<String, serde_json::Value>>{ code: StatusCode::BAD_REQUEST, msg: Some(err), payload: None })),
+    }
+}
@@ -1,6 +1,8 @@
 pub mod ai_rag_controller;
 pub mod rest_controller;
 pub mod account_controller;
+pub mod graphql_controller;
+pub mod openapi_controller;
 
 use actix_web::{get, http::StatusCode, web, HttpResponse, HttpResponseBuilder, Responder};
 use common::rpc::RpcResult;
@@ -37,6 +39,8 @@ pub fn register_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(web::scope("/api/v1")
         .service(account_controller::scope())
         .service(rest_controller::scope())
+        .service(graphql_controller::scope())
+        .service(openapi_controller::openapi)
         .service(ai_rag_controller::scope()))
     ;
 }
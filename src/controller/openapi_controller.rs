@@ -0,0 +1,84 @@
+use actix_web::{get, HttpResponse, Responder};
+use serde_json::json;
+
+use restful::openapi::openapi_document;
+use crate::G_ENV;
+
+
+/// OpenAPI 3.1 文档: 表相关的接口由元数据生成，另加账号、AI、GraphQL 接口
+#[get("/openapi.json")]
+pub async fn openapi() -> impl Responder {
+    let mut document = openapi_document(&G_ENV.database.decode);
+    if let Some(paths) = document["paths"].as_object_mut() {
+        paths.extend(static_paths().as_object().cloned().unwrap_or_default());
+        if !G_ENV.restful.graphql.enabled {
+            paths.remove("/api/v1/graphql");
+        }
+    }
+    HttpResponse::Ok().json(document)
+}
+
+fn json_body(schema: serde_json::Value) -> serde_json::Value {
+    json!({ "required": true, "content": { "application/json": { "schema": schema } } })
+}
+
+fn json_response(description: &str, schema: serde_json::Value) -> serde_json::Value {
+    json!({ "description": description, "content": { "application/json": { "schema": schema } } })
+}
+
+/// 非元数据生成的接口
+fn static_paths() -> serde_json::Value {
+    let account = json!({ "type": "object", "properties": { "email": { "type": "string", "format": "email" }, "password": { "type": "string" } }, "required": ["email", "password"] });
+    let conversation = json!({ "type": "object", "properties": { "collection": { "type": "string" }, "message": { "type": "string" } }, "required": ["collection", "message"] });
+    let error = json!({ "$ref": "#/components/responses/Error" });
+    json!({
+        "/api/v1/auth/logon.json": { "post": {
+            "tags": ["auth"], "summary": "登录",
+            "requestBody": json_body(account.clone()),
+            "responses": { "200": json_response("JWT 令牌", json!({ "type": "object", "properties": { "token": { "type": "string" } } })), "400": error, "401": error },
+        } },
+        "/api/v1/auth/account.json": { "post": {
+            "tags": ["auth"], "summary": "注册账号",
+            "requestBody": json_body(account),
+            "responses": { "200": json_response("账号 id", json!({ "type": "integer", "format": "int64" })), "400": error },
+        } },
+        "/api/v1/auth/account/api-key.json": { "post": {
+            "tags": ["auth"], "summary": "生成 api_key",
+            "security": [{ "bearerAuth": [] }],
+            "responses": { "200": json_response("api_key", json!({ "type": "object", "properties": { "api_key": { "type": "string" } } })), "401": error },
+        } },
+        "/api/v1/rest/export.{format}": { "post": {
+            "tags": ["rest"], "summary": "流式导出查询结果，请求体与 get.json 相同，只能包含一个表节点",
+            "security": [{}, { "bearerAuth": [] }, { "apiKey": [] }],
+            "parameters": [{ "name": "format", "in": "path", "required": true, "schema": { "type": "string", "enum": ["csv", "ndjson", "xlsx"] } }],
+            "requestBody": json_body(json!({ "type": "object" })),
+            "responses": {
+                "200": { "description": "导出文件", "content": { "text/csv": {}, "application/x-ndjson": {}, "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet": {} } },
+                "400": error, "403": error,
+            },
+        } },
+        "/api/v1/rest/get/{tag}.json": { "post": {
+            "tags": ["rest"], "summary": "按标签调用查询模板，请求体须符合模板的结构",
+            "security": [{}, { "bearerAuth": [] }, { "apiKey": [] }],
+            "parameters": [{ "name": "tag", "in": "path", "required": true, "schema": { "type": "string" } }],
+            "requestBody": json_body(json!({ "type": "object", "properties": { "version": { "type": "integer" } } })),
+            "responses": { "200": json_response("查询结果", json!({ "type": "object" })), "400": error, "403": error, "404": error },
+        } },
+        "/api/v1/ai/conversation.json": { "post": {
+            "tags": ["ai"], "summary": "基于知识库的对话",
+            "requestBody": json_body(conversation.clone()),
+            "responses": { "200": json_response("回答", json!({ "type": "string" })), "500": error },
+        } },
+        "/api/v1/ai/rag/recall.json": { "post": {
+            "tags": ["ai"], "summary": "知识库相似度召回",
+            "requestBody": json_body(conversation),
+            "responses": { "200": json_response("召回的文档", json!({ "type": "array", "items": { "type": "object" } })), "500": error },
+        } },
+        "/api/v1/graphql": { "post": {
+            "tags": ["graphql"], "summary": "GraphQL 查询",
+            "security": [{}, { "bearerAuth": [] }, { "apiKey": [] }],
+            "requestBody": json_body(json!({ "type": "object", "properties": { "query": { "type": "string" }, "variables": { "type": "object" }, "operationName": { "type": "string" } }, "required": ["query"] })),
+            "responses": { "200": json_response("GraphQL 响应", json!({ "type": "object", "properties": { "data": {}, "errors": { "type": "array", "items": { "type": "object" } } } })) },
+        } },
+    })
+}
@@ -1,20 +1,30 @@
 use std::collections::HashMap;
-use actix_web::{get, post, web, Responder};
+use actix_web::{get, post, route, web, Either, FromRequest, HttpRequest, HttpResponse, HttpResponseBuilder, Responder};
 use http::StatusCode;
 
 use common::rpc::RpcResult;
-use database::core::{get_table, get_table_name_list};
+use database::core::{get_table_name_list, meta_version};
 use restful::handler::delete::handle_delete;
 use restful::handler::get::handle_get;
 use restful::handler::head::handle_head;
 use restful::handler::post::handle_post;
 use restful::handler::put::handle_put;
+use restful::handler::resource::{to_apijson_request, to_resource_response};
+use restful::access::check_access;
+use restful::export::{export_stream, prepare_export, ExportFormat};
+use restful::exposure::{is_table_visible, visible_table};
+use restful::graphql::init_graphql;
+use restful::policy::{init_policies, Auth};
+use restful::template::{apply_template, is_free_form_allowed};
 use crate::controller::build_rpc_response;
-use crate::G_DB;
+use crate::global::jwt::JwtToken;
+use crate::service::model::account::{Account, Role};
+use crate::{G_DB, G_ENV};
 
 
 pub fn scope() -> actix_web::Scope {
-    web::scope("/rest").service(curd).service(get_table_names).service(get_table_meta)
+    web::scope("/rest").service(export).service(curd).service(get_by_template).service(get_table_names).service(get_table_meta).service(reload_meta)
+        .service(resource).service(resource_item)
 }
 
 /// 处理CRUD操作的REST API端点
@@ -27,59 +37,220 @@ pub fn scope() -> actix_web::Scope {
 /// # 返回值
 /// 返回JSON格式的响应数据，包含操作结果或错误信息
 #[post("/{method}.json")]
-async fn curd(params: web::Path<String>, request_data: web::Json<HashMap<String, serde_json::Value>>) -> impl Responder {
+async fn curd(req: HttpRequest, params: web::Path<String>, request_data: web::Json<HashMap<String, serde_json::Value>>) -> impl Responder {
     let method = params.into_inner();
     let request_data = request_data.into_inner();
-    let rpc_result: RpcResult<HashMap<String, serde_json::Value>>;
-    match method.as_str() {
-        "head" => {
-            let db = G_DB.get().unwrap();
-            rpc_result = handle_head(db, request_data).await;
+    let auth = match request_auth(&req).await {
+        Ok(auth) => auth,
+        Err(rpc_result) => return build_rpc_response(rpc_result),
+    };
+    if matches!(method.as_str(), "get" | "head") && !is_free_form_allowed(&auth) {
+        return build_rpc_response(free_form_forbidden());
+    }
+    build_rpc_response(dispatch(&method, request_data, &auth).await)
+}
+
+/// 按标签调用查询模板，请求体须符合模板的结构，模板中的固定值覆盖请求中的同名字段
+///
+/// * `tag` - 模板标签，请求体中的 `version` 指定模板版本，未指定时使用最大的版本
+#[post("/get/{tag}.json")]
+async fn get_by_template(req: HttpRequest, params: web::Path<String>, request_data: web::Json<HashMap<String, serde_json::Value>>) -> impl Responder {
+    let auth = match request_auth(&req).await {
+        Ok(auth) => auth,
+        Err(rpc_result) => return build_rpc_response(rpc_result),
+    };
+    match apply_template(&params.into_inner(), request_data.into_inner()) {
+        Ok(request_data) => build_rpc_response(dispatch("get", request_data, &auth).await),
+        Err((code, err)) => build_rpc_response(RpcResult{ code, msg: Some(err), payload: None }),
+    }
+}
+
+/// 开启 `restful.templates.strict` 后，任意结构的查询返回 403
+pub(crate) fn free_form_forbidden() -> RpcResult<HashMap<String, serde_json::Value>> {
+    RpcResult{ code: StatusCode::FORBIDDEN, msg: Some("free-form queries are disabled, use /get/{tag}.json".to_string()), payload: None }
+}
+
+/// 流式导出查询结果，请求体与 `get.json` 相同，只能包含一个表节点
+///
+/// * `format` - 导出格式: csv(表头取自 `@column`)、ndjson、xlsx
+#[post("/export.{format}")]
+async fn export(req: HttpRequest, params: web::Path<String>, request_data: web::Json<HashMap<String, serde_json::Value>>) -> impl Responder {
+    let error = |code: StatusCode, err: String| Either::Left(build_rpc_response(RpcResult::<serde_json::Value>{ code, msg: Some(err), payload: None }));
+    let format = match ExportFormat::from_extension(&params.into_inner()) {
+        Ok(format) => format,
+        Err(err) => return error(StatusCode::NOT_FOUND, err),
+    };
+    let request_data = request_data.into_inner();
+    let auth = match request_auth(&req).await {
+        Ok(auth) => auth,
+        Err(rpc_result) => return error(rpc_result.code, rpc_result.msg.unwrap_or_default()),
+    };
+    if !is_free_form_allowed(&auth) {
+        return error(StatusCode::FORBIDDEN, free_form_forbidden().msg.unwrap_or_default());
+    }
+    if let Err(denied) = check_access("get", &auth, &request_data) {
+        let mut err_msgs: Vec<String> = denied.into_values().filter_map(|err| err.as_str().map(str::to_string)).collect();
+        err_msgs.sort();
+        return error(StatusCode::FORBIDDEN, err_msgs.join("; "));
+    }
+    let query = match prepare_export(&request_data, format, &auth) {
+        Ok(query) => query,
+        Err(err) => return error(StatusCode::BAD_REQUEST, err),
+    };
+    let disposition = format!("attachment; filename=\"{}.{}\"", query.table, format.extension());
+    Either::Right(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header(("Content-Disposition", disposition))
+        .streaming(export_stream::<web::Bytes>(G_DB.get().unwrap(), query, format)))
+}
+
+/// PostgREST 风格的资源端点，转换为 APIJSON 请求后与 `/{method}.json` 的处理一致
+///
+/// * `GET /{schema}/{table}?status=eq.paid&price=gt.10&order=id.desc&limit=20&select=id,name` - 列表
+/// * `POST /{schema}/{table}` - 新增
+/// * `PATCH`、`DELETE /{schema}/{table}?id=eq.1` - 按 id 更新、删除，删除另支持 `id=in.(1,2)`
+#[route("/{schema}/{table}", method = "GET", method = "POST", method = "PATCH", method = "DELETE")]
+async fn resource(req: HttpRequest, params: web::Path<(String, String)>, query: web::Query<Vec<(String, String)>>, body: web::Bytes) -> impl Responder {
+    let (schema, table) = params.into_inner();
+    resource_response(&req, &schema, &table, None, &query, &body).await
+}
+
+/// PostgREST 风格的单条资源端点: `GET`、`PATCH`、`DELETE /{schema}/{table}/{id}`
+#[route("/{schema}/{table}/{id}", method = "GET", method = "PATCH", method = "DELETE")]
+async fn resource_item(req: HttpRequest, params: web::Path<(String, String, String)>, query: web::Query<Vec<(String, String)>>, body: web::Bytes) -> impl Responder {
+    let (schema, table, id) = params.into_inner();
+    resource_response(&req, &schema, &table, Some(&id), &query, &body).await
+}
+
+async fn resource_response(req: &HttpRequest, schema: &str, table: &str, id: Option<&str>, query: &[(String, String)], body: &[u8]) -> impl Responder + use<> {
+    let error = |code: StatusCode, err: Option<String>| Either::Left(build_rpc_response(RpcResult::<serde_json::Value>{ code, msg: err, payload: None }));
+    let auth = match request_auth(req).await {
+        Ok(auth) => auth,
+        Err(rpc_result) => return error(rpc_result.code, rpc_result.msg),
+    };
+    if req.method() == actix_web::http::Method::GET && !is_free_form_allowed(&auth) {
+        return error(StatusCode::FORBIDDEN, free_form_forbidden().msg);
+    }
+    let body = match body.is_empty() {
+        true => None,
+        false => match serde_json::from_slice(body) {
+            Ok(body) => Some(body),
+            Err(err) => return error(StatusCode::BAD_REQUEST, Some(format!("malformed json body: {}", err))),
         },
+    };
+    let request = match to_apijson_request(req.method().as_str(), &format!("{}.{}", schema, table), id, query, body) {
+        Ok(request) => request,
+        Err(err) => return error(StatusCode::BAD_REQUEST, Some(err)),
+    };
+    let rpc_result = dispatch(request.method, request.body_map.clone(), &auth).await;
+    let rpc_result = to_resource_response(&request, rpc_result);
+    match (rpc_result.code.is_success(), rpc_result.payload) {
+        (true, Some(payload)) => Either::Right(HttpResponseBuilder::new(rpc_result.code).json(payload)),
+        _ => error(rpc_result.code, rpc_result.msg),
+    }
+}
+
+/// 检查角色权限后按方法分发到对应的处理器
+async fn dispatch(method: &str, request_data: HashMap<String, serde_json::Value>, auth: &Auth) -> RpcResult<HashMap<String, serde_json::Value>> {
+    // 按表、方法检查角色权限
+    if let Err(denied) = check_access(method, auth, &request_data) {
+        let mut err_msgs: Vec<String> = denied.into_values().filter_map(|err| err.as_str().map(str::to_string)).collect();
+        err_msgs.sort();
+        return RpcResult{ code: StatusCode::FORBIDDEN, msg: Some(err_msgs.join("; ")), payload: None };
+    }
+    let db = G_DB.get().unwrap();
+    match method {
+        "head" => handle_head(db, request_data, auth).await,
         "get" => {
-            let db = G_DB.get().unwrap();
-            rpc_result = handle_get(db, request_data).await;
-        }
-        "put" => {
-            let db = G_DB.get().unwrap();
-            rpc_result = handle_put(db, request_data).await;
-        }
-        "post" => {
-            let db = G_DB.get().unwrap();
-            rpc_result = handle_post(db, request_data).await;
-        }
-        "delete" => {
-            let db = G_DB.get().unwrap();
-            rpc_result = handle_delete(db, request_data).await;
+            let explain_allowed = request_data.contains_key("@explain") && is_explain_allowed(auth);
+            handle_get(db, request_data, auth, explain_allowed).await
         }
+        "put" => handle_put(db, request_data, auth).await,
+        "post" => handle_post(db, request_data, auth).await,
+        "delete" => handle_delete(db, request_data, auth).await,
         _ => {
             let err_msg = format!("unknown method: {}", method);
-            rpc_result = RpcResult{ code: StatusCode::BAD_REQUEST, msg: Some(err_msg), payload: None };
+            RpcResult{ code: StatusCode::BAD_REQUEST, msg: Some(err_msg), payload: None }
         }
     }
-    build_rpc_response(rpc_result)
+}
+
+/// 请求者身份: 取自 `Authentication` 令牌或 `X-API-Key`，都未携带时为匿名，令牌或 api_key 无效时返回 401
+pub(crate) async fn request_auth(req: &HttpRequest) -> Result<Auth, RpcResult<HashMap<String, serde_json::Value>>> {
+    let unauthorized = || RpcResult{ code: StatusCode::UNAUTHORIZED, msg: Some("Unauthorized".to_string()), payload: None };
+    if req.headers().contains_key("Authentication") {
+        return match JwtToken::extract(req).await {
+            Ok(token) => Ok(Auth { sub: Some(token.sub), role: Some(token.role) }),
+            Err(_) => Err(unauthorized()),
+        };
+    }
+    match req.headers().get("X-API-Key").map(|key| key.to_str()) {
+        Some(Ok(api_key)) if !api_key.is_empty() => match Account::fetch_by_api_key(G_DB.get().unwrap(), api_key).await {
+            Ok(account) => Ok(Auth { sub: Some(account.id), role: Some(account.role) }),
+            Err(err) => {
+                log::error!("api_key verify error, {:?}", err);
+                Err(unauthorized())
+            }
+        },
+        Some(_) => Err(unauthorized()),
+        None => Ok(Auth::default()),
+    }
+}
+
+/// 是否允许 @explain: 配置开启，且未限制角色或当前用户的角色在允许列表中
+fn is_explain_allowed(auth: &Auth) -> bool {
+    let explain = &G_ENV.restful.explain;
+    if !explain.enabled { return false; }
+    if explain.roles.is_empty() { return true; }
+    auth.role.as_ref().is_some_and(|role| explain.roles.contains(role))
 }
 
 #[get("/{schema}/tables.json")]
 async fn get_table_names(schema: web::Path<String>) -> impl Responder {
     let schema = schema.into_inner();
-    let table_name_map = get_table_name_list(&schema);
+    // 隐藏的表不返回
+    let mut table_name_map = get_table_name_list(&schema);
+    table_name_map.retain(|table, _| is_table_visible(&schema, table));
     build_rpc_response(RpcResult{ code: StatusCode::OK, msg: None, payload: Some(table_name_map)})
 }
 
 #[get("/{schema}/{table}.json")]
 async fn get_table_meta(params: web::Path<(String, String)>) -> impl Responder {
     let (schema, table) = params.into_inner();
-    let table_mata_opt = get_table(&schema, &table);
+    // 隐藏的表视为不存在，不返回隐藏字段
+    let table_mata_opt = visible_table(&format!("{}.{}", schema, table));
     let mut rpc_result = RpcResult{ code: StatusCode::OK, msg: None, payload: None };
     match table_mata_opt {
-        Some(table_mata) => {
+        Ok(table_mata) => {
             rpc_result.payload = Some(table_mata);
         }
-        None => {
+        Err(_) => {
             rpc_result.code = StatusCode::BAD_REQUEST;
             rpc_result.msg = Some("table not found".to_string());
         }
     }
     build_rpc_response(rpc_result)
 }
+
+/// 重新加载库、表元数据，仅管理员可用，建表或表结构变更后调用
+///
+/// 行级安全策略、GraphQL schema 随之重新生成，OpenAPI 文档在下次请求时重新生成
+#[post("/meta/reload.json")]
+async fn reload_meta(req: HttpRequest) -> impl Responder {
+    let auth = match request_auth(&req).await {
+        Ok(auth) => auth,
+        Err(rpc_result) => return build_rpc_response(rpc_result),
+    };
+    if auth.role != Some(Role::Admin.to_string()) {
+        return build_rpc_response(RpcResult{ code: StatusCode::FORBIDDEN, msg: Some("Forbidden".to_string()), payload: None });
+    }
+    let db = G_DB.get().unwrap();
+    if let Err(err) = db.reload_meta().await {
+        return build_rpc_response(RpcResult{ code: StatusCode::INTERNAL_SERVER_ERROR, msg: Some(format!("metadata reload error: {}", err)), payload: None });
+    }
+    if let Err(err) = init_policies(&G_ENV.restful.rls).and_then(|_| init_graphql(&G_ENV.restful.graphql)) {
+        return build_rpc_response(RpcResult{ code: StatusCode::INTERNAL_SERVER_ERROR, msg: Some(err), payload: None });
+    }
+    let payload = HashMap::from([("version".to_string(), serde_json::json!(meta_version()))]);
+    build_rpc_response(RpcResult{ code: StatusCode::OK, msg: None, payload: Some(payload) })
+}
@@ -22,8 +22,20 @@ async fn main() -> std::io::Result<()> {
 
     // 数据源
     if let Ok(mysql_url) = std::env::var("MYSQL_URL") {
-        let db_conn = init_datasource_conn(&mysql_url).await.expect("datasource init error");
+        let db_conn = init_datasource_conn(&mysql_url, &G_ENV.database.decode).await.expect("datasource init error");
         G_DB.set(db_conn).unwrap();
+        // 对外暴露的库、表、字段
+        restful::exposure::init_exposure(&G_ENV.restful.exposure);
+        // 按表、方法的角色权限
+        restful::access::init_access(&G_ENV.restful.access);
+        // 查询开销限制
+        restful::limits::init_limits(&G_ENV.restful.limits);
+        // 查询模板
+        restful::template::init_templates(&G_ENV.restful.templates).expect("request template init error");
+        // 行级安全策略，依赖表元数据
+        restful::policy::init_policies(&G_ENV.restful.rls).expect("rls policy init error");
+        // GraphQL schema，依赖表元数据及暴露配置
+        restful::graphql::init_graphql(&G_ENV.restful.graphql).expect("graphql schema init error");
         
         // 实时数据
         // realtime::init_mysql_binlog_listener(&mysql_url);
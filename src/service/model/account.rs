@@ -73,7 +73,7 @@ impl Account {
         // 构建完整SQL语句
         let create_sql = format!("INSERT INTO `{IB_SCHEMA_NAME}`.`account` ({}) VALUES ({})", columns.join(", "), values.join(", "));
         log::info!("account.create.SQL: {}", create_sql);
-        db_conn.insert(&create_sql).await.map(|_| account_id)
+        db_conn.insert(&create_sql, vec![]).await.map(|_| account_id)
     }
 
     pub async fn update(db_conn: &DBConn, account_id: i64, dto: &AccountDTO) -> Result<i64, Error> {
@@ -106,20 +106,27 @@ impl Account {
         log::info!("account.update.SQL: {}", update_sql);
         
         // 执行更新并处理结果
-        db_conn.update(&update_sql).await.map(|cnt| if cnt > 0 { account_id } else { -1 })
+        db_conn.update(&update_sql, vec![]).await.map(|cnt| if cnt > 0 { account_id } else { -1 })
     }
 
     pub async fn fetch_by_id(db_conn: &DBConn, account_id: i64) -> Result<Account, Error> {
         let select_sql = format!("SELECT * FROM `{IB_SCHEMA_NAME}`.`account` WHERE id = ?");
         log::info!("account.fetch.SQL: {}", select_sql);
-        let fetch_result = db_conn.query_one(&select_sql, vec![account_id.to_string()]).await?;
+        let fetch_result = db_conn.query_one(&select_sql, vec![account_id.into()]).await?;
         let record = fetch_result.ok_or(Error::RowNotFound)?;
         Ok(Self::from_record(&record))
     }
 
     pub async fn fetch_by_email(db_conn: &DBConn, email: &str) -> Result<Account, Error> {
         let select_sql = format!("SELECT * FROM `{IB_SCHEMA_NAME}`.`account` WHERE email = ?");
-        let fetch_result = db_conn.query_one(&select_sql, vec![email.to_string()]).await?;
+        let fetch_result = db_conn.query_one(&select_sql, vec![email.into()]).await?;
+        let record = fetch_result.ok_or(Error::RowNotFound)?;
+        Ok(Self::from_record(&record))
+    }
+
+    pub async fn fetch_by_api_key(db_conn: &DBConn, api_key: &str) -> Result<Account, Error> {
+        let select_sql = format!("SELECT * FROM `{IB_SCHEMA_NAME}`.`account` WHERE api_key = ?");
+        let fetch_result = db_conn.query_one(&select_sql, vec![api_key.into()]).await?;
         let record = fetch_result.ok_or(Error::RowNotFound)?;
         Ok(Self::from_record(&record))
     }
@@ -127,7 +134,7 @@ impl Account {
     pub async fn count_by_email(db_conn: &DBConn, email: &str) -> Result<i64, Error> {
         let count_sql = format!("SELECT count(1) FROM `{IB_SCHEMA_NAME}`.`account` WHERE email = ?");
         log::info!("account.count_by_email.SQL: {}", count_sql);
-        let cnt = db_conn.count(&count_sql, vec![email.to_string()]).await?;
+        let cnt = db_conn.count(&count_sql, vec![email.into()]).await?;
         Ok(cnt)
     }
 